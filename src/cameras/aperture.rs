@@ -0,0 +1,137 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use image::{ImageResult, RgbImage};
+
+use crate::utils::color_utils::luminance;
+use crate::utils::distribution_utils::Distribution2D;
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::random_in_unit_disk;
+use crate::vectors::vec3::Vec3;
+
+// Shape of the lens opening, which out of focus highlights take on
+#[derive(Clone)]
+pub enum Aperture {
+    Disk,
+    // Regular polygon inscribed in the lens disk, rotated by rotation degrees
+    Polygon { blades: usize, rotation: f64 },
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    // Point on the aperture, scaled so that the lens disk has radius one
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Aperture::Disk => random_in_unit_disk(),
+            Aperture::Polygon { blades, rotation } if *blades >= 3 => {
+                // Pick one of the triangles between the center and a side, then a
+                // uniform point inside it
+                let blade = (random_f64() * *blades as f64) as usize % blades;
+                let angle = |i: usize| rotation.to_radians() + 2.0 * PI * i as f64 / *blades as f64;
+                let corner = |angle: f64| Vec3::new_with_values(angle.cos(), angle.sin(), 0.0);
+                let (a, b) = (corner(angle(blade)), corner(angle(blade + 1)));
+                let (mut x, mut y) = (random_f64(), random_f64());
+                if x + y > 1.0 {
+                    x = 1.0 - x;
+                    y = 1.0 - y;
+                }
+                x * a + y * b
+            }
+            Aperture::Polygon { .. } => random_in_unit_disk(),
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+// Custom aperture drawn as an image, bright where light passes. The image is centered
+// on the lens with its longer side spanning the lens diameter.
+pub struct ApertureMask {
+    distribution: Distribution2D,
+    extent: (f64, f64),
+}
+
+impl ApertureMask {
+    pub fn new(image: &RgbImage) -> Self {
+        let (width, height) = image.dimensions();
+        let transmission = image
+            .pixels()
+            .map(|pixel| {
+                let color = Vec3::new_with_values(
+                    pixel[0] as f64 / 255.0,
+                    pixel[1] as f64 / 255.0,
+                    pixel[2] as f64 / 255.0,
+                );
+                luminance(&color)
+            })
+            .collect::<Vec<f64>>();
+        let longest = width.max(height) as f64;
+        ApertureMask {
+            distribution: Distribution2D::new(&transmission, width as usize, height as usize),
+            extent: (width as f64 / longest, height as f64 / longest),
+        }
+    }
+
+    pub fn load(path: &str) -> ImageResult<Self> {
+        Ok(ApertureMask::new(&image::open(path)?.to_rgb8()))
+    }
+
+    fn sample(&self) -> Vec3 {
+        let (u, v, _) = self
+            .distribution
+            .sample_continuous(random_f64(), random_f64());
+        // Image rows run from the top down
+        Vec3::new_with_values(
+            (2.0 * u - 1.0) * self.extent.0,
+            (1.0 - 2.0 * v) * self.extent.1,
+            0.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::vec3_utils::cross;
+
+    #[test]
+    fn test_polygon_aperture() {
+        let aperture = Aperture::Polygon {
+            blades: 6,
+            rotation: 30.0,
+        };
+        let corners = (0..6)
+            .map(|i| {
+                let angle = (30.0 + 60.0 * i as f64).to_radians();
+                Vec3::new_with_values(angle.cos(), angle.sin(), 0.0)
+            })
+            .collect::<Vec<Vec3>>();
+        let mut farthest: f64 = 0.0;
+        for _ in 0..10000 {
+            let p = aperture.sample();
+            for i in 0..6 {
+                let (a, b) = (corners[i], corners[(i + 1) % 6]);
+                assert!(cross(&(b - a), &(p - a)).z() >= -1e-12);
+            }
+            farthest = farthest.max(p.length());
+        }
+        // Samples reach into the corners, beyond the inscribed circle
+        assert!(farthest > 0.95);
+    }
+
+    #[test]
+    fn test_mask_aperture() {
+        // Only the top right quadrant of a wide mask lets light through
+        let mut image = RgbImage::new(8, 4);
+        for x in 4..8 {
+            for y in 0..2 {
+                image.put_pixel(x, y, image::Rgb([255, 255, 255]));
+            }
+        }
+        let aperture = Aperture::Mask(Arc::new(ApertureMask::new(&image)));
+        for _ in 0..1000 {
+            let p = aperture.sample();
+            assert!((0.0..=1.0).contains(&p.x()));
+            assert!((0.0..=0.5).contains(&p.y()));
+        }
+    }
+}
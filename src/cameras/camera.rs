@@ -1,7 +1,13 @@
+use crate::cameras::aperture::Aperture;
+use crate::cameras::camera_model::CameraModel;
+use crate::cameras::exposure::Exposure;
+use crate::cameras::stereo_camera::StereoEye;
 use crate::rays::ray::Ray;
-use crate::utils::vec3_utils::{cross, random_in_unit_disk, unit_vector};
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::{cross, dot, unit_vector};
 use crate::vectors::vec3::Vec3;
 
+#[derive(Clone)]
 pub struct Camera {
     origin: Vec3,
     lower_left_corner: Vec3,
@@ -11,8 +17,19 @@ pub struct Camera {
     v: Vec3,
     w: Vec3,
     lens_radius: f64,
+    focus_dist: f64,
+    aperture: Aperture,
+    // Normal of a tilted plane of focus, not normalized
+    focus_normal: Option<Vec3>,
+    // Seconds the shutter stays open, with zero freezing motion
+    shutter: f64,
+    exposure: f64,
 }
 
+// Height of a full frame sensor in meters, which relates the field of view to a focal
+// length when exposure is physical
+const SENSOR_HEIGHT: f64 = 0.024;
+
 impl Camera {
     pub fn new(
         lookfrom: Vec3,
@@ -49,18 +66,178 @@ impl Camera {
             v,
             w,
             lens_radius,
+            focus_dist,
+            aperture: Aperture::Disk,
+            focus_normal: None,
+            shutter: 0.0,
+            exposure: 1.0,
+        }
+    }
+
+    // Shapes the lens opening, and with it the bokeh, within the aperture diameter
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    // Physical exposure, with scene units in meters. The f-number sets the aperture from
+    // the focal length of a full frame camera with this field of view, replacing the
+    // aperture passed to new, and the shutter speed sets the motion blur interval.
+    pub fn with_exposure(mut self, exposure: Exposure) -> Self {
+        let viewport_height = self.vertical.length() / self.focus_dist;
+        let focal_length = SENSOR_HEIGHT / viewport_height;
+        self.lens_radius = focal_length / (2.0 * exposure.f_number);
+        self.shutter = exposure.shutter_speed;
+        self.exposure = exposure.scale();
+        self
+    }
+
+    // Lens shift, in fractions of the image width and height. The view moves without
+    // turning the camera, so verticals stay parallel when shooting buildings.
+    pub fn with_shift(mut self, shift_x: f64, shift_y: f64) -> Self {
+        self.lower_left_corner += shift_x * self.horizontal + shift_y * self.vertical;
+        self
+    }
+
+    // Tilts the plane of focus about the point focus_dist ahead, by tilt degrees so that
+    // its top recedes and swing degrees so that its right side recedes. A tilted plane
+    // can follow a table top or a facade with the aperture wide open.
+    pub fn with_focus_tilt(mut self, tilt: f64, swing: f64) -> Self {
+        self.focus_normal = match tilt == 0.0 && swing == 0.0 {
+            true => None,
+            false => {
+                Some(self.w + swing.to_radians().tan() * self.u + tilt.to_radians().tan() * self.v)
+            }
+        };
+        self
+    }
+
+    fn shutter_time(&self) -> f64 {
+        random_f64() * self.shutter
+    }
+
+    // One eye of a stereo pair, moved half the interpupillary distance sideways. The
+    // views are shifted rather than turned inwards so that they line up at the
+    // convergence distance without vertical parallax.
+    pub fn stereo_eye(&self, eye: StereoEye, ipd: f64, convergence: f64) -> Camera {
+        let offset = match eye {
+            StereoEye::Left => -ipd / 2.0,
+            StereoEye::Right => ipd / 2.0,
+        };
+        let shift = offset * (1.0 - self.focus_dist / convergence);
+        Camera {
+            origin: self.origin + offset * self.u,
+            lower_left_corner: self.lower_left_corner + shift * self.u,
+            ..self.clone()
         }
     }
+}
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
+impl CameraModel for Camera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let rd = self.lens_radius * self.aperture.sample();
         let offset = self.u * rd.x() + self.v * rd.y();
 
-        Ray {
-            orig: self.origin + offset,
-            dir: self.lower_left_corner + s * self.horizontal + t * self.vertical
-                - self.origin
-                - offset,
+        let mut target = self.lower_left_corner + s * self.horizontal + t * self.vertical;
+        if let Some(normal) = self.focus_normal {
+            // Where the ray through the lens center meets the tilted plane of focus
+            let direction = target - self.origin;
+            let along = dot(&direction, &normal);
+            if along < 0.0 {
+                target = self.origin - self.focus_dist / along * direction;
+            }
         }
+
+        Some(Ray {
+            orig: self.origin + offset,
+            dir: target - self.origin - offset,
+            time: self.shutter_time(),
+        })
+    }
+
+    fn exposure(&self) -> f64 {
+        self.exposure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(aperture: f64) -> Camera {
+        Camera::new(
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            Vec3::new_with_values(0.0, 1.0, -1.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            60.0,
+            1.5,
+            aperture,
+            4.0,
+        )
+    }
+
+    #[test]
+    fn test_shift_keeps_verticals() {
+        let level = camera(0.0);
+        let shifted = camera(0.0).with_shift(0.1, 0.25);
+        let direction = |camera: &Camera, s, t| camera.get_ray(s, t).unwrap().direction();
+        assert!((direction(&shifted, 0.4, 0.5) - direction(&level, 0.5, 0.75)).length() < 1e-12);
+
+        // Rays in one column of the image stay in one vertical plane
+        let (low, high) = (direction(&shifted, 0.2, 0.0), direction(&shifted, 0.2, 1.0));
+        assert!((low.x() / low.z() - high.x() / high.z()).abs() < 1e-12);
+        assert!(low.y() < 0.0 && high.y() > 0.0);
+    }
+
+    #[test]
+    fn test_physical_exposure() {
+        // A 50mm lens on full frame at f/2 has a 25mm wide aperture
+        let vfov = 2.0 * (0.012f64 / 0.05).atan().to_degrees();
+        let exposure = Exposure::new(2.0, 1.0 / 50.0, 400.0);
+        let physical = Camera::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            vfov,
+            1.5,
+            1.0,
+            3.0,
+        )
+        .with_exposure(exposure);
+        assert!((physical.lens_radius - 0.0125).abs() < 1e-12);
+        assert_eq!(physical.exposure(), exposure.scale());
+
+        let times = (0..1000)
+            .map(|_| physical.get_ray(0.5, 0.5).unwrap().time())
+            .collect::<Vec<f64>>();
+        assert!(times.iter().all(|time| (0.0..0.02).contains(time)));
+        assert!(times.iter().any(|&time| time > 0.015));
+
+        // Without physical exposure the shutter is instantaneous
+        let still = camera(0.5);
+        assert_eq!(still.get_ray(0.5, 0.5).unwrap().time(), 0.0);
+        assert_eq!(still.exposure(), 1.0);
+    }
+
+    #[test]
+    fn test_tilted_focus_plane() {
+        let tilted = camera(0.5).with_focus_tilt(-30.0, 0.0);
+        let untilted = camera(0.5).with_focus_tilt(0.0, 0.0);
+        let focus = |camera: &Camera, t| {
+            // Every sample through the lens passes the same point on the plane of focus
+            let ray = camera.get_ray(0.3, t).unwrap();
+            for _ in 0..10 {
+                let other = camera.get_ray(0.3, t).unwrap();
+                assert!((other.at(1.0) - ray.at(1.0)).length() < 1e-9);
+            }
+            ray.at(1.0)
+        };
+
+        // Tilting the top of the plane towards the camera brings it into focus closer
+        let (bottom, top) = (focus(&tilted, 0.1), focus(&tilted, 0.9));
+        assert!(-top.z() < 4.0 && -bottom.z() > 4.0);
+        let slope = (top.z() - bottom.z()) / (top.y() - bottom.y());
+        assert!((slope - 30f64.to_radians().tan()).abs() < 1e-9);
+        assert!((focus(&untilted, 0.9).z() + 4.0).abs() < 1e-9);
     }
 }
@@ -0,0 +1,12 @@
+use crate::rays::ray::Ray;
+
+// Maps a point on the image, with s and t running from 0 to 1 left to right and bottom
+// to top, to a primary ray. Points the projection doesn't cover give None and stay black.
+pub trait CameraModel: Sync + Send {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray>;
+
+    // Factor from scene radiance to film values
+    fn exposure(&self) -> f64 {
+        1.0
+    }
+}
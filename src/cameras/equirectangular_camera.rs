@@ -0,0 +1,73 @@
+use std::f64::consts::PI;
+
+use crate::cameras::camera_model::CameraModel;
+use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::{cross, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+// 360 degree panorama with longitude along the width and latitude along the height,
+// centered on the view direction. Images should have an aspect ratio of 2.
+pub struct EquirectangularCamera {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl EquirectangularCamera {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3) -> Self {
+        let w = unit_vector(lookfrom - lookat);
+        let u = unit_vector(cross(&vup, &w));
+        let v = cross(&w, &u);
+        EquirectangularCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+        }
+    }
+
+    // Unit direction for a point on the panorama
+    pub fn direction(&self, s: f64, t: f64) -> Vec3 {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        latitude.cos() * (longitude.sin() * self.u - longitude.cos() * self.w)
+            + latitude.sin() * self.v
+    }
+}
+
+impl CameraModel for EquirectangularCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray {
+            orig: self.origin,
+            dir: self.direction(s, t),
+            time: 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panorama_directions() {
+        let camera = EquirectangularCamera::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+        );
+        let expected = [
+            ((0.5, 0.5), Vec3::new_with_values(0.0, 0.0, -1.0)),
+            ((0.75, 0.5), Vec3::new_with_values(1.0, 0.0, 0.0)),
+            ((0.25, 0.5), Vec3::new_with_values(-1.0, 0.0, 0.0)),
+            ((0.0, 0.5), Vec3::new_with_values(0.0, 0.0, 1.0)),
+            ((0.3, 1.0), Vec3::new_with_values(0.0, 1.0, 0.0)),
+            ((0.3, 0.0), Vec3::new_with_values(0.0, -1.0, 0.0)),
+        ];
+        for ((s, t), direction) in expected {
+            let ray = camera.get_ray(s, t).unwrap();
+            assert!((ray.direction() - direction).length() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::cameras::camera_model::CameraModel;
+use crate::rays::ray::Ray;
+use crate::utils::random_number_utils::random_f64;
+
+// Camera settings deciding how much light reaches the film. With them scene radiance is
+// read as luminance in cd/m2, so a sunlit scene and a dim interior each come out
+// correctly exposed for the settings a photographer would pick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    pub f_number: f64,
+    // Time the shutter stays open, in seconds
+    pub shutter_speed: f64,
+    pub iso: f64,
+}
+
+impl Exposure {
+    pub fn new(f_number: f64, shutter_speed: f64, iso: f64) -> Self {
+        Exposure {
+            f_number,
+            shutter_speed,
+            iso,
+        }
+    }
+
+    // Exposure value of the settings, normalized to ISO 100
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    // Factor from luminance to film values, with 1 at the luminance that saturates the
+    // sensor under the saturation based ISO speed definition
+    pub fn scale(&self) -> f64 {
+        1.0 / (1.2 * 2f64.powf(self.ev100()))
+    }
+}
+
+// Physical exposure for any camera model. Rays are spread over the time the shutter is
+// open, which blurs moving objects, and the film response is scaled by the settings.
+// Models with a lens of their own keep its aperture, so the f-number should be the one
+// that lens is stopped down to. Camera::with_exposure also sizes the aperture to match.
+pub struct ExposedCamera<C: CameraModel> {
+    camera: C,
+    exposure: Exposure,
+}
+
+impl<C: CameraModel> ExposedCamera<C> {
+    pub fn new(camera: C, exposure: Exposure) -> Self {
+        ExposedCamera { camera, exposure }
+    }
+}
+
+impl<C: CameraModel> CameraModel for ExposedCamera<C> {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let ray = self.camera.get_ray(s, t)?;
+        Some(Ray {
+            orig: ray.origin(),
+            dir: ray.direction(),
+            time: random_f64() * self.exposure.shutter_speed,
+        })
+    }
+
+    fn exposure(&self) -> f64 {
+        self.exposure.scale()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::{
+        equirectangular_camera::EquirectangularCamera, fisheye_camera::FisheyeCamera,
+        orthographic_camera::OrthographicCamera,
+    };
+    use crate::vectors::vec3::Vec3;
+
+    #[test]
+    fn test_exposure_reciprocity() {
+        // Sunny sixteen: f/16 at 1/100s and ISO 100 is about EV 15
+        let sunny = Exposure::new(16.0, 0.01, 100.0);
+        assert!((sunny.ev100() - 14.64).abs() < 0.01);
+
+        // A stop more light from any of the three settings doubles the film response
+        let doubled = [
+            Exposure::new(16.0 / 2f64.sqrt(), 0.01, 100.0),
+            Exposure::new(16.0, 0.02, 100.0),
+            Exposure::new(16.0, 0.01, 200.0),
+        ];
+        for exposure in doubled {
+            assert!((exposure.scale() / sunny.scale() - 2.0).abs() < 1e-12);
+        }
+    }
+
+    fn check_exposed(camera: &dyn CameraModel, exposure: Exposure) {
+        assert_eq!(camera.exposure(), exposure.scale());
+        let times: Vec<f64> = (0..1000)
+            .map(|_| camera.get_ray(0.5, 0.5).unwrap().time())
+            .collect();
+        assert!(times.iter().all(|time| (0.0..0.02).contains(time)));
+        assert!(times.iter().any(|&time| time > 0.015));
+    }
+
+    #[test]
+    fn test_exposed_camera_models() {
+        let exposure = Exposure::new(2.0, 1.0 / 50.0, 400.0);
+        let (lookfrom, lookat) = (Vec3::new_with_values(0.0, 0.0, 5.0), Vec3::new());
+        let vup = Vec3::new_with_values(0.0, 1.0, 0.0);
+
+        let orthographic = OrthographicCamera::new(lookfrom, lookat, vup, 2.0, 1.0);
+        assert_eq!(orthographic.exposure(), 1.0);
+        let orthographic = ExposedCamera::new(orthographic, exposure);
+        check_exposed(&orthographic, exposure);
+        check_exposed(
+            &ExposedCamera::new(
+                FisheyeCamera::new(lookfrom, lookat, vup, 180.0, 1.0),
+                exposure,
+            ),
+            exposure,
+        );
+        check_exposed(
+            &ExposedCamera::new(EquirectangularCamera::new(lookfrom, lookat, vup), exposure),
+            exposure,
+        );
+    }
+}
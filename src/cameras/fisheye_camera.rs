@@ -0,0 +1,86 @@
+use crate::cameras::camera_model::CameraModel;
+use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::{cross, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+// Equidistant fisheye, where the angle from the view direction grows linearly with the
+// distance from the image center. The image circle spans the shorter image axis, and
+// fov may go up to 360 degrees.
+pub struct FisheyeCamera {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    half_fov: f64,
+    aspect_ratio: f64,
+}
+
+impl FisheyeCamera {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, fov: f64, aspect_ratio: f64) -> Self {
+        let w = unit_vector(lookfrom - lookat);
+        let u = unit_vector(cross(&vup, &w));
+        let v = cross(&w, &u);
+        FisheyeCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            half_fov: fov.to_radians() / 2.0,
+            aspect_ratio,
+        }
+    }
+}
+
+impl CameraModel for FisheyeCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        // Image coordinates scaled so that the image circle has radius one
+        let x = (2.0 * s - 1.0) * self.aspect_ratio.max(1.0);
+        let y = (2.0 * t - 1.0) * (1.0 / self.aspect_ratio).max(1.0);
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = r * self.half_fov;
+        let (x, y) = match r > 0.0 {
+            true => (x / r, y / r),
+            false => (0.0, 0.0),
+        };
+        Some(Ray {
+            orig: self.origin,
+            dir: theta.sin() * (x * self.u + y * self.v) - theta.cos() * self.w,
+            time: 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::vec3_utils::dot;
+
+    #[test]
+    fn test_equidistant_projection() {
+        let camera = FisheyeCamera::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            180.0,
+            2.0,
+        );
+        let forward = Vec3::new_with_values(0.0, 0.0, -1.0);
+        let center = camera.get_ray(0.5, 0.5).unwrap();
+        assert_eq!(center.direction(), forward);
+
+        // Half way to the edge of the circle is 45 degrees off axis
+        let up = camera.get_ray(0.5, 0.75).unwrap();
+        assert!((dot(&up.direction(), &forward) - 0.5f64.sqrt()).abs() < 1e-12);
+        assert!(up.direction().y() > 0.0);
+        let edge = camera.get_ray(0.75, 0.5).unwrap();
+        assert!(dot(&edge.direction(), &forward).abs() < 1e-12);
+        assert!(edge.direction().x() > 0.0);
+
+        // The corners of a wide image are outside the circle
+        assert!(camera.get_ray(0.0, 0.0).is_none());
+    }
+}
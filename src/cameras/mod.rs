@@ -1 +1,10 @@
+pub mod aperture;
 pub mod camera;
+pub mod camera_model;
+pub mod equirectangular_camera;
+pub mod exposure;
+pub mod fisheye_camera;
+pub mod ods_camera;
+pub mod orthographic_camera;
+pub mod realistic_camera;
+pub mod stereo_camera;
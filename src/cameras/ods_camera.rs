@@ -0,0 +1,87 @@
+use crate::cameras::camera_model::CameraModel;
+use crate::cameras::equirectangular_camera::EquirectangularCamera;
+use crate::cameras::stereo_camera::StereoEye;
+use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::cross;
+use crate::vectors::vec3::Vec3;
+
+// One eye of an omni-directional stereo panorama. Every ray starts on a circle of
+// diameter ipd around lookfrom, offset sideways from its own direction, so that each
+// column of the panorama sees the scene as an eye turned towards it would.
+pub struct OdsCamera {
+    panorama: EquirectangularCamera,
+    up: Vec3,
+    offset: f64,
+}
+
+impl OdsCamera {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, ipd: f64, eye: StereoEye) -> Self {
+        let panorama = EquirectangularCamera::new(lookfrom, lookat, vup);
+        // The panorama's own up direction, perpendicular to the view direction
+        let up = panorama.direction(0.5, 1.0);
+        OdsCamera {
+            panorama,
+            up,
+            offset: match eye {
+                StereoEye::Left => -ipd / 2.0,
+                StereoEye::Right => ipd / 2.0,
+            },
+        }
+    }
+}
+
+impl CameraModel for OdsCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let ray = self.panorama.get_ray(s, t)?;
+        // Sideways direction of an eye looking at this column of the panorama
+        let right = cross(&self.panorama.direction(s, 0.5), &self.up);
+        Some(Ray {
+            orig: ray.origin() + self.offset * right,
+            dir: ray.direction(),
+            time: ray.time(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::stereo_camera::{StereoCamera, StereoLayout};
+    use crate::utils::vec3_utils::dot;
+
+    #[test]
+    fn test_top_bottom_ods() {
+        let ods = |eye| {
+            Box::new(OdsCamera::new(
+                Vec3::new_with_values(0.0, 1.6, 0.0),
+                Vec3::new_with_values(0.0, 1.6, -1.0),
+                Vec3::new_with_values(0.0, 1.0, 0.0),
+                0.064,
+                eye,
+            ))
+        };
+        let stereo = StereoCamera::new(
+            ods(StereoEye::Left),
+            ods(StereoEye::Right),
+            StereoLayout::TopBottom,
+        );
+
+        // Looking forward the left eye sits to the left, and looking right it sits in front
+        let left_forward = stereo.get_ray(0.5, 0.75).unwrap();
+        let right_forward = stereo.get_ray(0.5, 0.25).unwrap();
+        assert!((left_forward.origin() - Vec3::new_with_values(-0.032, 1.6, 0.0)).length() < 1e-12);
+        assert!((right_forward.origin() - Vec3::new_with_values(0.032, 1.6, 0.0)).length() < 1e-12);
+        assert_eq!(left_forward.direction(), right_forward.direction());
+        let left_right = stereo.get_ray(0.75, 0.75).unwrap();
+        assert!((left_right.origin() - Vec3::new_with_values(0.0, 1.6, -0.032)).length() < 1e-12);
+
+        // Eyes are always offset perpendicular to the view, by the full ipd
+        for (s, t) in [(0.1, 0.3), (0.6, 0.45), (0.9, 0.1)] {
+            let left = stereo.get_ray(s, t + 0.5).unwrap();
+            let right = stereo.get_ray(s, t).unwrap();
+            let baseline = right.origin() - left.origin();
+            assert!((baseline.length() - 0.064).abs() < 1e-12);
+            assert!(dot(&baseline, &right.direction()).abs() < 1e-12);
+        }
+    }
+}
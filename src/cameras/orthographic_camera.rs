@@ -0,0 +1,68 @@
+use crate::cameras::camera_model::CameraModel;
+use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::{cross, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+// Parallel projection for technical views, where sizes don't shrink with distance
+pub struct OrthographicCamera {
+    lower_left_corner: Vec3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    direction: Vec3,
+}
+
+impl OrthographicCamera {
+    // view_height is the height of the visible region in world units
+    pub fn new(
+        lookfrom: Vec3,
+        lookat: Vec3,
+        vup: Vec3,
+        view_height: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let w = unit_vector(lookfrom - lookat);
+        let u = unit_vector(cross(&vup, &w));
+        let v = cross(&w, &u);
+
+        let horizontal = aspect_ratio * view_height * u;
+        let vertical = view_height * v;
+        OrthographicCamera {
+            lower_left_corner: lookfrom - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            direction: -w,
+        }
+    }
+}
+
+impl CameraModel for OrthographicCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray {
+            orig: self.lower_left_corner + s * self.horizontal + t * self.vertical,
+            dir: self.direction,
+            time: 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_rays() {
+        let camera = OrthographicCamera::new(
+            Vec3::new_with_values(0.0, 0.0, 5.0),
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            2.0,
+            2.0,
+        );
+        let corner = camera.get_ray(0.0, 0.0).unwrap();
+        let center = camera.get_ray(0.5, 0.5).unwrap();
+        assert_eq!(corner.direction(), center.direction());
+        assert_eq!(center.direction(), Vec3::new_with_values(0.0, 0.0, -1.0));
+        assert_eq!(corner.origin(), Vec3::new_with_values(-2.0, -1.0, 5.0));
+        assert_eq!(center.origin(), Vec3::new_with_values(0.0, 0.0, 5.0));
+    }
+}
@@ -0,0 +1,494 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::cameras::camera_model::CameraModel;
+use crate::rays::ray::Ray;
+use crate::utils::polynomial_utils::solve_quadratic;
+use crate::utils::vec3_utils::{cross, dot, random_in_unit_disk, refract, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum LensError {
+    Io(std::io::Error),
+    InvalidData(String),
+}
+
+impl fmt::Display for LensError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LensError::Io(error) => write!(f, "could not read lens prescription: {}", error),
+            LensError::InvalidData(message) => write!(f, "invalid lens system: {}", message),
+        }
+    }
+}
+
+impl Error for LensError {}
+
+impl From<std::io::Error> for LensError {
+    fn from(error: std::io::Error) -> Self {
+        LensError::Io(error)
+    }
+}
+
+// One refracting surface of a lens, or the aperture stop when the radius is zero.
+// Lengths are in scene units, taken to be meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+    // Signed radius of curvature, positive when the center lies towards the film
+    pub curvature_radius: f64,
+    // Distance along the axis to the next surface, or to the film for the last one
+    pub thickness: f64,
+    // Index of refraction behind the surface, with zero meaning air
+    pub eta: f64,
+    pub aperture_radius: f64,
+}
+
+// Surfaces of a lens ordered from the scene to the film, in the camera space used by
+// pbrt's realistic camera: the film lies at z = 0 and the scene towards +z.
+#[derive(Debug, Clone)]
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+}
+
+fn medium(eta: f64) -> f64 {
+    match eta == 0.0 {
+        true => 1.0,
+        false => eta,
+    }
+}
+
+// Intersection with a spherical surface centered on the axis at z_center, and the
+// surface normal facing the ray
+fn intersect_spherical(radius: f64, z_center: f64, ray: &Ray) -> Option<(f64, Vec3)> {
+    let o = ray.origin() - Vec3::new_with_values(0.0, 0.0, z_center);
+    let d = ray.direction();
+    let roots = solve_quadratic(
+        dot(&d, &d),
+        2.0 * dot(&d, &o),
+        dot(&o, &o) - radius * radius,
+    );
+    // Of the two crossings, the surface is the cap on the side the curvature points to
+    let use_closer = (d.z() > 0.0) ^ (radius < 0.0);
+    let t = match (roots.as_slice(), use_closer) {
+        ([t0, _], true) => *t0,
+        ([_, t1], false) => *t1,
+        _ => return None,
+    };
+    if t < 0.0 {
+        return None;
+    }
+    let normal = unit_vector(o + t * d);
+    match dot(&normal, &d) > 0.0 {
+        true => Some((t, -normal)),
+        false => Some((t, normal)),
+    }
+}
+
+// Refracted direction, or None under total internal reflection
+fn refract_through(direction: &Vec3, normal: &Vec3, eta_ratio: f64) -> Option<Vec3> {
+    let direction = unit_vector(*direction);
+    let cos_i = dot(&-direction, normal);
+    match eta_ratio * eta_ratio * (1.0 - cos_i * cos_i) > 1.0 {
+        true => None,
+        false => Some(refract(&direction, normal, eta_ratio)),
+    }
+}
+
+// Axial positions of the principal plane and focal point for a ray entering parallel
+// to the axis, following pbrt
+fn cardinal_points(r_in: &Ray, r_out: &Ray) -> (f64, f64) {
+    let tf = -r_out.origin().x() / r_out.direction().x();
+    let tp = (r_in.origin().x() - r_out.origin().x()) / r_out.direction().x();
+    (-r_out.at(tp).z(), -r_out.at(tf).z())
+}
+
+impl LensSystem {
+    pub fn new(elements: Vec<LensElement>) -> Result<Self, LensError> {
+        if elements.is_empty() {
+            return Err(LensError::InvalidData("no lens elements".to_string()));
+        }
+        if let Some(element) = elements
+            .iter()
+            .find(|element| element.aperture_radius <= 0.0 || element.thickness < 0.0)
+        {
+            return Err(LensError::InvalidData(format!(
+                "element {:?} needs a positive aperture and thickness",
+                element
+            )));
+        }
+        Ok(LensSystem { elements })
+    }
+
+    // Reads a prescription in pbrt's format: one surface per line with the curvature
+    // radius, thickness, index of refraction and aperture diameter, all in millimeters
+    pub fn parse(text: &str) -> Result<Self, LensError> {
+        let mut elements = vec![];
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| {
+                    LensError::InvalidData(format!("invalid number on line {}", line_number + 1))
+                })?;
+            match values.as_slice() {
+                [] => {}
+                [radius, thickness, eta, diameter] => elements.push(LensElement {
+                    curvature_radius: radius * 0.001,
+                    thickness: thickness * 0.001,
+                    eta: *eta,
+                    aperture_radius: diameter * 0.0005,
+                }),
+                _ => {
+                    return Err(LensError::InvalidData(format!(
+                        "line {} should have 4 values, found {}",
+                        line_number + 1,
+                        values.len()
+                    )))
+                }
+            }
+        }
+        LensSystem::new(elements)
+    }
+
+    pub fn load(path: &str) -> Result<Self, LensError> {
+        LensSystem::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn elements(&self) -> &[LensElement] {
+        &self.elements
+    }
+
+    // Stops the lens down to the given diameter in millimeters. The stop can't open
+    // wider than the prescription allows.
+    pub fn with_aperture_diameter(mut self, diameter: f64) -> Self {
+        for element in self.elements.iter_mut() {
+            if element.curvature_radius == 0.0 {
+                element.aperture_radius = element.aperture_radius.min(diameter * 0.0005);
+            }
+        }
+        self
+    }
+
+    fn rear_z(&self) -> f64 {
+        self.elements
+            .last()
+            .map_or(0.0, |element| element.thickness)
+    }
+
+    fn front_z(&self) -> f64 {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    fn rear_aperture(&self) -> f64 {
+        self.elements
+            .last()
+            .map_or(0.0, |element| element.aperture_radius)
+    }
+
+    // Traces a camera space ray leaving the film out through the lens, None when an
+    // aperture blocks it
+    pub fn trace_from_film(&self, ray: &Ray) -> Option<Ray> {
+        let mut element_z = 0.0;
+        let mut ray = Ray {
+            orig: ray.origin(),
+            dir: ray.direction(),
+            time: ray.time(),
+        };
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            element_z += element.thickness;
+            let (t, normal) = self.intersect(element, element_z, &ray)?;
+            ray.orig = ray.at(t);
+            if let Some(normal) = normal {
+                let eta_t = match i {
+                    0 => 1.0,
+                    _ => medium(self.elements[i - 1].eta),
+                };
+                ray.dir = refract_through(&ray.dir, &normal, medium(element.eta) / eta_t)?;
+            }
+        }
+        Some(ray)
+    }
+
+    // Traces a camera space ray from the scene in through the lens towards the film
+    pub fn trace_from_scene(&self, ray: &Ray) -> Option<Ray> {
+        let mut element_z = self.front_z();
+        let mut ray = Ray {
+            orig: ray.origin(),
+            dir: ray.direction(),
+            time: ray.time(),
+        };
+        for (i, element) in self.elements.iter().enumerate() {
+            let (t, normal) = self.intersect(element, element_z, &ray)?;
+            ray.orig = ray.at(t);
+            if let Some(normal) = normal {
+                let eta_i = match i {
+                    0 => 1.0,
+                    _ => medium(self.elements[i - 1].eta),
+                };
+                ray.dir = refract_through(&ray.dir, &normal, eta_i / medium(element.eta))?;
+            }
+            element_z -= element.thickness;
+        }
+        Some(ray)
+    }
+
+    // Intersection with the surface at element_z, with the normal for refracting ones
+    fn intersect(
+        &self,
+        element: &LensElement,
+        element_z: f64,
+        ray: &Ray,
+    ) -> Option<(f64, Option<Vec3>)> {
+        // The surface is mirrored into camera space, where +z points at the scene
+        let (t, normal) = match element.curvature_radius == 0.0 {
+            true => match ray.direction().z() == 0.0 {
+                true => return None,
+                false => ((element_z - ray.origin().z()) / ray.direction().z(), None),
+            },
+            false => {
+                let radius = -element.curvature_radius;
+                let (t, normal) = intersect_spherical(radius, element_z + radius, ray)?;
+                (t, Some(normal))
+            }
+        };
+        let hit = ray.at(t);
+        match t >= 0.0 && hit.x().powi(2) + hit.y().powi(2) <= element.aperture_radius.powi(2) {
+            true => Some((t, normal)),
+            false => None,
+        }
+    }
+
+    // Principal planes and focal points on the scene and film sides, from paraxial rays
+    fn thick_lens(&self) -> Result<[(f64, f64); 2], LensError> {
+        let height = 0.01
+            * self
+                .elements
+                .iter()
+                .map(|element| element.aperture_radius)
+                .fold(f64::INFINITY, f64::min);
+        let from_scene = Ray {
+            orig: Vec3::new_with_values(height, 0.0, self.front_z() + 1.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let from_film = Ray {
+            orig: Vec3::new_with_values(height, 0.0, self.rear_z() - 1.0),
+            dir: Vec3::new_with_values(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        match (
+            self.trace_from_scene(&from_scene),
+            self.trace_from_film(&from_film),
+        ) {
+            (Some(to_film), Some(to_scene)) => Ok([
+                cardinal_points(&from_scene, &to_film),
+                cardinal_points(&from_film, &to_scene),
+            ]),
+            _ => Err(LensError::InvalidData(
+                "paraxial rays don't pass through the lens".to_string(),
+            )),
+        }
+    }
+
+    pub fn focal_length(&self) -> Result<f64, LensError> {
+        let [(principal, focal), _] = self.thick_lens()?;
+        Ok(focal - principal)
+    }
+
+    // Moves the film so that objects focus_distance in front of it are sharp
+    pub fn focused(mut self, focus_distance: f64) -> Result<Self, LensError> {
+        let [(pz0, fz0), (pz1, _)] = self.thick_lens()?;
+        let f = fz0 - pz0;
+        let z = -focus_distance;
+        let c = (pz1 - z - pz0) * (pz1 - z - 4.0 * f - pz0);
+        if c <= 0.0 {
+            return Err(LensError::InvalidData(format!(
+                "can't focus at {} with this lens",
+                focus_distance
+            )));
+        }
+        let delta = 0.5 * (pz1 - z + pz0 - c.sqrt());
+        if let Some(rear) = self.elements.last_mut() {
+            rear.thickness += delta;
+        }
+        Ok(self)
+    }
+}
+
+// Camera that traces rays through the surfaces of a real lens design, so vignetting,
+// distortion and the shape of the depth of field come from the lens itself. Rays are
+// aimed at the rear element and those the lens blocks stay black, so stopping down
+// darkens the image as it would on a real camera.
+pub struct RealisticCamera {
+    lens: LensSystem,
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    film_width: f64,
+    film_height: f64,
+}
+
+impl RealisticCamera {
+    // film_diagonal is in millimeters, 43.3 for a full frame sensor
+    pub fn new(
+        lookfrom: Vec3,
+        lookat: Vec3,
+        vup: Vec3,
+        lens: LensSystem,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+        focus_distance: f64,
+    ) -> Result<Self, LensError> {
+        let w = unit_vector(lookfrom - lookat);
+        let u = unit_vector(cross(&vup, &w));
+        let v = cross(&w, &u);
+        let film_height = 0.001 * film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        Ok(RealisticCamera {
+            lens: lens.focused(focus_distance)?,
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            film_width: aspect_ratio * film_height,
+            film_height,
+        })
+    }
+
+    pub fn lens(&self) -> &LensSystem {
+        &self.lens
+    }
+
+    // Ray through the lens in camera space, from a point on the film towards a point
+    // on the rear element
+    fn camera_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        // The lens forms an inverted image, so the film is flipped both ways
+        let film = Vec3::new_with_values(
+            -(s - 0.5) * self.film_width,
+            -(t - 0.5) * self.film_height,
+            0.0,
+        );
+        let rear = self.lens.rear_aperture() * random_in_unit_disk()
+            + Vec3::new_with_values(0.0, 0.0, self.lens.rear_z());
+        self.lens.trace_from_film(&Ray {
+            orig: film,
+            dir: rear - film,
+            time: 0.0,
+        })
+    }
+}
+
+impl CameraModel for RealisticCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let ray = self.camera_ray(s, t)?;
+        let to_world = |p: Vec3| p.x() * self.u + p.y() * self.v - p.z() * self.w;
+        Some(Ray {
+            orig: self.origin + to_world(ray.origin()),
+            dir: to_world(ray.direction()),
+            time: ray.time(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Double Gauss design scaled to 50mm, from pbrt's lens collection
+    const DOUBLE_GAUSS: &str = "
+# D-GAUSS F/2 22deg HFOV
+# radius sep n aperture
+29.475  3.76   1.67   25.2
+84.83   0.12   1      25.2
+19.275  4.025  1.67   23
+40.77   3.275  1.699  23
+12.75   5.705  1      18
+0       4.5    0      17.1
+-14.495 1.18   1.603  17
+40.77   6.065  1.658  20
+-20.385 0.19   1      20
+437.065 3.22   1.717  20
+-39.73  0      1      20
+";
+
+    fn camera(focus_distance: f64) -> RealisticCamera {
+        RealisticCamera::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            LensSystem::parse(DOUBLE_GAUSS).unwrap(),
+            43.3,
+            1.5,
+            focus_distance,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_prescription() {
+        let lens = LensSystem::parse(DOUBLE_GAUSS).unwrap();
+        assert_eq!(lens.elements().len(), 11);
+        assert_eq!(lens.elements()[5].curvature_radius, 0.0);
+        let focal_length = lens.focal_length().unwrap();
+        assert!((focal_length - 0.05).abs() < 0.005, "{}", focal_length);
+
+        assert!(matches!(
+            LensSystem::parse("29.475 3.76 1.67"),
+            Err(LensError::InvalidData(_))
+        ));
+        assert!(matches!(
+            LensSystem::parse("29.475 3.76 one 25.2"),
+            Err(LensError::InvalidData(_))
+        ));
+        assert!(matches!(
+            LensSystem::load("does_not_exist.dat"),
+            Err(LensError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_focus_and_vignetting() {
+        // Rays from the film center meet again at the focus distance
+        let camera = camera(2.0);
+        let mut center_passed = 0;
+        for _ in 0..1000 {
+            if let Some(ray) = camera.get_ray(0.5, 0.5) {
+                center_passed += 1;
+                let t = -(2.0 + ray.origin().z()) / ray.direction().z();
+                let miss = ray.at(t) - Vec3::new_with_values(0.0, 0.0, -2.0);
+                assert!(miss.length() < 0.002, "{:?}", miss);
+            }
+        }
+
+        // Fewer rays make it through the lens from the corners of the film
+        let corner_passed = (0..1000)
+            .filter(|_| camera.get_ray(0.0, 1.0).is_some())
+            .count();
+        assert!(corner_passed < center_passed / 2);
+
+        // The image is upright, with a point up and to the right of the view there too
+        let ray = (0..100).find_map(|_| camera.get_ray(0.7, 0.6)).unwrap();
+        assert!(ray.direction().x() > 0.0 && ray.direction().y() > 0.0);
+        assert!(ray.direction().z() < 0.0);
+
+        // Stopping down lets fewer rays through
+        let stopped_down = RealisticCamera::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            LensSystem::parse(DOUBLE_GAUSS)
+                .unwrap()
+                .with_aperture_diameter(5.0),
+            43.3,
+            1.5,
+            2.0,
+        )
+        .unwrap();
+        let stopped_passed = (0..1000)
+            .filter(|_| stopped_down.get_ray(0.5, 0.5).is_some())
+            .count();
+        assert!(stopped_passed < center_passed / 4);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::cameras::camera_model::CameraModel;
+use crate::rays::ray::Ray;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoEye {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    // Left eye in the left half of the image
+    SideBySide,
+    // Left eye in the top half of the image, as VR players expect for 360 video
+    TopBottom,
+}
+
+// Packs the views of both eyes into one image
+pub struct StereoCamera {
+    left: Box<dyn CameraModel>,
+    right: Box<dyn CameraModel>,
+    layout: StereoLayout,
+}
+
+impl StereoCamera {
+    pub fn new(
+        left: Box<dyn CameraModel>,
+        right: Box<dyn CameraModel>,
+        layout: StereoLayout,
+    ) -> Self {
+        StereoCamera {
+            left,
+            right,
+            layout,
+        }
+    }
+}
+
+impl CameraModel for StereoCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        match self.layout {
+            StereoLayout::SideBySide => match s < 0.5 {
+                true => self.left.get_ray(2.0 * s, t),
+                false => self.right.get_ray(2.0 * s - 1.0, t),
+            },
+            StereoLayout::TopBottom => match t >= 0.5 {
+                true => self.left.get_ray(s, 2.0 * t - 1.0),
+                false => self.right.get_ray(s, 2.0 * t),
+            },
+        }
+    }
+
+    fn exposure(&self) -> f64 {
+        self.left.exposure()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::camera::Camera;
+    use crate::vectors::vec3::Vec3;
+
+    #[test]
+    fn test_perspective_pair_converges() {
+        let camera = Camera::new(
+            Vec3::new_with_values(0.0, 0.0, 5.0),
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            0.0,
+            2.0,
+        );
+        let left = camera.stereo_eye(StereoEye::Left, 0.064, 3.0);
+        let right = camera.stereo_eye(StereoEye::Right, 0.064, 3.0);
+        let stereo = StereoCamera::new(Box::new(left), Box::new(right), StereoLayout::SideBySide);
+
+        // The centers of both views meet at the convergence distance, with the eyes level
+        let convergence = Vec3::new_with_values(0.0, 0.0, 2.0);
+        for (s, x) in [(0.25, -0.032), (0.75, 0.032)] {
+            let ray = stereo.get_ray(s, 0.5).unwrap();
+            assert!((ray.origin() - Vec3::new_with_values(x, 0.0, 5.0)).length() < 1e-12);
+            let t = (convergence.z() - ray.origin().z()) / ray.direction().z();
+            assert!((ray.at(t) - convergence).length() < 1e-12);
+        }
+    }
+}
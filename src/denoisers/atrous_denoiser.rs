@@ -0,0 +1,378 @@
+use rayon::prelude::*;
+
+use crate::vectors::vec3::Vec3;
+
+use std::error::Error;
+use std::fmt;
+
+// Weights of the B3 spline the wavelet transform is built on
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Albedo below which a channel is filtered as is rather than divided out
+const MIN_ALBEDO: f64 = 1e-3;
+
+#[derive(Debug)]
+pub enum DenoiseError {
+    InvalidSize {
+        layer: &'static str,
+        width: u32,
+        height: u32,
+        pixels: usize,
+    },
+}
+
+impl fmt::Display for DenoiseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DenoiseError::InvalidSize {
+                layer,
+                width,
+                height,
+                pixels,
+            } => write!(
+                f,
+                "{} layer of {} pixels does not fit a {}x{} image",
+                layer, pixels, width, height
+            ),
+        }
+    }
+}
+
+impl Error for DenoiseError {}
+
+fn is_finite(value: &Vec3) -> bool {
+    value.x().is_finite() && value.y().is_finite() && value.z().is_finite()
+}
+
+// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010). Each pass blurs with the
+// 5x5 B3 kernel spread out to twice the spacing of the previous pass, and neighbors only
+// count as much as their color, normal and albedo resemble those of the pixel. Texture
+// is divided out before filtering and put back after, so only lighting gets blurred.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtrousDenoiser {
+    iterations: u32,
+    strength: f64,
+    normal_sigma: f64,
+    albedo_sigma: f64,
+}
+
+impl AtrousDenoiser {
+    pub fn new() -> Self {
+        AtrousDenoiser {
+            iterations: 5,
+            strength: 1.0,
+            normal_sigma: 0.3,
+            albedo_sigma: 0.1,
+        }
+    }
+
+    // Number of passes, each doubling the reach of the filter
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    // How different in color neighbors may be and still get averaged in. Zero leaves
+    // the image untouched, larger values smooth more at the cost of detail in shadows
+    // and highlights.
+    pub fn with_strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    // How closely normals and albedos must match for neighbors to be averaged in
+    pub fn with_feature_sigmas(mut self, normal_sigma: f64, albedo_sigma: f64) -> Self {
+        self.normal_sigma = normal_sigma;
+        self.albedo_sigma = albedo_sigma;
+        self
+    }
+
+    // Denoises a width x height image indexed by y * width + x, guided by the albedo and
+    // normal layers of the same pixels. Pixels with a NaN or infinite value in any layer
+    // are left out of their neighbors' averages and filled in from the neighbors instead.
+    pub fn denoise(
+        &self,
+        width: u32,
+        height: u32,
+        color: &[Vec3],
+        albedo: &[Vec3],
+        normal: &[Vec3],
+    ) -> Result<Vec<Vec3>, DenoiseError> {
+        for (layer, pixels) in [("color", color), ("albedo", albedo), ("normal", normal)] {
+            if pixels.len() != width as usize * height as usize {
+                return Err(DenoiseError::InvalidSize {
+                    layer,
+                    width,
+                    height,
+                    pixels: pixels.len(),
+                });
+            }
+        }
+        let (width, height) = (width as usize, height as usize);
+        if self.strength <= 0.0 || self.iterations == 0 {
+            return Ok(color.to_vec());
+        }
+        let valid = (0..width * height)
+            .map(|index| {
+                is_finite(&color[index]) && is_finite(&albedo[index]) && is_finite(&normal[index])
+            })
+            .collect::<Vec<bool>>();
+
+        let demodulate = |value: f64, albedo: f64| match albedo > MIN_ALBEDO {
+            true => value / albedo,
+            false => value,
+        };
+        let remodulate = |value: f64, albedo: f64| match albedo > MIN_ALBEDO {
+            true => value * albedo,
+            false => value,
+        };
+        let mut irradiance = color
+            .iter()
+            .zip(albedo)
+            .zip(&valid)
+            .map(|((color, albedo), &valid)| match valid {
+                true => Vec3::new_with_values(
+                    demodulate(color.x(), albedo.x()),
+                    demodulate(color.y(), albedo.y()),
+                    demodulate(color.z(), albedo.z()),
+                ),
+                false => Vec3::new(),
+            })
+            .collect::<Vec<Vec3>>();
+
+        let normal_falloff = 1.0 / (self.normal_sigma * self.normal_sigma);
+        let albedo_falloff = 1.0 / (self.albedo_sigma * self.albedo_sigma);
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            // Later passes see smoother input, so colors are held to a tighter match
+            let color_sigma = self.strength / step as f64;
+            let color_falloff = 1.0 / (color_sigma * color_sigma);
+            let input = irradiance;
+            irradiance = vec![Vec3::new(); width * height];
+            irradiance
+                .par_chunks_mut(width)
+                .enumerate()
+                .for_each(|(y, row)| {
+                    for (x, output) in row.iter_mut().enumerate() {
+                        let center = y * width + x;
+                        let mut sum = Vec3::new();
+                        let mut total_weight = 0.0;
+                        for (j, kernel_y) in KERNEL.iter().enumerate() {
+                            let ny = y as isize + (j as isize - 2) * step;
+                            if ny < 0 || ny >= height as isize {
+                                continue;
+                            }
+                            for (i, kernel_x) in KERNEL.iter().enumerate() {
+                                let nx = x as isize + (i as isize - 2) * step;
+                                if nx < 0 || nx >= width as isize {
+                                    continue;
+                                }
+                                let neighbor = ny as usize * width + nx as usize;
+                                if !valid[neighbor] {
+                                    continue;
+                                }
+                                let distance = |layer: &[Vec3]| {
+                                    (layer[neighbor] - layer[center]).length_squared()
+                                };
+                                // Invalid pixels have nothing to compare, so they take
+                                // the plain blur of their neighbors
+                                let similarity = match valid[center] {
+                                    true => (-distance(&input) * color_falloff
+                                        - distance(normal) * normal_falloff
+                                        - distance(albedo) * albedo_falloff)
+                                        .exp(),
+                                    false => 1.0,
+                                };
+                                let weight = kernel_x * kernel_y * similarity;
+                                sum += weight * input[neighbor];
+                                total_weight += weight;
+                            }
+                        }
+                        // Valid pixels always weigh themselves in, invalid ones with no
+                        // valid neighbors stay black
+                        *output = match total_weight > 0.0 {
+                            true => sum / total_weight,
+                            false => Vec3::new(),
+                        };
+                    }
+                });
+        }
+
+        // Pixels without a usable albedo get the texture of their neighbors back, or
+        // none at all if there is nothing to go by
+        let albedo = (0..width * height)
+            .map(|index| match is_finite(&albedo[index]) {
+                true => albedo[index],
+                false => {
+                    let (x, y) = ((index % width) as isize, (index / width) as isize);
+                    let neighbors = (y - 1..=y + 1)
+                        .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                        .filter(|&(nx, ny)| {
+                            nx >= 0 && ny >= 0 && nx < width as isize && ny < height as isize
+                        })
+                        .map(|(nx, ny)| albedo[ny as usize * width + nx as usize])
+                        .filter(is_finite)
+                        .collect::<Vec<Vec3>>();
+                    match neighbors.len() {
+                        0 => Vec3::new_with_values(1.0, 1.0, 1.0),
+                        count => {
+                            neighbors.iter().fold(Vec3::new(), |sum, &a| sum + a) / count as f64
+                        }
+                    }
+                }
+            })
+            .collect::<Vec<Vec3>>();
+        Ok(irradiance
+            .iter()
+            .zip(&albedo)
+            .map(|(irradiance, albedo)| {
+                Vec3::new_with_values(
+                    remodulate(irradiance.x(), albedo.x()),
+                    remodulate(irradiance.y(), albedo.y()),
+                    remodulate(irradiance.z(), albedo.z()),
+                )
+            })
+            .collect())
+    }
+}
+
+impl Default for AtrousDenoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_number_utils::random_f64_range;
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 48;
+
+    // Two materials meeting at x = 32, lit evenly and rendered with heavy noise
+    fn noisy_image() -> (Vec<Vec3>, Vec<Vec3>, Vec<Vec3>, Vec<Vec3>) {
+        let mut clean = vec![];
+        let mut noisy = vec![];
+        let mut albedo = vec![];
+        for _ in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let material = match x < WIDTH / 2 {
+                    true => Vec3::new_with_values(0.8, 0.6, 0.4),
+                    false => Vec3::new_with_values(0.2, 0.3, 0.4),
+                };
+                let color = 0.5 * material;
+                let noise = Vec3::new_with_values(
+                    random_f64_range(-0.5, 0.5),
+                    random_f64_range(-0.5, 0.5),
+                    random_f64_range(-0.5, 0.5),
+                );
+                clean.push(color);
+                noisy.push(color + noise * color);
+                albedo.push(material);
+            }
+        }
+        let normal = vec![Vec3::new_with_values(0.0, 0.0, 1.0); clean.len()];
+        (clean, noisy, albedo, normal)
+    }
+
+    fn mean_squared_error(image: &[Vec3], reference: &[Vec3]) -> f64 {
+        image
+            .iter()
+            .zip(reference)
+            .map(|(pixel, reference)| (*pixel - *reference).length_squared())
+            .sum::<f64>()
+            / image.len() as f64
+    }
+
+    #[test]
+    fn test_reduces_variance() {
+        let (clean, noisy, albedo, normal) = noisy_image();
+        let denoised = AtrousDenoiser::new()
+            .denoise(WIDTH, HEIGHT, &noisy, &albedo, &normal)
+            .unwrap();
+        let before = mean_squared_error(&noisy, &clean);
+        let after = mean_squared_error(&denoised, &clean);
+        assert!(after < 0.1 * before);
+
+        // Pixels right next to the edge keep their own material's color
+        for y in 0..HEIGHT as usize {
+            let row = y * WIDTH as usize;
+            let left = row + WIDTH as usize / 2 - 1;
+            assert!((denoised[left] - clean[left]).length() < 0.1);
+            assert!((denoised[left + 1] - clean[left + 1]).length() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_strength() {
+        let (clean, noisy, albedo, normal) = noisy_image();
+        let denoise = |strength: f64| {
+            let denoised = AtrousDenoiser::new()
+                .with_strength(strength)
+                .denoise(WIDTH, HEIGHT, &noisy, &albedo, &normal)
+                .unwrap();
+            mean_squared_error(&denoised, &clean)
+        };
+        assert_eq!(denoise(0.0), mean_squared_error(&noisy, &clean));
+        assert!(denoise(0.05) > denoise(1.0));
+    }
+
+    #[test]
+    fn test_normals_stop_the_filter() {
+        // Same material folded along x = 32 and lit differently on each side
+        let normal = (0..HEIGHT * WIDTH)
+            .map(|index| match index % WIDTH < WIDTH / 2 {
+                true => Vec3::new_with_values(0.0, 0.0, 1.0),
+                false => Vec3::new_with_values(1.0, 0.0, 0.0),
+            })
+            .collect::<Vec<Vec3>>();
+        let color = normal
+            .iter()
+            .map(|normal| match normal.z() > 0.0 {
+                true => Vec3::new_with_values(0.5, 0.5, 0.5),
+                false => Vec3::new_with_values(0.1, 0.1, 0.1),
+            })
+            .collect::<Vec<Vec3>>();
+        let albedo = vec![Vec3::new_with_values(0.5, 0.5, 0.5); color.len()];
+
+        // Colors alone would let a strong filter blur across
+        let denoised = AtrousDenoiser::new()
+            .with_strength(100.0)
+            .denoise(WIDTH, HEIGHT, &color, &albedo, &normal)
+            .unwrap();
+        for (pixel, expected) in denoised.iter().zip(&color) {
+            assert!((*pixel - *expected).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        let (_, noisy, albedo, normal) = noisy_image();
+        let denoised = AtrousDenoiser::new().denoise(WIDTH, HEIGHT, &noisy, &albedo[1..], &normal);
+        assert!(matches!(
+            denoised,
+            Err(DenoiseError::InvalidSize {
+                layer: "albedo",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_non_finite_pixels() {
+        let (clean, mut noisy, mut albedo, normal) = noisy_image();
+        noisy[100] = Vec3::new_with_values(f64::NAN, 0.0, 0.0);
+        noisy[200] = Vec3::new_with_values(f64::INFINITY, 0.0, 0.0);
+        albedo[300] = Vec3::new_with_values(0.0, f64::NAN, 0.0);
+        let denoised = AtrousDenoiser::new()
+            .denoise(WIDTH, HEIGHT, &noisy, &albedo, &normal)
+            .unwrap();
+
+        // Bad pixels neither spread to their neighbors nor stay bad themselves
+        assert!(denoised.iter().all(is_finite));
+        for index in [99, 100, 101, 199, 200, 201, 299, 300, 301] {
+            assert!((denoised[index] - clean[index]).length() < 0.1);
+        }
+    }
+}
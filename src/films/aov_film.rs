@@ -0,0 +1,379 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use exr::prelude::*;
+use image::RgbImage;
+
+use crate::renderers::aov::{Aov, AovSample};
+use crate::utils::color_utils::{convert_vec3_to_color, gamma_correct};
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+use super::film::Film;
+
+#[derive(Debug, Clone, Copy)]
+struct Pixel {
+    samples: u32,
+    hits: u32,
+    depth: f64,
+    position: Vec3,
+    normal: Vec3,
+    albedo: Vec3,
+    direct: Vec3,
+    indirect: Vec3,
+    material: Option<usize>,
+    object: Option<usize>,
+}
+
+// Per pixel buffers for the arbitrary output variables. Unlike the beauty film samples
+// stay in the pixel they were taken in, since filtering across an edge would blend
+// depths and normals into values no surface has. Geometric layers average the samples
+// that hit something, ids come from the first sample that did.
+pub struct AovFilm {
+    width: u32,
+    height: u32,
+    rows: Vec<Mutex<Vec<Pixel>>>,
+}
+
+impl AovFilm {
+    pub fn new(width: u32, height: u32) -> Self {
+        let empty = Pixel {
+            samples: 0,
+            hits: 0,
+            depth: 0.0,
+            position: Vec3::new(),
+            normal: Vec3::new(),
+            albedo: Vec3::new(),
+            direct: Vec3::new(),
+            indirect: Vec3::new(),
+            material: None,
+            object: None,
+        };
+        AovFilm {
+            width,
+            height,
+            rows: (0..height)
+                .map(|_| Mutex::new(vec![empty; width as usize]))
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Adds a sample to pixel (x, y), with y running up from the bottom row like Film
+    pub fn add_sample(&self, x: u32, y: u32, sample: &AovSample) {
+        let mut pixels = self.rows[y as usize].lock().unwrap();
+        let pixel = &mut pixels[x as usize];
+        pixel.samples += 1;
+        pixel.albedo += sample.albedo;
+        pixel.direct += sample.direct;
+        pixel.indirect += sample.indirect;
+        if sample.depth.is_finite() {
+            pixel.hits += 1;
+            pixel.depth += sample.depth;
+            pixel.position += sample.position;
+            pixel.normal += sample.normal;
+            if pixel.object.is_none() {
+                pixel.material = sample.material;
+                pixel.object = sample.object;
+            }
+        }
+    }
+
+    // Values of a layer for every pixel, indexed by y * width + x. Scalar layers repeat
+    // their value in all three components. Depth is infinite where nothing was hit, and
+    // ids start at one so that zero marks the background. Objects keep their index in
+    // the scene and materials their index in the scene's registry.
+    pub fn layer(&self, aov: Aov) -> Vec<Vec3> {
+        let pixels = self
+            .rows
+            .iter()
+            .flat_map(|row| row.lock().unwrap().clone())
+            .collect::<Vec<Pixel>>();
+        let per_sample = |value: Vec3, pixel: &Pixel| match pixel.samples {
+            0 => Vec3::new(),
+            samples => value / samples as f64,
+        };
+        let per_hit = |value: Vec3, pixel: &Pixel| match pixel.hits {
+            0 => Vec3::new(),
+            hits => value / hits as f64,
+        };
+        let scalar = |value: f64| Vec3::new_with_values(value, value, value);
+
+        match aov {
+            Aov::Depth => pixels
+                .iter()
+                .map(|pixel| match pixel.hits {
+                    0 => scalar(f64::INFINITY),
+                    hits => scalar(pixel.depth / hits as f64),
+                })
+                .collect(),
+            Aov::Position => pixels
+                .iter()
+                .map(|pixel| per_hit(pixel.position, pixel))
+                .collect(),
+            Aov::Normal => pixels
+                .iter()
+                .map(|pixel| match pixel.normal.near_zero() {
+                    true => Vec3::new(),
+                    false => unit_vector(pixel.normal),
+                })
+                .collect(),
+            Aov::Albedo => pixels
+                .iter()
+                .map(|pixel| per_sample(pixel.albedo, pixel))
+                .collect(),
+            Aov::MaterialId => pixels
+                .iter()
+                .map(|pixel| match pixel.material {
+                    Some(material) => scalar(material as f64 + 1.0),
+                    None => Vec3::new(),
+                })
+                .collect(),
+            Aov::ObjectId => pixels
+                .iter()
+                .map(|pixel| match pixel.object {
+                    Some(object) => scalar(object as f64 + 1.0),
+                    None => Vec3::new(),
+                })
+                .collect(),
+            Aov::Direct => pixels
+                .iter()
+                .map(|pixel| per_sample(pixel.direct, pixel))
+                .collect(),
+            Aov::Indirect => pixels
+                .iter()
+                .map(|pixel| per_sample(pixel.indirect, pixel))
+                .collect(),
+        }
+    }
+
+    // Layer mapped to something viewable: depth fades from white at the nearest hit to
+    // black, positions span the bounds of the hits, normals map to 0.5 * (n + 1), ids
+    // get arbitrary colors and lighting is gamma corrected like the beauty image.
+    pub fn to_image(&self, aov: Aov) -> RgbImage {
+        let values = self.layer(aov);
+        let finite = values.iter().filter(|value| value.x().is_finite());
+        let (minimum, maximum) = finite.fold(
+            (
+                Vec3::new_with_values(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+                Vec3::new_with_values(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(minimum, maximum), value| {
+                (
+                    Vec3::new_with_values(
+                        minimum.x().min(value.x()),
+                        minimum.y().min(value.y()),
+                        minimum.z().min(value.z()),
+                    ),
+                    Vec3::new_with_values(
+                        maximum.x().max(value.x()),
+                        maximum.y().max(value.y()),
+                        maximum.z().max(value.z()),
+                    ),
+                )
+            },
+        );
+        let normalized = |value: f64, axis: usize| match maximum[axis] > minimum[axis] {
+            true => (value - minimum[axis]) / (maximum[axis] - minimum[axis]),
+            false => 1.0,
+        };
+
+        let mut image = RgbImage::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = values[(y * self.width + x) as usize];
+                let color = match aov {
+                    Aov::Depth => match value.x().is_finite() {
+                        true => {
+                            let shade = 1.0 - 0.9 * normalized(value.x(), 0);
+                            Vec3::new_with_values(shade, shade, shade)
+                        }
+                        false => Vec3::new(),
+                    },
+                    Aov::Position => match value.near_zero() {
+                        true => Vec3::new(),
+                        false => Vec3::new_with_values(
+                            normalized(value.x(), 0),
+                            normalized(value.y(), 1),
+                            normalized(value.z(), 2),
+                        ),
+                    },
+                    Aov::Normal => match value.near_zero() {
+                        true => Vec3::new(),
+                        false => 0.5 * (value + Vec3::new_with_values(1.0, 1.0, 1.0)),
+                    },
+                    Aov::Albedo => value,
+                    Aov::MaterialId | Aov::ObjectId => id_color(value.x() as usize),
+                    Aov::Direct | Aov::Indirect => gamma_correct(&value, 0.5),
+                };
+                let clamped = Vec3::new_with_values(
+                    color.x().clamp(0.0, 1.0),
+                    color.y().clamp(0.0, 1.0),
+                    color.z().clamp(0.0, 1.0),
+                );
+                image.put_pixel(
+                    x,
+                    self.height - 1 - y,
+                    image::Rgb(convert_vec3_to_color(clamped)),
+                );
+            }
+        }
+        image
+    }
+
+    // Writes the linear beauty image as the R, G and B channels of an EXR file, with
+    // every layer in channels prefixed by its name, such as albedo.R or depth.Z.
+    pub fn write_exr(&self, beauty: &Film, path: impl AsRef<Path>) -> Result<()> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        // EXR scanlines run top down
+        let top_down = |values: &[Vec3], component: usize| {
+            (0..height)
+                .rev()
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| values[y * width + x][component] as f32)
+                .collect::<Vec<f32>>()
+        };
+
+        let beauty_values = beauty.pixels();
+        let mut channels = ["R", "G", "B"]
+            .iter()
+            .enumerate()
+            .map(|(component, name)| {
+                AnyChannel::new(*name, FlatSamples::F32(top_down(&beauty_values, component)))
+            })
+            .collect::<Vec<AnyChannel<FlatSamples>>>();
+        for aov in Aov::ALL.iter() {
+            let values = self.layer(*aov);
+            for (component, channel) in aov.channels().iter().enumerate() {
+                channels.push(AnyChannel::new(
+                    format!("{}.{}", aov.name(), channel).as_str(),
+                    FlatSamples::F32(top_down(&values, component)),
+                ));
+            }
+        }
+
+        let layer = Layer::new(
+            (width, height),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(channels)),
+        );
+        Image::from_layer(layer).write().to_file(path)
+    }
+}
+
+// Color that tells neighboring ids apart, black for the background
+fn id_color(id: usize) -> Vec3 {
+    if id == 0 {
+        return Vec3::new();
+    }
+    let mut hash = (id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    hash ^= hash >> 29;
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Vec3::new_with_values(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::films::box_filter::BoxFilter;
+
+    fn hit(depth: f64, material: usize, object: usize) -> AovSample {
+        AovSample {
+            depth,
+            position: Vec3::new_with_values(0.0, 0.0, -depth),
+            normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            albedo: Vec3::new_with_values(0.5, 0.25, 0.125),
+            material: Some(material),
+            object: Some(object),
+            direct: Vec3::new_with_values(0.5, 0.5, 0.5),
+            indirect: Vec3::new_with_values(0.25, 0.25, 0.25),
+        }
+    }
+
+    #[test]
+    fn test_layers() {
+        let film = AovFilm::new(2, 2);
+        film.add_sample(0, 0, &hit(2.0, 2, 3));
+        film.add_sample(0, 0, &hit(4.0, 1, 4));
+        film.add_sample(0, 0, &AovSample::new());
+        film.add_sample(1, 1, &hit(1.0, 0, 0));
+
+        // Geometry averages over the hits, shading over every sample
+        let depth = film.layer(Aov::Depth);
+        assert_eq!(depth[0].x(), 3.0);
+        assert_eq!(depth[1].x(), f64::INFINITY);
+        let albedo = film.layer(Aov::Albedo);
+        assert!((albedo[0] - Vec3::new_with_values(1.0, 0.5, 0.25) / 3.0).length() < 1e-12);
+        assert_eq!(
+            film.layer(Aov::Normal)[3],
+            Vec3::new_with_values(0.0, 0.0, 1.0)
+        );
+
+        // Ids come from the first hit
+        assert_eq!(film.layer(Aov::ObjectId)[0].x(), 4.0);
+        assert_eq!(film.layer(Aov::ObjectId)[1].x(), 0.0);
+        let materials = film.layer(Aov::MaterialId);
+        assert_eq!(materials[0].x(), 3.0);
+        assert_eq!(materials[1].x(), 0.0);
+        assert_eq!(materials[3].x(), 1.0);
+
+        let image = film.to_image(Aov::Depth);
+        assert_eq!(image.get_pixel(0, 1), &image::Rgb([25, 25, 25]));
+        assert_eq!(image.get_pixel(1, 0), &image::Rgb([255, 255, 255]));
+    }
+
+    #[test]
+    fn test_write_exr() {
+        let beauty = Film::new(3, 2, Box::new(BoxFilter::new(0.5)));
+        let film = AovFilm::new(3, 2);
+        let sample = hit(2.0, 0, 1);
+        for y in 0..2 {
+            for x in 0..3 {
+                beauty.add_sample(x as f64 + 0.5, y as f64 + 0.5, sample.beauty());
+                film.add_sample(x, y, &sample);
+            }
+        }
+
+        // Named after the process so that concurrent test runs do not share the file
+        let path = std::env::temp_dir().join(format!("aov_film_test_{}.exr", std::process::id()));
+        film.write_exr(&beauty, &path).unwrap();
+        let image = read_all_flat_layers_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let channels = &image.layer_data[0].channel_data.list;
+        let find = |name: &str| {
+            channels
+                .iter()
+                .find(|channel| channel.name == *name)
+                .unwrap_or_else(|| panic!("Missing channel {}", name))
+        };
+        assert_eq!(channels.len(), 3 + 1 + 3 + 3 + 3 + 1 + 1 + 3 + 3);
+        assert_eq!(find("R").sample_data.value_by_flat_index(0).to_f32(), 0.75);
+        assert_eq!(
+            find("depth.Z").sample_data.value_by_flat_index(5).to_f32(),
+            2.0
+        );
+        assert_eq!(
+            find("object_id.id")
+                .sample_data
+                .value_by_flat_index(0)
+                .to_f32(),
+            2.0
+        );
+        assert_eq!(
+            find("indirect.B")
+                .sample_data
+                .value_by_flat_index(3)
+                .to_f32(),
+            0.25
+        );
+    }
+}
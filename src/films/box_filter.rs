@@ -0,0 +1,26 @@
+use super::filter::Filter;
+
+// Equal weight for every sample within the radius. A radius of half a pixel averages
+// the samples inside each pixel.
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> Self {
+        BoxFilter { radius }
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        match x.abs() < self.radius && y.abs() < self.radius {
+            true => 1.0,
+            false => 0.0,
+        }
+    }
+}
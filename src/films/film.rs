@@ -0,0 +1,215 @@
+use std::sync::Mutex;
+
+use image::RgbImage;
+
+use crate::utils::image_utils::linear_to_image;
+use crate::vectors::vec3::Vec3;
+
+use super::filter::Filter;
+
+// Smallest share of the total filter weight a pixel's net weight may have. Below it the
+// negative lobes of sharpening filters cancel out most of the weight, and dividing by
+// what is left would blow the few samples up into fireflies.
+const MIN_WEIGHT_FRACTION: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+struct Pixel {
+    weighted_sum: Vec3,
+    weight: f64,
+    absolute_weight: f64,
+}
+
+// Image the renderer splats samples into. Each sample adds to every pixel within the
+// filter radius, and pixels resolve to their weighted average. Rows sit behind their own
+// locks, so threads rendering different rows rarely wait on each other.
+pub struct Film {
+    width: u32,
+    height: u32,
+    filter: Box<dyn Filter>,
+    rows: Vec<Mutex<Vec<Pixel>>>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Box<dyn Filter>) -> Self {
+        let empty = Pixel {
+            weighted_sum: Vec3::new(),
+            weight: 0.0,
+            absolute_weight: 0.0,
+        };
+        Film {
+            width,
+            height,
+            filter,
+            rows: (0..height)
+                .map(|_| Mutex::new(vec![empty; width as usize]))
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Adds a sample taken at (x, y) in pixels, with y running up from the bottom row as
+    // the camera's t does. Pixel (i, j) covers [i, i + 1) x [j, j + 1).
+    pub fn add_sample(&self, x: f64, y: f64, color: Vec3) {
+        let radius = self.filter.radius();
+        let range = |center: f64, size: u32| {
+            let first = (center - 0.5 - radius).ceil().max(0.0) as u32;
+            let last = (center - 0.5 + radius).floor().min(size as f64 - 1.0);
+            match last < 0.0 {
+                true => 0..0,
+                false => first..last as u32 + 1,
+            }
+        };
+        for row in range(y, self.height) {
+            let weights = range(x, self.width)
+                .map(|column| {
+                    let weight = self
+                        .filter
+                        .evaluate(column as f64 + 0.5 - x, row as f64 + 0.5 - y);
+                    (column as usize, weight)
+                })
+                .filter(|(_, weight)| *weight != 0.0)
+                .collect::<Vec<(usize, f64)>>();
+            if weights.is_empty() {
+                continue;
+            }
+            let mut pixels = self.rows[row as usize].lock().unwrap();
+            for (column, weight) in weights {
+                pixels[column].weighted_sum += weight * color;
+                pixels[column].weight += weight;
+                pixels[column].absolute_weight += weight.abs();
+            }
+        }
+    }
+
+    // Filtered color of a pixel, black where no sample reached or only the negative
+    // lobes of the filter did
+    pub fn pixel(&self, x: u32, y: u32) -> Vec3 {
+        let pixel = self.rows[y as usize].lock().unwrap()[x as usize];
+        match pixel.weight <= MIN_WEIGHT_FRACTION * pixel.absolute_weight {
+            true => Vec3::new(),
+            false => pixel.weighted_sum / pixel.weight,
+        }
+    }
+
+    // Filtered colors of every pixel, indexed by y * width + x
+    pub fn pixels(&self) -> Vec<Vec3> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel(x, y))
+            .collect()
+    }
+
+    // Gamma corrected image, with negative lobes of sharpening filters clamped to black
+    pub fn to_image(&self) -> RgbImage {
+        linear_to_image(self.width, self.height, &self.pixels())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::films::box_filter::BoxFilter;
+    use crate::films::gaussian_filter::GaussianFilter;
+    use crate::films::lanczos_filter::LanczosFilter;
+    use crate::films::mitchell_filter::MitchellFilter;
+    use crate::films::tent_filter::TentFilter;
+    use crate::utils::random_number_utils::random_f64;
+    use rayon::prelude::*;
+
+    fn filters() -> Vec<Box<dyn Filter>> {
+        vec![
+            Box::new(BoxFilter::new(0.5)),
+            Box::new(TentFilter::new(1.5)),
+            Box::new(GaussianFilter::new(1.5, 0.5)),
+            Box::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+            Box::new(LanczosFilter::new(3.0)),
+        ]
+    }
+
+    #[test]
+    fn test_filters() {
+        for filter in filters() {
+            let radius = filter.radius();
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+            assert!(filter.evaluate(0.3, 0.0) <= filter.evaluate(0.0, 0.0));
+            assert_eq!(filter.evaluate(radius, 0.0), 0.0);
+            assert_eq!(filter.evaluate(0.1, radius + 0.1), 0.0);
+            for (x, y) in [(0.2, -0.7), (1.1, 0.4)] {
+                assert_eq!(filter.evaluate(x, y), filter.evaluate(-x, y));
+                assert_eq!(filter.evaluate(x, y), filter.evaluate(y, x));
+            }
+        }
+
+        // The sharpening filters have negative lobes
+        let mitchell = MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0);
+        assert!(mitchell.evaluate(1.5, 0.0) < 0.0);
+        assert!(LanczosFilter::new(3.0).evaluate(1.5, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn test_splatting() {
+        // A sample splats into the pixels whose centers lie within the filter radius
+        let film = Film::new(6, 4, Box::new(TentFilter::new(1.5)));
+        let white = Vec3::new_with_values(1.0, 1.0, 1.0);
+        film.add_sample(2.5, 1.5, white);
+        assert_eq!(film.pixel(2, 1), white);
+        assert_eq!(film.pixel(3, 2), white);
+        assert_eq!(film.pixel(4, 1), Vec3::new());
+
+        // Closer samples weigh more
+        let red = Vec3::new_with_values(1.0, 0.0, 0.0);
+        film.add_sample(3.4, 1.5, red);
+        assert!(film.pixel(3, 1).y() < 0.5);
+        assert!(film.pixel(2, 1).y() > 0.5);
+
+        // The image is stored bottom up and written top down
+        let image = film.to_image();
+        assert_eq!(image.get_pixel(2, 2), &image::Rgb([255, 215, 215]));
+        assert_eq!(image.get_pixel(2, 0), &image::Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_negative_lobes() {
+        // Pixels only reached by the negative lobe of a sample stay black, as any
+        // weight left after cancellation could be arbitrarily small
+        let white = Vec3::new_with_values(1.0, 1.0, 1.0);
+        for filter in [
+            Box::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)) as Box<dyn Filter>,
+            Box::new(LanczosFilter::new(3.0)),
+        ] {
+            let film = Film::new(4, 1, filter);
+            film.add_sample(2.0, 0.5, white);
+            assert_eq!(film.pixel(0, 0), Vec3::new());
+            assert!((film.pixel(2, 0) - white).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_parallel_flat_field() {
+        // Every filter reproduces a flat image, whichever thread each sample came from
+        let color = Vec3::new_with_values(0.2, 0.4, 0.6);
+        for filter in filters() {
+            let film = Film::new(16, 8, filter);
+            (0..8).into_par_iter().for_each(|y| {
+                for x in 0..16 {
+                    for _ in 0..16 {
+                        let (dx, dy) = (random_f64(), random_f64());
+                        film.add_sample(x as f64 + dx, y as f64 + dy, color);
+                    }
+                }
+            });
+            for y in 0..8 {
+                for x in 0..16 {
+                    assert!((film.pixel(x, y) - color).length() < 1e-9);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,8 @@
+// Reconstruction filter weighting a sample's contribution to nearby pixels by its
+// offset from the pixel center, in pixels
+pub trait Filter: Sync + Send {
+    // Half width of the square outside of which the filter is zero
+    fn radius(&self) -> f64;
+
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
@@ -0,0 +1,29 @@
+use super::filter::Filter;
+
+// Gaussian with standard deviation sigma, shifted down so that it reaches zero at the
+// radius instead of being cut off
+pub struct GaussianFilter {
+    radius: f64,
+    sigma: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, sigma: f64) -> Self {
+        GaussianFilter { radius, sigma }
+    }
+
+    fn gaussian(&self, x: f64) -> f64 {
+        let g = |x: f64| (-x * x / (2.0 * self.sigma * self.sigma)).exp();
+        (g(x) - g(self.radius)).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
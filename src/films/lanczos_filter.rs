@@ -0,0 +1,36 @@
+use std::f64::consts::PI;
+
+use super::filter::Filter;
+
+// Sinc windowed by a wider sinc that reaches zero at the radius. The sharpest of the
+// filters, at the cost of some ringing around edges.
+pub struct LanczosFilter {
+    radius: f64,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f64) -> Self {
+        LanczosFilter { radius }
+    }
+
+    fn lanczos(&self, x: f64) -> f64 {
+        let sinc = |x: f64| match x.abs() < 1e-5 {
+            true => 1.0,
+            false => (PI * x).sin() / (PI * x),
+        };
+        match x.abs() < self.radius {
+            true => sinc(x) * sinc(x / self.radius),
+            false => 0.0,
+        }
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.lanczos(x) * self.lanczos(y)
+    }
+}
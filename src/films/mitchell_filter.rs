@@ -0,0 +1,46 @@
+use super::filter::Filter;
+
+// Mitchell-Netravali cubic, stretched over the radius. b = c = 1/3 balances blurring
+// against ringing, and b + 2c = 1 keeps flat regions flat.
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64, b: f64, c: f64) -> Self {
+        MitchellFilter { radius, b, c }
+    }
+
+    fn mitchell(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        // The cubic is defined over [-2, 2]
+        let x = (2.0 * x / self.radius).abs();
+        let value = match x {
+            x if x >= 2.0 => 0.0,
+            x if x >= 1.0 => {
+                (-b - 6.0 * c) * x.powi(3)
+                    + (6.0 * b + 30.0 * c) * x * x
+                    + (-12.0 * b - 48.0 * c) * x
+                    + (8.0 * b + 24.0 * c)
+            }
+            x => {
+                (12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                    + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                    + (6.0 - 2.0 * b)
+            }
+        };
+        value / 6.0
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell(x) * self.mitchell(y)
+    }
+}
@@ -0,0 +1,8 @@
+pub mod aov_film;
+pub mod box_filter;
+pub mod film;
+pub mod filter;
+pub mod gaussian_filter;
+pub mod lanczos_filter;
+pub mod mitchell_filter;
+pub mod tent_filter;
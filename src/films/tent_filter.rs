@@ -0,0 +1,26 @@
+use super::filter::Filter;
+
+// Weights falling off linearly to zero at the radius
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub fn new(radius: f64) -> Self {
+        TentFilter { radius }
+    }
+
+    fn tent(&self, x: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0)
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.tent(x) * self.tent(y)
+    }
+}
@@ -0,0 +1,134 @@
+use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Aabb {
+    pub minimum: Vec3,
+    pub maximum: Vec3,
+}
+
+impl Aabb {
+    pub fn new(minimum: Vec3, maximum: Vec3) -> Self {
+        Aabb { minimum, maximum }
+    }
+
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let mut minimum = Vec3::new_with_values(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut maximum = -minimum;
+        for point in points {
+            for axis in 0..3 {
+                minimum[axis] = minimum[axis].min(point[axis]);
+                maximum[axis] = maximum[axis].max(point[axis]);
+            }
+        }
+        Aabb { minimum, maximum }
+    }
+
+    pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Self {
+        Aabb::from_points(&[box0.minimum, box0.maximum, box1.minimum, box1.maximum])
+    }
+
+    // Tight box around a disk, padded so that axis aligned disks keep some volume
+    pub fn from_disk(center: &Vec3, normal: &Vec3, radius: f64) -> Self {
+        let normal = unit_vector(*normal);
+        let mut extent = Vec3::new();
+        for axis in 0..3 {
+            extent[axis] = radius * (1.0 - normal[axis] * normal[axis]).max(0.0).sqrt();
+        }
+        Aabb::new(*center - extent, *center + extent).padded(1e-4)
+    }
+
+    pub fn padded(&self, delta: f64) -> Self {
+        let padding = Vec3::new_with_values(delta, delta, delta);
+        Aabb::new(self.minimum - padding, self.maximum + padding)
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.maximum - self.minimum;
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_range(r, t_min, t_max).is_some()
+    }
+
+    // Part of [t_min, t_max] where the ray is inside the box
+    pub fn hit_range(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction()[axis];
+            let mut t0 = (self.minimum[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.maximum[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN appears when the ray lies in a slab plane, treat that as overlapping
+            if t0 > t_min {
+                t_min = t0;
+            }
+            if t1 < t_max {
+                t_max = t1;
+            }
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aabb_hit() {
+        let aabb = Aabb::new(
+            Vec3::new_with_values(-1.0, -1.0, -1.0),
+            Vec3::new_with_values(1.0, 1.0, 1.0),
+        );
+        let hitting = Ray {
+            orig: Vec3::new_with_values(0.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let missing = Ray {
+            orig: Vec3::new_with_values(2.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(aabb.hit(&hitting, 0.001, f64::INFINITY));
+        assert!(!aabb.hit(&hitting, 0.001, 3.0));
+        assert!(!aabb.hit(&missing, 0.001, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_surrounding_box() {
+        let box0 = Aabb::new(Vec3::new(), Vec3::new_with_values(1.0, 1.0, 1.0));
+        let box1 = Aabb::new(
+            Vec3::new_with_values(-2.0, 0.5, 0.0),
+            Vec3::new_with_values(0.0, 2.0, 0.5),
+        );
+        let surrounding = Aabb::surrounding_box(&box0, &box1);
+        assert_eq!(surrounding.minimum, Vec3::new_with_values(-2.0, 0.0, 0.0));
+        assert_eq!(surrounding.maximum, Vec3::new_with_values(1.0, 2.0, 1.0));
+        assert_eq!(surrounding.longest_axis(), 0);
+    }
+
+    #[test]
+    fn test_from_disk() {
+        let aabb = Aabb::from_disk(&Vec3::new(), &Vec3::new_with_values(0.0, 0.0, 2.0), 1.5);
+        assert!((aabb.maximum - Vec3::new_with_values(1.5, 1.5, 0.0)).length() < 1e-3);
+        assert!(aabb.maximum.z() > aabb.minimum.z());
+    }
+}
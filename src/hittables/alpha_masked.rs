@@ -0,0 +1,158 @@
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::rays::ray::Ray;
+use crate::textures::texture::Texture;
+use crate::utils::random_number_utils::random_f64;
+
+use std::option::Option;
+use std::sync::Arc;
+
+const MAX_SKIPPED_HITS: usize = 64;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AlphaMode {
+    // Hits where the mask is below the threshold are skipped
+    Cutout(f64),
+    // Hits are kept with a probability equal to the mask value
+    Stochastic,
+}
+
+impl AlphaMode {
+    pub fn passes(&self, mask: &dyn Texture, rec: &HitRecord) -> bool {
+        let alpha = mask.value(rec.u, rec.v, &rec.p).x();
+        match self {
+            AlphaMode::Cutout(threshold) => alpha >= *threshold,
+            AlphaMode::Stochastic => random_f64() < alpha,
+        }
+    }
+}
+
+// Applies an opacity mask to any primitive. Rays continue through masked out hits to
+// the next surface of the same primitive, and the caller then finds whatever lies behind.
+pub struct AlphaMasked {
+    object: Box<dyn Hittable>,
+    mask: Arc<dyn Texture>,
+    mode: AlphaMode,
+}
+
+impl AlphaMasked {
+    pub fn new(object: Box<dyn Hittable>, mask: Arc<dyn Texture>, mode: AlphaMode) -> Self {
+        AlphaMasked { object, mask, mode }
+    }
+}
+
+impl Hittable for AlphaMasked {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut t_start = t_min;
+        for _ in 0..MAX_SKIPPED_HITS {
+            let rec = self.object.hit(r, t_start, t_max)?;
+            if self.mode.passes(self.mask.as_ref(), &rec) {
+                return Some(rec);
+            }
+            t_start = rec.t + 1e-9 * rec.t.abs().max(1.0);
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::{hittable_list::HittableList, sphere::Sphere};
+    use crate::materials::{alpha_mask::AlphaMask, lambertian::Lambertian};
+    use crate::textures::solid_color::SolidColor;
+    use crate::vectors::vec3::Vec3;
+
+    // Opaque for u >= 0.5, which covers the half of the sphere facing -z
+    struct HalfMask;
+
+    impl Texture for HalfMask {
+        fn value(&self, u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+            match u >= 0.5 {
+                true => Vec3::new_with_values(1.0, 1.0, 1.0),
+                false => Vec3::new(),
+            }
+        }
+    }
+
+    fn grey() -> Arc<Lambertian> {
+        Arc::new(Lambertian {
+            albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+        })
+    }
+
+    fn ray_towards_minus_z() -> Ray {
+        Ray {
+            orig: Vec3::new_with_values(0.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_cutout_skips_to_next_object() {
+        let mut world = HittableList::new();
+        world.add(Box::new(AlphaMasked::new(
+            Box::new(Sphere::new(Vec3::new(), 1.0, grey())),
+            Arc::new(SolidColor::new_with_values(0.2, 0.2, 0.2)),
+            AlphaMode::Cutout(0.5),
+        )));
+        world.add(Box::new(Sphere::new(
+            Vec3::new_with_values(0.0, 0.0, -5.0),
+            1.0,
+            grey(),
+        )));
+        let rec = world
+            .hit(&ray_towards_minus_z(), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.t - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cutout_continues_to_far_side() {
+        // The near side (u < 0.5) is cut out, so the ray hits the inside of the far side
+        let sphere = AlphaMasked::new(
+            Box::new(Sphere::new(Vec3::new(), 1.0, grey())),
+            Arc::new(HalfMask),
+            AlphaMode::Cutout(0.5),
+        );
+        let rec = sphere
+            .hit(&ray_towards_minus_z(), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-9);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn test_alpha_mask_material() {
+        let material = Arc::new(AlphaMask {
+            base: grey(),
+            mask: Arc::new(SolidColor::new_with_values(0.0, 0.0, 0.0)),
+            mode: AlphaMode::Cutout(0.5),
+        });
+        let sphere = Sphere::new(Vec3::new(), 1.0, material);
+        assert!(sphere
+            .hit(&ray_towards_minus_z(), 0.001, f64::INFINITY)
+            .is_none());
+    }
+
+    #[test]
+    fn test_stochastic_pass_through_rate() {
+        let sphere = AlphaMasked::new(
+            Box::new(Sphere::new(Vec3::new(), 1.0, grey())),
+            Arc::new(SolidColor::new_with_values(0.5, 0.5, 0.5)),
+            AlphaMode::Stochastic,
+        );
+        let samples = 10000;
+        let front_hits = (0..samples)
+            .filter_map(|_| sphere.hit(&ray_towards_minus_z(), 0.001, f64::INFINITY))
+            .filter(|rec| rec.front_face)
+            .count();
+        let rate = front_hits as f64 / samples as f64;
+        assert!((rate - 0.5).abs() < 0.03);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::hittables::hittable_list::HittableList;
+use crate::hittables::quad::Quad;
+use crate::materials::material::Material;
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Axis aligned box made of six outward facing quads
+pub struct BoxShape {
+    sides: HittableList,
+}
+
+impl BoxShape {
+    pub fn new(a: Vec3, b: Vec3, mat_ptr: Arc<dyn Material>) -> Self {
+        let min = Vec3::new_with_values(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+        let max = Vec3::new_with_values(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+        let dx = Vec3::new_with_values(max.x() - min.x(), 0.0, 0.0);
+        let dy = Vec3::new_with_values(0.0, max.y() - min.y(), 0.0);
+        let dz = Vec3::new_with_values(0.0, 0.0, max.z() - min.z());
+
+        let mut sides = HittableList::new();
+        let faces = [
+            (Vec3::new_with_values(min.x(), min.y(), max.z()), dx, dy),
+            (Vec3::new_with_values(max.x(), min.y(), max.z()), -dz, dy),
+            (Vec3::new_with_values(max.x(), min.y(), min.z()), -dx, dy),
+            (Vec3::new_with_values(min.x(), min.y(), min.z()), dz, dy),
+            (Vec3::new_with_values(min.x(), max.y(), max.z()), dx, -dz),
+            (Vec3::new_with_values(min.x(), min.y(), min.z()), dx, dz),
+        ];
+        for (q, u, v) in faces {
+            sides.add(Box::new(Quad::new(q, u, v, mat_ptr.clone())));
+        }
+        BoxShape { sides }
+    }
+}
+
+impl Hittable for BoxShape {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.sides.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.sides.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.sides.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Vec3) -> Vec3 {
+        self.sides.random(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_box_faces_point_outwards() {
+        let box_shape = BoxShape::new(
+            Vec3::new_with_values(1.0, 1.0, 1.0),
+            Vec3::new_with_values(-1.0, -1.0, -1.0),
+            Arc::new(TestMaterial),
+        );
+        let directions = [
+            Vec3::new_with_values(1.0, 0.0, 0.0),
+            Vec3::new_with_values(-1.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            Vec3::new_with_values(0.0, -1.0, 0.0),
+            Vec3::new_with_values(0.0, 0.0, 1.0),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+        ];
+        for direction in directions {
+            let outside = Ray {
+                orig: 5.0 * direction,
+                dir: -direction,
+                time: 0.0,
+            };
+            let rec = box_shape.hit(&outside, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(rec.t, 4.0);
+            assert!(rec.front_face);
+            assert_eq!(rec.normal, direction);
+
+            let inside = Ray {
+                orig: Vec3::new(),
+                dir: direction,
+                time: 0.0,
+            };
+            let rec = box_shape.hit(&inside, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(rec.t, 1.0);
+            assert!(!rec.front_face);
+        }
+    }
+}
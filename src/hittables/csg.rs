@@ -0,0 +1,245 @@
+use crate::rays::ray::Ray;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+
+use std::option::Option;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    // The left object with the right one carved out of it
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// Boolean combination of two closed objects. Surfaces keep the material of the object
+// they come from, so a difference shows the carving object's material inside the cut.
+pub struct Csg {
+    left: Box<dyn Hittable>,
+    right: Box<dyn Hittable>,
+    operation: CsgOperation,
+}
+
+impl Csg {
+    pub fn new(left: Box<dyn Hittable>, right: Box<dyn Hittable>, operation: CsgOperation) -> Self {
+        Csg {
+            left,
+            right,
+            operation,
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.intervals(r)
+            .into_iter()
+            .flat_map(|interval| [interval.enter, interval.exit])
+            .find(|rec| t_min <= rec.t && rec.t <= t_max)
+    }
+
+    // Sweeps the boundaries of both operands in order, tracking which of them the ray is
+    // inside, and keeps the boundaries where the combined inside state flips
+    fn intervals(&self, r: &Ray) -> Vec<HitInterval> {
+        let mut events = vec![];
+        for (is_left, object) in [(true, &self.left), (false, &self.right)] {
+            for interval in object.intervals(r) {
+                events.push((is_left, true, interval.enter));
+                events.push((is_left, false, interval.exit));
+            }
+        }
+        events.sort_by(|a, b| a.2.t.total_cmp(&b.2.t));
+
+        let mut intervals = vec![];
+        let (mut in_left, mut in_right) = (false, false);
+        let mut enter: Option<HitRecord> = None;
+        for (is_left, entering, mut rec) in events {
+            let was_inside = self.operation.inside(in_left, in_right);
+            match is_left {
+                true => in_left = entering,
+                false => in_right = entering,
+            }
+            let inside = self.operation.inside(in_left, in_right);
+            if inside == was_inside {
+                continue;
+            }
+            // Normals always face the incoming ray, only the side flag can change here,
+            // as when leaving the carved out object means entering the difference
+            rec.front_face = inside;
+            match (inside, enter.take()) {
+                (true, _) => enter = Some(rec),
+                (false, Some(enter)) => intervals.push(HitInterval { enter, exit: rec }),
+                (false, None) => {}
+            }
+        }
+        intervals
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.operation {
+            CsgOperation::Union => {
+                let left = self.left.bounding_box()?;
+                let right = self.right.bounding_box()?;
+                Some(Aabb::surrounding_box(&left, &right))
+            }
+            CsgOperation::Intersection => match self.left.bounding_box() {
+                Some(left) => Some(left),
+                None => self.right.bounding_box(),
+            },
+            CsgOperation::Difference => self.left.bounding_box(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::{box_shape::BoxShape, sphere::Sphere};
+    use crate::materials::material::Material;
+    use crate::vectors::vec3::Vec3;
+    use std::sync::Arc;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn sphere_at(x: f64, radius: f64) -> Box<dyn Hittable> {
+        Box::new(Sphere::new(
+            Vec3::new_with_values(x, 0.0, 0.0),
+            radius,
+            Arc::new(TestMaterial),
+        ))
+    }
+
+    fn along_x(x: f64) -> Ray {
+        Ray {
+            orig: Vec3::new_with_values(x, 0.0, 0.0),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_lens_intersection() {
+        // Two spheres overlapping between x = -0.5 and x = 0.5
+        let lens = Csg::new(
+            sphere_at(-1.5, 2.0),
+            sphere_at(1.5, 2.0),
+            CsgOperation::Intersection,
+        );
+        let rec = lens.hit(&along_x(-5.0), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(-1.0, 0.0, 0.0)).length() < 1e-9);
+
+        let rec = lens.hit(&along_x(0.0), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        let beside = Ray {
+            orig: Vec3::new_with_values(-5.0, 1.9, 0.0),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(lens.hit(&beside, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_union_skips_inner_surfaces() {
+        let union = Csg::new(
+            sphere_at(-1.0, 2.0),
+            sphere_at(1.0, 2.0),
+            CsgOperation::Union,
+        );
+        let intervals = union.intervals(&along_x(-5.0));
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].enter.t - 2.0).abs() < 1e-9);
+        assert!((intervals[0].exit.t - 8.0).abs() < 1e-9);
+
+        let rec = union.hit(&along_x(0.0), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_difference_carves_a_hole() {
+        // A cube with a sphere scooped out of its +x face
+        let carved = Csg::new(
+            Box::new(BoxShape::new(
+                Vec3::new_with_values(-1.0, -1.0, -1.0),
+                Vec3::new_with_values(1.0, 1.0, 1.0),
+                Arc::new(TestMaterial),
+            )),
+            sphere_at(1.0, 0.5),
+            CsgOperation::Difference,
+        );
+        let into_hole = Ray {
+            orig: Vec3::new_with_values(5.0, 0.0, 0.0),
+            dir: Vec3::new_with_values(-1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let rec = carved.hit(&into_hole, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(1.0, 0.0, 0.0)).length() < 1e-9);
+
+        let past_hole = Ray {
+            orig: Vec3::new_with_values(5.0, 0.8, 0.0),
+            dir: Vec3::new_with_values(-1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let rec = carved.hit(&past_hole, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+
+        // Nested operations see the carved shape through its intervals
+        let sliced = Csg::new(
+            Box::new(carved),
+            sphere_at(0.0, 1.2),
+            CsgOperation::Intersection,
+        );
+        let rec = sliced.hit(&past_hole, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - (5.0 - (1.44f64 - 0.64).sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nan_crossings() {
+        // A degenerate child reporting a NaN crossing must not bring the union down
+        struct Degenerate(Box<dyn Hittable>);
+
+        impl Hittable for Degenerate {
+            fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+                None
+            }
+
+            fn intervals(&self, r: &Ray) -> Vec<HitInterval> {
+                let mut intervals = self.0.intervals(r);
+                for interval in intervals.iter_mut() {
+                    interval.enter.t = f64::NAN;
+                }
+                intervals
+            }
+        }
+
+        let union = Csg::new(
+            sphere_at(0.0, 1.0),
+            Box::new(Degenerate(sphere_at(3.0, 1.0))),
+            CsgOperation::Union,
+        );
+        let rec = union.hit(&along_x(-5.0), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,447 @@
+use crate::rays::ray::Ray;
+use crate::vectors::onb::Onb;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::hittables::hittable_list::HittableList;
+use crate::materials::material::Material;
+use crate::utils::vec3_utils::*;
+
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::option::Option;
+use std::sync::Arc;
+
+const MAX_SUBDIVISION_DEPTH: f64 = 10.0;
+
+#[derive(Debug)]
+pub enum CurveError {
+    MismatchedWidths { points: usize, widths: usize },
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurveError::MismatchedWidths { points, widths } => write!(
+                f,
+                "strand through {} points needs as many widths, got {}",
+                points, widths
+            ),
+        }
+    }
+}
+
+impl Error for CurveError {}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CurveType {
+    // Flat strip that always faces the incoming ray
+    Ribbon,
+    // The same strip with normals bent across its width as if it were a tube
+    Cylinder,
+}
+
+// Cubic Bezier strand whose width changes linearly from one end to the other. u runs
+// along the strand and v across it, which is what hair materials use as the offset
+// from the fiber center.
+pub struct Curve {
+    control_points: [Vec3; 4],
+    widths: (f64, f64),
+    u_range: (f64, f64),
+    curve_type: CurveType,
+    mat_ptr: Arc<dyn Material>,
+}
+
+fn lerp(t: f64, a: Vec3, b: Vec3) -> Vec3 {
+    (1.0 - t) * a + t * b
+}
+
+// Point and derivative by de Casteljau's algorithm
+fn eval_bezier(cp: &[Vec3; 4], u: f64) -> (Vec3, Vec3) {
+    let cp1 = [
+        lerp(u, cp[0], cp[1]),
+        lerp(u, cp[1], cp[2]),
+        lerp(u, cp[2], cp[3]),
+    ];
+    let cp2 = [lerp(u, cp1[0], cp1[1]), lerp(u, cp1[1], cp1[2])];
+    let derivative = match (cp2[1] - cp2[0]).length_squared() > 0.0 {
+        true => 3.0 * (cp2[1] - cp2[0]),
+        false => cp[3] - cp[0],
+    };
+    (lerp(u, cp2[0], cp2[1]), derivative)
+}
+
+fn split_bezier(cp: &[Vec3; 4]) -> ([Vec3; 4], [Vec3; 4]) {
+    let middle = (cp[0] + 3.0 * cp[1] + 3.0 * cp[2] + cp[3]) / 8.0;
+    (
+        [
+            cp[0],
+            (cp[0] + cp[1]) / 2.0,
+            (cp[0] + 2.0 * cp[1] + cp[2]) / 4.0,
+            middle,
+        ],
+        [
+            middle,
+            (cp[1] + 2.0 * cp[2] + cp[3]) / 4.0,
+            (cp[2] + cp[3]) / 2.0,
+            cp[3],
+        ],
+    )
+}
+
+// Closest hit found so far, in ray space where z is the distance along the ray
+struct CurveHit {
+    u: f64,
+    v: f64,
+    z: f64,
+}
+
+impl Curve {
+    pub fn new(
+        control_points: [Vec3; 4],
+        width0: f64,
+        width1: f64,
+        curve_type: CurveType,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Curve {
+            control_points,
+            widths: (width0, width1),
+            u_range: (0.0, 1.0),
+            curve_type,
+            mat_ptr,
+        }
+    }
+
+    // Smooth strand through points, one Catmull-Rom segment between each pair of
+    // neighbours, with a width per point. u runs from 0 to 1 over the whole strand.
+    pub fn strand(
+        points: &[Vec3],
+        widths: &[f64],
+        curve_type: CurveType,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Result<HittableList, CurveError> {
+        if widths.len() != points.len() {
+            return Err(CurveError::MismatchedWidths {
+                points: points.len(),
+                widths: widths.len(),
+            });
+        }
+
+        let mut strand = HittableList::new();
+        let segments = points.len().saturating_sub(1);
+        for i in 0..segments {
+            let previous = points[i.saturating_sub(1)];
+            let next = points[(i + 2).min(points.len() - 1)];
+            let (p1, p2) = (points[i], points[i + 1]);
+            strand.add(Box::new(Curve {
+                control_points: [p1, p1 + (p2 - previous) / 6.0, p2 - (next - p1) / 6.0, p2],
+                widths: (widths[i], widths[i + 1]),
+                u_range: (i as f64 / segments as f64, (i + 1) as f64 / segments as f64),
+                curve_type,
+                mat_ptr: mat_ptr.clone(),
+            }));
+        }
+        Ok(strand)
+    }
+
+    fn width_at(&self, u: f64) -> f64 {
+        (1.0 - u) * self.widths.0 + u * self.widths.1
+    }
+
+    // Subdivides the curve until the pieces are close to straight, discarding pieces
+    // whose padded bounds miss the ray, then tests the remaining segments as flat strips
+    fn recursive_intersect(
+        &self,
+        cp: &[Vec3; 4],
+        u0: f64,
+        u1: f64,
+        depth: usize,
+        (z_min, z_limit): (f64, f64),
+        closest: &mut Option<CurveHit>,
+    ) {
+        let z_max = closest.as_ref().map_or(z_limit, |hit| hit.z);
+        let half_width = 0.5 * self.width_at(u0).max(self.width_at(u1));
+        let bounds = Aabb::from_points(cp).padded(half_width);
+        if bounds.minimum.x() > 0.0
+            || bounds.maximum.x() < 0.0
+            || bounds.minimum.y() > 0.0
+            || bounds.maximum.y() < 0.0
+            || bounds.maximum.z() < z_min
+            || bounds.minimum.z() > z_max
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let (first, second) = split_bezier(cp);
+            let middle = 0.5 * (u0 + u1);
+            let z_range = (z_min, z_limit);
+            self.recursive_intersect(&first, u0, middle, depth - 1, z_range, closest);
+            self.recursive_intersect(&second, middle, u1, depth - 1, z_range, closest);
+            return;
+        }
+
+        // The ray passes the origin of ray space, so it must lie between the planes
+        // through each end perpendicular to the segment
+        let start_edge = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        let end_edge = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if start_edge < 0.0 || end_edge < 0.0 {
+            return;
+        }
+
+        let segment = Vec3::new_with_values(cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y(), 0.0);
+        let denominator = segment.length_squared();
+        if denominator == 0.0 {
+            return;
+        }
+        let w = (-cp[0].x() * segment.x() - cp[0].y() * segment.y()) / denominator;
+        let u = ((1.0 - w) * u0 + w * u1).clamp(u0, u1);
+        let width = self.width_at(u);
+        let (pc, dpcdw) = eval_bezier(cp, w.clamp(0.0, 1.0));
+        let distance_squared = pc.x() * pc.x() + pc.y() * pc.y();
+        if distance_squared > 0.25 * width * width || pc.z() < z_min || pc.z() > z_max {
+            return;
+        }
+
+        let distance = distance_squared.sqrt();
+        let edge = dpcdw.x() * -pc.y() + pc.x() * dpcdw.y();
+        let v = match edge > 0.0 {
+            true => 0.5 + distance / width,
+            false => 0.5 - distance / width,
+        };
+        *closest = Some(CurveHit { u, v, z: pc.z() });
+    }
+
+    fn hit_record(&self, r: &Ray, t: f64, u: f64, v: f64) -> HitRecord {
+        let (_, dpdu) = eval_bezier(&self.control_points, u);
+        let direction = unit_vector(r.direction());
+        let tangent = match dpdu.near_zero() {
+            true => perpendicular(&direction),
+            false => unit_vector(dpdu),
+        };
+        // side points towards increasing v, across the strip and perpendicular to the ray
+        let side = cross(&direction, &tangent);
+        let side = match side.near_zero() {
+            true => perpendicular(&tangent),
+            false => unit_vector(side),
+        };
+        let facing = unit_vector(cross(&side, &tangent));
+        let outward_normal = match self.curve_type {
+            CurveType::Ribbon => facing,
+            CurveType::Cylinder => {
+                let theta = (v - 0.5) * PI;
+                theta.cos() * facing + theta.sin() * side
+            }
+        };
+
+        let mut hit_record = HitRecord {
+            t,
+            u: (1.0 - u) * self.u_range.0 + u * self.u_range.1,
+            v: v.clamp(0.0, 1.0),
+            p: r.at(t),
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent,
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &outward_normal);
+        hit_record
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let length = r.direction().length();
+        let frame = Onb::from_w(&r.direction());
+        let cp = self
+            .control_points
+            .map(|p| frame.to_local(&(p - r.origin())));
+
+        // Enough subdivisions to keep the flattened pieces within a fraction of the
+        // width of the true curve
+        let mut curvature: f64 = 0.0;
+        for i in 0..2 {
+            let second_difference = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            for axis in 0..3 {
+                curvature = curvature.max(second_difference[axis].abs());
+            }
+        }
+        let epsilon = 0.05 * self.widths.0.max(self.widths.1);
+        let levels = (2.0f64.sqrt() * 6.0 * curvature / (8.0 * epsilon)).log2() / 2.0;
+        let depth = match levels.is_finite() {
+            true => levels.round().clamp(0.0, MAX_SUBDIVISION_DEPTH) as usize,
+            false => 0,
+        };
+
+        let mut closest = None;
+        let z_range = (t_min * length, t_max * length);
+        self.recursive_intersect(&cp, 0.0, 1.0, depth, z_range, &mut closest);
+        let hit = closest?;
+
+        let hit_record = self.hit_record(r, hit.z / length, hit.u, hit.v);
+        match hit_record.mat_ptr.opaque(&hit_record) {
+            true => Some(hit_record),
+            false => None,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let half_width = 0.5 * self.widths.0.max(self.widths.1);
+        Some(Aabb::from_points(&self.control_points).padded(half_width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn straight(width0: f64, width1: f64, curve_type: CurveType) -> Curve {
+        Curve::new(
+            [
+                Vec3::new(),
+                Vec3::new_with_values(1.0, 0.0, 0.0),
+                Vec3::new_with_values(2.0, 0.0, 0.0),
+                Vec3::new_with_values(3.0, 0.0, 0.0),
+            ],
+            width0,
+            width1,
+            curve_type,
+            Arc::new(TestMaterial),
+        )
+    }
+
+    fn looking_at(x: f64, y: f64) -> Ray {
+        Ray {
+            orig: Vec3::new_with_values(x, y, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_ribbon_hit() {
+        let curve = straight(0.1, 0.1, CurveType::Ribbon);
+        let rec = curve
+            .hit(&looking_at(1.5, 0.0), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((rec.tangent - Vec3::new_with_values(1.0, 0.0, 0.0)).length() < 1e-9);
+
+        let rec = curve
+            .hit(&looking_at(1.5, 0.04), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.v - 0.5).abs() > 0.39 && (rec.v - 0.5).abs() < 0.41);
+        assert!(curve
+            .hit(&looking_at(1.5, 0.06), 0.001, f64::INFINITY)
+            .is_none());
+        assert!(curve
+            .hit(&looking_at(3.1, 0.0), 0.001, f64::INFINITY)
+            .is_none());
+        assert!(curve.hit(&looking_at(1.5, 0.0), 0.001, 4.0).is_none());
+    }
+
+    #[test]
+    fn test_cylinder_normals_bend_across_the_width() {
+        let curve = straight(0.1, 0.1, CurveType::Cylinder);
+        let above = curve
+            .hit(&looking_at(1.5, 0.045), 0.001, f64::INFINITY)
+            .unwrap();
+        let below = curve
+            .hit(&looking_at(1.5, -0.045), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!(above.normal.y() > 0.9 && below.normal.y() < -0.9);
+        assert!(above.front_face && below.front_face);
+        assert!(dot(&above.normal, &above.tangent).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tapered_width() {
+        let curve = straight(0.2, 0.0, CurveType::Ribbon);
+        assert!(curve
+            .hit(&looking_at(0.3, 0.08), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(curve
+            .hit(&looking_at(2.7, 0.08), 0.001, f64::INFINITY)
+            .is_none());
+    }
+
+    #[test]
+    fn test_thin_curved_curve() {
+        // A hair thin, strongly bent curve, aimed at along its length
+        let control_points = [
+            Vec3::new_with_values(1.0, 0.0, 0.0),
+            Vec3::new_with_values(1.0, 1.3, 0.2),
+            Vec3::new_with_values(-1.0, 1.3, -0.2),
+            Vec3::new_with_values(-1.0, 0.0, 0.0),
+        ];
+        let curve = Curve::new(
+            control_points,
+            1e-3,
+            1e-3,
+            CurveType::Cylinder,
+            Arc::new(TestMaterial),
+        );
+        for i in 1..40 {
+            let u = i as f64 / 40.0;
+            let (p, _) = eval_bezier(&control_points, u);
+            let rec = curve.hit(&looking_at(p.x(), p.y()), 0.001, f64::INFINITY);
+            assert!(rec.is_some(), "missed the curve at u = {}", u);
+            let rec = rec.unwrap();
+            assert!((rec.u - u).abs() < 0.01);
+            assert!((rec.p.z() - p.z()).abs() < 0.01);
+        }
+        assert!(curve
+            .hit(&looking_at(0.0, 0.5), 0.001, f64::INFINITY)
+            .is_none());
+    }
+
+    #[test]
+    fn test_strand_passes_through_its_points() {
+        let points: Vec<Vec3> = (0..=8)
+            .map(|i| {
+                let angle = PI * i as f64 / 8.0;
+                Vec3::new_with_values(angle.cos(), angle.sin(), 0.0)
+            })
+            .collect();
+        let widths: Vec<f64> = (0..=8).map(|i| 0.01 - 0.001 * i as f64).collect();
+        let strand =
+            Curve::strand(&points, &widths, CurveType::Ribbon, Arc::new(TestMaterial)).unwrap();
+        for (i, point) in points.iter().enumerate().skip(1).take(7) {
+            let rec = strand
+                .hit(&looking_at(point.x(), point.y()), 0.001, f64::INFINITY)
+                .unwrap();
+            assert!((rec.u - i as f64 / 8.0).abs() < 1e-6);
+        }
+        let bbox = strand.bounding_box().unwrap();
+        assert!(bbox.minimum.x() < -1.0 && bbox.maximum.y() > 1.0);
+    }
+
+    #[test]
+    fn test_strand_needs_a_width_per_point() {
+        let points = [Vec3::new(), Vec3::new_with_values(1.0, 0.0, 0.0)];
+        match Curve::strand(&points, &[0.01], CurveType::Ribbon, Arc::new(TestMaterial)) {
+            Err(CurveError::MismatchedWidths { points, widths }) => {
+                assert_eq!((points, widths), (2, 1))
+            }
+            _ => panic!("expected a mismatched widths error"),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::rays::ray::Ray;
+use crate::vectors::onb::Onb;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::utils::polynomial_utils::solve_quadratic;
+
+use std::f64::consts::PI;
+use std::option::Option;
+use std::sync::Arc;
+
+// Solid cylinder from base to top, closed by a disk at either end
+pub struct Cylinder {
+    base: Vec3,
+    height: f64,
+    radius: f64,
+    frame: Onb,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(base: Vec3, top: Vec3, radius: f64, mat_ptr: Arc<dyn Material>) -> Self {
+        Cylinder {
+            base,
+            height: (top - base).length(),
+            radius,
+            frame: Onb::from_w(&(top - base)),
+            mat_ptr,
+        }
+    }
+
+    // Hits in the local frame where the axis is z and the base sits at the origin, as
+    // (t, outward normal) pairs in no particular order
+    fn local_hits(&self, o: &Vec3, d: &Vec3) -> Vec<(f64, Vec3)> {
+        let mut hits = vec![];
+        let a = d.x() * d.x() + d.y() * d.y();
+        if a > 0.0 {
+            let b = 2.0 * (o.x() * d.x() + o.y() * d.y());
+            let c = o.x() * o.x() + o.y() * o.y() - self.radius * self.radius;
+            for t in solve_quadratic(a, b, c) {
+                let p = *o + t * *d;
+                if (0.0..=self.height).contains(&p.z()) {
+                    let normal = Vec3::new_with_values(p.x(), p.y(), 0.0) / self.radius;
+                    hits.push((t, normal));
+                }
+            }
+        }
+        if d.z() != 0.0 {
+            for (z, normal_z) in [(0.0, -1.0), (self.height, 1.0)] {
+                let t = (z - o.z()) / d.z();
+                let p = *o + t * *d;
+                if p.x() * p.x() + p.y() * p.y() <= self.radius * self.radius {
+                    hits.push((t, Vec3::new_with_values(0.0, 0.0, normal_z)));
+                }
+            }
+        }
+        hits
+    }
+
+    fn hit_record(&self, r: &Ray, t: f64, local: &Vec3, local_normal: &Vec3) -> HitRecord {
+        let rho = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        let phi = local.y().atan2(local.x()).rem_euclid(2.0 * PI);
+        // v climbs the side and runs outwards from the center on the caps
+        let v = match local_normal.z() == 0.0 {
+            true => local.z() / self.height,
+            false => rho / self.radius,
+        };
+        let tangent = match rho > 0.0 {
+            true => Vec3::new_with_values(-local.y() / rho, local.x() / rho, 0.0),
+            false => Vec3::new_with_values(1.0, 0.0, 0.0),
+        };
+        let mut hit_record = HitRecord {
+            t,
+            u: phi / (2.0 * PI),
+            v,
+            p: r.at(t),
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: self.frame.to_world(&tangent),
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &self.frame.to_world(local_normal));
+        hit_record
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = self.frame.to_local(&(r.origin() - self.base));
+        let d = self.frame.to_local(&r.direction());
+        let mut hits = self.local_hits(&o, &d);
+        hits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        for (t, local_normal) in hits {
+            if t < t_min || t_max < t {
+                continue;
+            }
+            let hit_record = self.hit_record(r, t, &(o + t * d), &local_normal);
+            if hit_record.mat_ptr.opaque(&hit_record) {
+                return Some(hit_record);
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.base + self.height * self.frame.w;
+        Some(Aabb::surrounding_box(
+            &Aabb::from_disk(&self.base, &self.frame.w, self.radius),
+            &Aabb::from_disk(&top, &self.frame.w, self.radius),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn upright_cylinder() -> Cylinder {
+        Cylinder::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 2.0, 0.0),
+            1.0,
+            Arc::new(TestMaterial),
+        )
+    }
+
+    #[test]
+    fn test_cylinder_side_and_caps() {
+        let cylinder = upright_cylinder();
+        let side = Ray {
+            orig: Vec3::new_with_values(0.0, 1.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = cylinder.hit(&side, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(0.0, 0.0, 1.0)).length() < 1e-12);
+        assert!((rec.v - 0.5).abs() < 1e-12);
+
+        let top = Ray {
+            orig: Vec3::new_with_values(0.5, 5.0, 0.0),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let rec = cylinder.hit(&top, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-12);
+        assert!((rec.normal - Vec3::new_with_values(0.0, 1.0, 0.0)).length() < 1e-12);
+
+        // Through the rim of the top cap, hitting the cap before the side
+        let corner = Ray {
+            orig: Vec3::new_with_values(0.0, 3.0, 2.0),
+            dir: Vec3::new_with_values(0.0, -1.0, -1.0),
+            time: 0.0,
+        };
+        let rec = cylinder.hit(&corner, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Vec3::new_with_values(0.0, 2.0, 1.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_cylinder_inside_and_grazing() {
+        let cylinder = upright_cylinder();
+        let inside = Ray {
+            orig: Vec3::new_with_values(0.0, 1.0, 0.0),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let rec = cylinder.hit(&inside, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!(!rec.front_face);
+
+        let grazing = |x: f64| Ray {
+            orig: Vec3::new_with_values(x, 1.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(cylinder
+            .hit(&grazing(1.0 - 1e-6), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(cylinder
+            .hit(&grazing(1.0 + 1e-6), 0.001, f64::INFINITY)
+            .is_none());
+
+        // Parallel to the axis just outside the side
+        let parallel = Ray {
+            orig: Vec3::new_with_values(1.0 + 1e-6, 5.0, 0.0),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cylinder.hit(&parallel, 0.001, f64::INFINITY).is_none());
+
+        let bbox = cylinder.bounding_box().unwrap();
+        assert!((bbox.maximum - Vec3::new_with_values(1.0, 2.0, 1.0)).length() < 1e-3);
+        assert!((bbox.minimum - Vec3::new_with_values(-1.0, 0.0, -1.0)).length() < 1e-3);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::rays::ray::Ray;
+use crate::vectors::onb::Onb;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::utils::vec3_utils::*;
+
+use std::f64::consts::PI;
+use std::option::Option;
+use std::sync::Arc;
+
+pub struct Disk {
+    center: Vec3,
+    radius: f64,
+    frame: Onb,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, mat_ptr: Arc<dyn Material>) -> Self {
+        Disk {
+            center,
+            radius,
+            frame: Onb::from_w(&normal),
+            mat_ptr,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denominator = dot(&self.frame.w, &r.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = dot(&(self.center - r.origin()), &self.frame.w) / denominator;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let hit_point = r.at(t);
+        let local = self.frame.to_local(&(hit_point - self.center));
+        let rho = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        if rho > self.radius {
+            return None;
+        }
+
+        // u runs around the disk and v outwards from the center
+        let phi = local.y().atan2(local.x()).rem_euclid(2.0 * PI);
+        let tangent = match rho > 0.0 {
+            true => self.frame.to_world(&Vec3::new_with_values(
+                -local.y() / rho,
+                local.x() / rho,
+                0.0,
+            )),
+            false => self.frame.u,
+        };
+        let mut hit_record = HitRecord {
+            t,
+            u: phi / (2.0 * PI),
+            v: rho / self.radius,
+            p: hit_point,
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent,
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &self.frame.w);
+        match hit_record.mat_ptr.opaque(&hit_record) {
+            true => Some(hit_record),
+            false => None,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_disk(&self.center, &self.frame.w, self.radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_disk_hit() {
+        let disk = Disk::new(
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+            Vec3::new_with_values(0.0, 0.0, 1.0),
+            2.0,
+            Arc::new(TestMaterial),
+        );
+        let ray_at = |x: f64| Ray {
+            orig: Vec3::new_with_values(x, 0.0, 4.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = disk.hit(&ray_at(1.0), 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.t, 5.0);
+        assert!(rec.front_face);
+        assert!((rec.v - 0.5).abs() < 1e-12);
+        assert!(dot(&rec.tangent, &rec.normal).abs() < 1e-12);
+
+        // Right at the rim still hits, just past it misses
+        assert!(disk
+            .hit(&ray_at(2.0 - 1e-9), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(disk
+            .hit(&ray_at(2.0 + 1e-9), 0.001, f64::INFINITY)
+            .is_none());
+
+        let bbox = disk.bounding_box().unwrap();
+        assert!(bbox.maximum.x() >= 2.0 && bbox.minimum.y() <= -2.0);
+    }
+}
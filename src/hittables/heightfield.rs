@@ -0,0 +1,543 @@
+use image::DynamicImage;
+
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::hittables::triangle_mesh::intersect_triangle;
+use crate::materials::material::Material;
+use crate::utils::vec3_utils::*;
+
+use std::error::Error;
+use std::fmt;
+use std::option::Option;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum HeightfieldError {
+    Image(image::ImageError),
+    InvalidSize {
+        width: usize,
+        depth: usize,
+        heights: usize,
+    },
+}
+
+impl fmt::Display for HeightfieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightfieldError::Image(error) => write!(f, "could not read heightfield: {}", error),
+            HeightfieldError::InvalidSize {
+                width,
+                depth,
+                heights,
+            } => write!(
+                f,
+                "heightfield of {}x{} samples cannot be built from {} heights",
+                width, depth, heights
+            ),
+        }
+    }
+}
+
+impl Error for HeightfieldError {}
+
+impl From<image::ImageError> for HeightfieldError {
+    fn from(error: image::ImageError) -> Self {
+        HeightfieldError::Image(error)
+    }
+}
+
+// One level of the min-max pyramid, holding the height range of blocks of cells
+struct HeightRange {
+    columns: usize,
+    rows: usize,
+    ranges: Vec<(f64, f64)>,
+}
+
+// Terrain over the xz plane. The grid of samples spans size.x by size.z starting at
+// corner, and heights in [0, 1] are scaled by size.y. Each cell is split into two
+// triangles, found by walking a min-max pyramid of the heights instead of a BVH.
+pub struct Heightfield {
+    heights: Vec<f64>,
+    width: usize,
+    depth: usize,
+    corner: Vec3,
+    size: Vec3,
+    normals: Vec<Vec3>,
+    levels: Vec<HeightRange>,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Heightfield {
+    // Heights are stored row by row, rows running along z. At least 2x2 samples are
+    // needed to make a single cell.
+    pub fn new(
+        heights: Vec<f64>,
+        width: usize,
+        depth: usize,
+        corner: Vec3,
+        size: Vec3,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Result<Self, HeightfieldError> {
+        if width < 2 || depth < 2 || heights.len() != width * depth {
+            return Err(HeightfieldError::InvalidSize {
+                width,
+                depth,
+                heights: heights.len(),
+            });
+        }
+
+        let mut heightfield = Heightfield {
+            heights,
+            width,
+            depth,
+            corner,
+            size,
+            normals: vec![],
+            levels: vec![],
+            mat_ptr,
+        };
+        heightfield.normals = (0..depth)
+            .flat_map(|row| (0..width).map(move |column| (column, row)))
+            .map(|(column, row)| heightfield.vertex_normal(column, row))
+            .collect();
+        heightfield.build_levels();
+        Ok(heightfield)
+    }
+
+    // Image rows run along z and columns along x. 16 bit images keep their full
+    // precision, anything else is read as 8 bit grayscale.
+    pub fn from_image(
+        image: &DynamicImage,
+        corner: Vec3,
+        size: Vec3,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Result<Self, HeightfieldError> {
+        let (width, depth, heights) = match image {
+            DynamicImage::ImageLuma16(image) => (
+                image.width(),
+                image.height(),
+                image
+                    .pixels()
+                    .map(|pixel| pixel[0] as f64 / u16::MAX as f64)
+                    .collect(),
+            ),
+            _ => {
+                let image = image.to_luma8();
+                (
+                    image.width(),
+                    image.height(),
+                    image
+                        .pixels()
+                        .map(|pixel| pixel[0] as f64 / u8::MAX as f64)
+                        .collect(),
+                )
+            }
+        };
+        Heightfield::new(
+            heights,
+            width as usize,
+            depth as usize,
+            corner,
+            size,
+            mat_ptr,
+        )
+    }
+
+    pub fn load(
+        path: &str,
+        corner: Vec3,
+        size: Vec3,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Result<Self, HeightfieldError> {
+        Heightfield::from_image(&image::open(path)?, corner, size, mat_ptr)
+    }
+
+    fn spacing(&self) -> (f64, f64) {
+        (
+            self.size.x() / (self.width - 1) as f64,
+            self.size.z() / (self.depth - 1) as f64,
+        )
+    }
+
+    fn height(&self, column: usize, row: usize) -> f64 {
+        self.heights[row * self.width + column]
+    }
+
+    fn vertex(&self, column: usize, row: usize) -> Vec3 {
+        let (dx, dz) = self.spacing();
+        self.corner
+            + Vec3::new_with_values(
+                column as f64 * dx,
+                self.height(column, row) * self.size.y(),
+                row as f64 * dz,
+            )
+    }
+
+    // Normal of y = f(x, z) from central differences, one sided at the borders
+    fn vertex_normal(&self, column: usize, row: usize) -> Vec3 {
+        let (dx, dz) = self.spacing();
+        let (left, right) = (column.saturating_sub(1), (column + 1).min(self.width - 1));
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(self.depth - 1));
+        let slope_x = (self.height(right, row) - self.height(left, row)) * self.size.y()
+            / ((right - left) as f64 * dx);
+        let slope_z = (self.height(column, front) - self.height(column, back)) * self.size.y()
+            / ((front - back) as f64 * dz);
+        unit_vector(Vec3::new_with_values(-slope_x, 1.0, -slope_z))
+    }
+
+    fn build_levels(&mut self) {
+        if self.width < 2 || self.depth < 2 {
+            return;
+        }
+        let (columns, rows) = (self.width - 1, self.depth - 1);
+        let mut ranges = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let corners = [
+                    self.height(column, row),
+                    self.height(column + 1, row),
+                    self.height(column, row + 1),
+                    self.height(column + 1, row + 1),
+                ];
+                let min = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                ranges.push((min, max));
+            }
+        }
+        self.levels.push(HeightRange {
+            columns,
+            rows,
+            ranges,
+        });
+
+        while let Some(level) = self.levels.last() {
+            if level.columns == 1 && level.rows == 1 {
+                break;
+            }
+            let (columns, rows) = (level.columns.div_ceil(2), level.rows.div_ceil(2));
+            let mut ranges = vec![(f64::INFINITY, f64::NEG_INFINITY); columns * rows];
+            for row in 0..level.rows {
+                for column in 0..level.columns {
+                    let (min, max) = level.ranges[row * level.columns + column];
+                    let parent = &mut ranges[(row / 2) * columns + column / 2];
+                    *parent = (parent.0.min(min), parent.1.max(max));
+                }
+            }
+            self.levels.push(HeightRange {
+                columns,
+                rows,
+                ranges,
+            });
+        }
+    }
+
+    // World space box of a block of cells at the given pyramid level
+    fn block_box(&self, level: usize, column: usize, row: usize) -> Aabb {
+        let (dx, dz) = self.spacing();
+        let cells = 1 << level;
+        let (columns, rows) = (self.width - 1, self.depth - 1);
+        let (min, max) = self.levels[level].ranges[row * self.levels[level].columns + column];
+        Aabb::new(
+            self.corner
+                + Vec3::new_with_values(
+                    (column * cells) as f64 * dx,
+                    min * self.size.y(),
+                    (row * cells) as f64 * dz,
+                ),
+            self.corner
+                + Vec3::new_with_values(
+                    ((column + 1) * cells).min(columns) as f64 * dx,
+                    max * self.size.y(),
+                    ((row + 1) * cells).min(rows) as f64 * dz,
+                ),
+        )
+        .padded(1e-9)
+    }
+
+    fn hit_record(
+        &self,
+        r: &Ray,
+        t: f64,
+        corners: [(usize, usize); 3],
+        b1: f64,
+        b2: f64,
+    ) -> HitRecord {
+        let b0 = 1.0 - b1 - b2;
+        let [c0, c1, c2] = corners.map(|(column, row)| self.vertex(column, row));
+        let geometric_normal = unit_vector(cross(&(c1 - c0), &(c2 - c0)));
+        let normal_at = |(column, row): (usize, usize)| self.normals[row * self.width + column];
+        let mut shading_normal = unit_vector(
+            b0 * normal_at(corners[0]) + b1 * normal_at(corners[1]) + b2 * normal_at(corners[2]),
+        );
+
+        // v follows ImageTexture, so the source image drapes over the terrain unchanged
+        let p = r.at(t);
+        let mut hit_record = HitRecord {
+            t,
+            u: ((p.x() - self.corner.x()) / self.size.x()).clamp(0.0, 1.0),
+            v: 1.0 - ((p.z() - self.corner.z()) / self.size.z()).clamp(0.0, 1.0),
+            p,
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: Vec3::new(),
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &geometric_normal);
+        if !hit_record.front_face {
+            shading_normal = -shading_normal;
+        }
+        hit_record.normal = shading_normal;
+
+        let tangent = Vec3::new_with_values(1.0, 0.0, 0.0);
+        let tangent = tangent - dot(&tangent, &shading_normal) * shading_normal;
+        hit_record.tangent = match tangent.near_zero() {
+            true => perpendicular(&shading_normal),
+            false => unit_vector(tangent),
+        };
+        hit_record
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if self.levels.is_empty() {
+            return None;
+        }
+
+        let mut closest_so_far = t_max;
+        let mut closest = None;
+        let mut stack = vec![(self.levels.len() - 1, 0, 0)];
+        while let Some((level, column, row)) = stack.pop() {
+            if !self
+                .block_box(level, column, row)
+                .hit(r, t_min, closest_so_far)
+            {
+                continue;
+            }
+            if level > 0 {
+                let below = &self.levels[level - 1];
+                for child_row in 2 * row..(2 * row + 2).min(below.rows) {
+                    for child_column in 2 * column..(2 * column + 2).min(below.columns) {
+                        stack.push((level - 1, child_column, child_row));
+                    }
+                }
+                continue;
+            }
+
+            // Both triangles wind so that their normals point up
+            let (c, r0) = (column, row);
+            let triangles = [
+                [(c, r0), (c + 1, r0 + 1), (c + 1, r0)],
+                [(c, r0), (c, r0 + 1), (c + 1, r0 + 1)],
+            ];
+            for corners in triangles {
+                let [p0, p1, p2] = corners.map(|(column, row)| self.vertex(column, row));
+                if let Some((t, b1, b2)) =
+                    intersect_triangle(&p0, &p1, &p2, r, t_min, closest_so_far)
+                {
+                    let hit_record = self.hit_record(r, t, corners, b1, b2);
+                    if hit_record.mat_ptr.opaque(&hit_record) {
+                        closest_so_far = t;
+                        closest = Some(hit_record);
+                    }
+                }
+            }
+        }
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.levels.is_empty() {
+            true => None,
+            false => Some(self.block_box(self.levels.len() - 1, 0, 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    // A smooth bump, highest in the middle
+    fn bump(size: usize) -> Heightfield {
+        let mut heights = vec![];
+        for row in 0..size {
+            for column in 0..size {
+                let x = column as f64 / (size - 1) as f64 - 0.5;
+                let z = row as f64 / (size - 1) as f64 - 0.5;
+                heights.push((-(x * x + z * z) * 8.0).exp());
+            }
+        }
+        Heightfield::new(
+            heights,
+            size,
+            size,
+            Vec3::new_with_values(-1.0, 0.0, -1.0),
+            Vec3::new_with_values(2.0, 1.0, 2.0),
+            Arc::new(TestMaterial),
+        )
+        .unwrap()
+    }
+
+    fn looking_down(x: f64, z: f64) -> Ray {
+        Ray {
+            orig: Vec3::new_with_values(x, 5.0, z),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_heightfield_hit_from_above() {
+        let heightfield = bump(65);
+        let rec = heightfield
+            .hit(&looking_down(0.0, 0.0), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(0.0, 1.0, 0.0)).length() < 1e-9);
+        assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+
+        // On the slope the smooth normal leans away from the peak
+        let rec = heightfield
+            .hit(&looking_down(0.3, 0.01), 0.001, f64::INFINITY)
+            .unwrap();
+        let expected_height = (-(0.15f64 * 0.15 + 0.005 * 0.005) * 8.0).exp();
+        assert!((rec.p.y() - expected_height).abs() < 1e-3);
+        assert!(rec.normal.x() > 0.1);
+
+        assert!(heightfield
+            .hit(&looking_down(1.5, 0.0), 0.001, f64::INFINITY)
+            .is_none());
+    }
+
+    #[test]
+    fn test_heightfield_matches_brute_force() {
+        // Rays skimming over the terrain from the side have to find the same triangle
+        // as testing every cell
+        let heightfield = bump(17);
+        let (columns, rows) = (heightfield.width - 1, heightfield.depth - 1);
+        let mut hits = 0;
+        for i in 0..20 {
+            let ray = Ray {
+                orig: Vec3::new_with_values(-3.0, 0.6, -0.9 + 0.09 * i as f64),
+                dir: Vec3::new_with_values(1.0, -0.2, 0.05),
+                time: 0.0,
+            };
+            let mut expected: Option<f64> = None;
+            for row in 0..rows {
+                for column in 0..columns {
+                    let (c, r0) = (column, row);
+                    for corners in [
+                        [(c, r0), (c + 1, r0 + 1), (c + 1, r0)],
+                        [(c, r0), (c, r0 + 1), (c + 1, r0 + 1)],
+                    ] {
+                        let [p0, p1, p2] =
+                            corners.map(|(column, row)| heightfield.vertex(column, row));
+                        if let Some((t, _, _)) =
+                            intersect_triangle(&p0, &p1, &p2, &ray, 0.001, f64::INFINITY)
+                        {
+                            expected = Some(expected.map_or(t, |e: f64| e.min(t)));
+                        }
+                    }
+                }
+            }
+            let found = heightfield.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.t);
+            assert_eq!(found, expected);
+            hits += found.is_some() as usize;
+        }
+        assert!(hits > 5);
+    }
+
+    #[test]
+    fn test_heightfield_from_image() {
+        let mut image = GrayImage::new(3, 2);
+        image.put_pixel(2, 1, Luma([255]));
+        let heightfield = Heightfield::from_image(
+            &DynamicImage::ImageLuma8(image),
+            Vec3::new(),
+            Vec3::new_with_values(2.0, 3.0, 1.0),
+            Arc::new(TestMaterial),
+        )
+        .unwrap();
+        assert_eq!(
+            heightfield.vertex(2, 1),
+            Vec3::new_with_values(2.0, 3.0, 1.0)
+        );
+        let bbox = heightfield.bounding_box().unwrap();
+        assert!((bbox.maximum - Vec3::new_with_values(2.0, 3.0, 1.0)).length() < 1e-6);
+
+        // Seen from below the terrain shows its back face
+        let from_below = Ray {
+            orig: Vec3::new_with_values(0.5, -1.0, 0.5),
+            dir: Vec3::new_with_values(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let rec = heightfield.hit(&from_below, 0.001, f64::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert!(rec.normal.y() < 0.0);
+    }
+
+    #[test]
+    fn test_heightfield_keeps_geometric_normal() {
+        // A single raised corner, so the smooth normals differ from the flat triangles
+        let heightfield = Heightfield::new(
+            vec![0.0, 0.0, 0.0, 1.0],
+            2,
+            2,
+            Vec3::new(),
+            Vec3::new_with_values(1.0, 1.0, 1.0),
+            Arc::new(TestMaterial),
+        )
+        .unwrap();
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.8, 5.0, 0.2),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let rec = heightfield.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let [p0, p1, p2] =
+            [(0, 0), (1, 1), (1, 0)].map(|(column, row)| heightfield.vertex(column, row));
+        let mut expected = unit_vector(cross(&(p1 - p0), &(p2 - p0)));
+        if expected.y() < 0.0 {
+            expected = -expected;
+        }
+        assert!((rec.geometric_normal - expected).length() < 1e-9);
+        assert!((rec.normal - expected).length() > 0.1);
+    }
+
+    #[test]
+    fn test_heightfield_invalid_size() {
+        for (heights, width, depth) in [(6, 2, 2), (4, 4, 1), (0, 0, 0)] {
+            match Heightfield::new(
+                vec![0.0; heights],
+                width,
+                depth,
+                Vec3::new(),
+                Vec3::new_with_values(1.0, 1.0, 1.0),
+                Arc::new(TestMaterial),
+            ) {
+                Err(HeightfieldError::InvalidSize { heights: count, .. }) => {
+                    assert_eq!(count, heights)
+                }
+                _ => panic!("expected an invalid size error"),
+            }
+        }
+    }
+}
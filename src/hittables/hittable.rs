@@ -1,3 +1,4 @@
+use crate::hittables::aabb::Aabb;
 use crate::materials::material::Material;
 use crate::rays::ray::Ray;
 use crate::utils::vec3_utils::*;
@@ -6,12 +7,25 @@ use crate::vectors::vec3::Vec3;
 use std::option::Option;
 use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct HitRecord {
     pub p: Vec3,
     pub normal: Vec3,
+    // Normal of the actual surface, facing the ray like normal. Differs from normal
+    // where a primitive interpolates or bends its shading normal.
+    pub geometric_normal: Vec3,
+    pub tangent: Vec3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
     pub mat_ptr: Arc<dyn Material>,
+    // Interpolated vertex color, for meshes that carry one
+    pub color: Option<Vec3>,
+    // Index of the top level object that was hit, filled in by HittableList
+    pub object_id: usize,
+    // Index of the material in the scene's registry, filled in by Scene
+    pub material_id: Option<usize>,
 }
 
 impl HitRecord {
@@ -20,12 +34,81 @@ impl HitRecord {
         self.normal = match self.front_face {
             true => *outward_normal,
             false => -*outward_normal,
-        }
+        };
+        self.geometric_normal = self.normal;
     }
 }
 
-pub trait Hittable: Sync {
+const MAX_INTERVAL_CROSSINGS: usize = 64;
+
+// Stretch of a ray that lies inside a closed object
+#[derive(Clone)]
+pub struct HitInterval {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
+}
+
+pub trait Hittable: Sync + Send {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    // Box enclosing the object, None for unbounded objects such as infinite planes
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    // Intervals along the whole line of the ray, not just its forward half, ordered by t.
+    // Only meaningful for closed objects, whose crossings alternate between entering
+    // and leaving. The default walks the crossings with repeated calls to hit.
+    fn intervals(&self, r: &Ray) -> Vec<HitInterval> {
+        let mut intervals = vec![];
+        let mut enter: Option<HitRecord> = None;
+        let mut t_start = f64::NEG_INFINITY;
+        for _ in 0..MAX_INTERVAL_CROSSINGS {
+            let rec = match self.hit(r, t_start, f64::INFINITY) {
+                Some(rec) => rec,
+                None => break,
+            };
+            t_start = rec.t + 1e-9 * rec.t.abs().max(1.0);
+            match (rec.front_face, enter.take()) {
+                (true, _) => enter = Some(rec),
+                (false, Some(enter)) => intervals.push(HitInterval { enter, exit: rec }),
+                (false, None) => {}
+            }
+        }
+        intervals
+    }
+
+    // Solid angle density of random picking direction from origin, used by area lights
+    fn pdf_value(&self, _origin: &Vec3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Random direction from origin towards the surface
+    fn random(&self, _origin: &Vec3) -> Vec3 {
+        Vec3::new_with_values(1.0, 0.0, 0.0)
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.as_ref().hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.as_ref().bounding_box()
+    }
+
+    fn intervals(&self, r: &Ray) -> Vec<HitInterval> {
+        self.as_ref().intervals(r)
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.as_ref().pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Vec3) -> Vec3 {
+        self.as_ref().random(origin)
+    }
 }
 
 #[cfg(test)]
@@ -35,7 +118,7 @@ mod tests {
     struct TestMaterial;
 
     impl Material for TestMaterial {
-        fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
             None
         }
     }
@@ -46,17 +129,52 @@ mod tests {
         let mut hr = HitRecord {
             p: Vec3::new(),
             normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: Vec3::new(),
             t: 1.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
             mat_ptr: material.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
         };
         let ray = Ray {
             orig: Vec3::new_with_values(0f64, 0f64, 0f64),
             dir: Vec3::new_with_values(0f64, 0f64, 1f64),
+            time: 0.0,
         };
         let outward_normal = Vec3::new_with_values(1.0, 0.0, 0.0);
         hr.set_face_normal(&ray, &outward_normal);
-        assert_eq!(hr.front_face, false);
+        assert!(!hr.front_face);
         assert_eq!(hr.normal, -outward_normal);
     }
+
+    #[test]
+    fn test_default_intervals() {
+        use crate::hittables::torus::Torus;
+
+        let torus = Torus::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            Arc::new(TestMaterial),
+        );
+        // Starting in the hole, the tube behind the origin still counts
+        let ray = Ray {
+            orig: Vec3::new(),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let intervals = torus.intervals(&ray);
+        assert_eq!(intervals.len(), 2);
+        let expected = [(-2.5, -1.5), (1.5, 2.5)];
+        for (interval, (enter, exit)) in intervals.iter().zip(expected) {
+            assert!((interval.enter.t - enter).abs() < 1e-9);
+            assert!((interval.exit.t - exit).abs() < 1e-9);
+            assert!(interval.enter.front_face && !interval.exit.front_face);
+        }
+    }
 }
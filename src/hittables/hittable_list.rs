@@ -1,5 +1,8 @@
+use crate::hittables::aabb::Aabb;
 use crate::hittables::hittable::*;
 use crate::rays::ray::Ray;
+use crate::utils::random_number_utils::random_f64;
+use crate::vectors::vec3::Vec3;
 
 use std::option::Option;
 
@@ -21,17 +24,52 @@ impl HittableList {
     }
 }
 
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hittable for HittableList {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let mut hit_record = None;
         let mut closest_so_far = t_max;
 
-        for object in &self.objects {
-            if let Some(rec) = object.hit(r, t_min, closest_so_far) {
+        for (index, object) in self.objects.iter().enumerate() {
+            if let Some(mut rec) = object.hit(r, t_min, closest_so_far) {
                 closest_so_far = rec.t;
+                rec.object_id = index;
                 hit_record = Some(rec);
             }
         }
         hit_record
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut bbox: Option<Aabb> = None;
+        for object in &self.objects {
+            let object_box = object.bounding_box()?;
+            bbox = Some(match bbox {
+                Some(bbox) => Aabb::surrounding_box(&bbox, &object_box),
+                None => object_box,
+            });
+        }
+        bbox
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Vec3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new_with_values(1.0, 0.0, 0.0);
+        }
+        let index = (random_f64() * self.objects.len() as f64) as usize;
+        self.objects[index.min(self.objects.len() - 1)].random(origin)
+    }
 }
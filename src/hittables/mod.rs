@@ -1,3 +1,19 @@
+pub mod aabb;
+pub mod alpha_masked;
+pub mod box_shape;
+pub mod cone;
+pub mod csg;
+pub mod curve;
+pub mod cylinder;
+pub mod disk;
+pub mod heightfield;
 pub mod hittable;
 pub mod hittable_list;
+pub mod moving;
+pub mod plane;
+pub mod quad;
+pub mod sdf;
 pub mod sphere;
+pub mod subdivision_surface;
+pub mod torus;
+pub mod triangle_mesh;
@@ -0,0 +1,127 @@
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use std::option::Option;
+
+// Moves any primitive at constant velocity, in scene units per second. Rays sent at
+// different times within the shutter interval see it in different places, which blurs
+// it along its path.
+pub struct Moving {
+    object: Box<dyn Hittable>,
+    velocity: Vec3,
+    // Longest shutter interval the bounding box has to cover
+    duration: f64,
+}
+
+impl Moving {
+    pub fn new(object: Box<dyn Hittable>, velocity: Vec3, duration: f64) -> Self {
+        Moving {
+            object,
+            velocity,
+            duration,
+        }
+    }
+
+    // Moves the ray back instead of the object forward, returning the offset that takes
+    // hits on the object back to where it is at the time of the ray
+    fn moved_ray(&self, r: &Ray) -> (Ray, Vec3) {
+        let offset = r.time() * self.velocity;
+        let moved = Ray {
+            orig: r.origin() - offset,
+            dir: r.direction(),
+            time: r.time(),
+        };
+        (moved, offset)
+    }
+}
+
+impl Hittable for Moving {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (moved, offset) = self.moved_ray(r);
+        let mut rec = self.object.hit(&moved, t_min, t_max)?;
+        rec.p += offset;
+        Some(rec)
+    }
+
+    fn intervals(&self, r: &Ray) -> Vec<HitInterval> {
+        let (moved, offset) = self.moved_ray(r);
+        let mut intervals = self.object.intervals(&moved);
+        for interval in &mut intervals {
+            interval.enter.p += offset;
+            interval.exit.p += offset;
+        }
+        intervals
+    }
+
+    // Light sampling has no notion of time, so a moving light is sampled where it is
+    // when the shutter opens
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Vec3) -> Vec3 {
+        self.object.random(origin)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let start = self.object.bounding_box()?;
+        let offset = self.duration * self.velocity;
+        let end = Aabb::new(start.minimum + offset, start.maximum + offset);
+        Some(Aabb::surrounding_box(&start, &end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::sphere::Sphere;
+    use crate::materials::material::Material;
+    use std::sync::Arc;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_moving_sphere() {
+        let sphere = Sphere::new(Vec3::new(), 0.5, Arc::new(TestMaterial));
+        let moving = Moving::new(Box::new(sphere), Vec3::new_with_values(4.0, 0.0, 0.0), 0.5);
+        let ray = |time| Ray {
+            orig: Vec3::new_with_values(1.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time,
+        };
+        assert!(moving.hit(&ray(0.0), 0.001, f64::INFINITY).is_none());
+        let rec = moving.hit(&ray(0.25), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Vec3::new_with_values(1.0, 0.0, 0.5)).length() < 1e-12);
+        assert!((rec.t - 4.5).abs() < 1e-12);
+
+        let bounds = moving.bounding_box().unwrap();
+        assert!(bounds.minimum.x() <= -0.5 && bounds.maximum.x() >= 2.5);
+    }
+
+    #[test]
+    fn test_moving_intervals_and_sampling() {
+        let sphere = Sphere::new(Vec3::new(), 0.5, Arc::new(TestMaterial));
+        let moving = Moving::new(Box::new(sphere), Vec3::new_with_values(4.0, 0.0, 0.0), 0.5);
+        let ray = Ray {
+            orig: Vec3::new_with_values(1.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.25,
+        };
+        let intervals = moving.intervals(&ray);
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].enter.p - Vec3::new_with_values(1.0, 0.0, 0.5)).length() < 1e-12);
+        assert!((intervals[0].exit.p - Vec3::new_with_values(1.0, 0.0, -0.5)).length() < 1e-12);
+
+        let origin = Vec3::new_with_values(0.0, 0.0, 5.0);
+        let direction = moving.random(&origin);
+        assert!(moving.pdf_value(&origin, &direction) > 0.0);
+    }
+}
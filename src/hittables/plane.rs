@@ -0,0 +1,112 @@
+use crate::rays::ray::Ray;
+use crate::vectors::onb::Onb;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::utils::vec3_utils::*;
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Infinite plane through point. The uv coordinates repeat every unit along the plane
+// so that image textures tile.
+pub struct Plane {
+    point: Vec3,
+    frame: Onb,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, mat_ptr: Arc<dyn Material>) -> Self {
+        Plane {
+            point,
+            frame: Onb::from_w(&normal),
+            mat_ptr,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denominator = dot(&self.frame.w, &r.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = dot(&(self.point - r.origin()), &self.frame.w) / denominator;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let hit_point = r.at(t);
+        let local = self.frame.to_local(&(hit_point - self.point));
+        let mut hit_record = HitRecord {
+            t,
+            u: local.x().rem_euclid(1.0),
+            v: local.y().rem_euclid(1.0),
+            p: hit_point,
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: self.frame.u,
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &self.frame.w);
+        match hit_record.mat_ptr.opaque(&hit_record) {
+            true => Some(hit_record),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_plane_hit() {
+        let plane = Plane::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            Arc::new(TestMaterial),
+        );
+        let above = Ray {
+            orig: Vec3::new_with_values(-3.7, 2.0, 12.2),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let rec = plane.hit(&above, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.t, 2.0);
+        assert!(rec.front_face);
+        assert!((0.0..1.0).contains(&rec.u) && (0.0..1.0).contains(&rec.v));
+
+        let below = Ray {
+            orig: Vec3::new_with_values(0.0, -1.0, 0.0),
+            dir: Vec3::new_with_values(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let rec = plane.hit(&below, 0.001, f64::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, Vec3::new_with_values(0.0, -1.0, 0.0));
+
+        // Grazing rays parallel to the plane never hit it
+        let grazing = Ray {
+            orig: Vec3::new_with_values(0.0, 1e-6, 0.0),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(plane.hit(&grazing, 0.001, f64::INFINITY).is_none());
+        assert!(plane.bounding_box().is_none());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::*;
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Parallelogram spanned by the edges u and v from the corner q. The normal follows
+// the right hand rule, cross(u, v).
+pub struct Quad {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    d: f64,
+    w: Vec3,
+    area: f64,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, mat_ptr: Arc<dyn Material>) -> Self {
+        let n = cross(&u, &v);
+        let normal = unit_vector(n);
+        Quad {
+            q,
+            u,
+            v,
+            normal,
+            d: dot(&normal, &q),
+            w: n / dot(&n, &n),
+            area: n.length(),
+            mat_ptr,
+        }
+    }
+
+    pub fn corner(&self) -> Vec3 {
+        self.q
+    }
+
+    pub fn edges(&self) -> (Vec3, Vec3) {
+        (self.u, self.v)
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denominator = dot(&self.normal, &r.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = (self.d - dot(&self.normal, &r.origin())) / denominator;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let hit_point = r.at(t);
+        let planar_hit = hit_point - self.q;
+        let alpha = dot(&self.w, &cross(&planar_hit, &self.v));
+        let beta = dot(&self.w, &cross(&self.u, &planar_hit));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut hit_record = HitRecord {
+            t,
+            u: alpha,
+            v: beta,
+            p: hit_point,
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: unit_vector(self.u),
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &self.normal);
+        match hit_record.mat_ptr.opaque(&hit_record) {
+            true => Some(hit_record),
+            false => None,
+        }
+    }
+
+    // Padded so that quads lying in an axis plane still get a box with volume
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = Aabb::from_points(&[
+            self.q,
+            self.q + self.u,
+            self.q + self.v,
+            self.q + self.u + self.v,
+        ]);
+        Some(bbox.padded(1e-4))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let ray = Ray {
+            orig: *origin,
+            dir: *direction,
+            time: 0.0,
+        };
+        match self.hit(&ray, 0.001, f64::INFINITY) {
+            None => 0.0,
+            Some(rec) => {
+                let distance_squared = rec.t * rec.t * direction.length_squared();
+                let cosine = dot(direction, &rec.normal).abs() / direction.length();
+                distance_squared / (cosine * self.area)
+            }
+        }
+    }
+
+    fn random(&self, origin: &Vec3) -> Vec3 {
+        let p = self.q + random_f64() * self.u + random_f64() * self.v;
+        p - *origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn unit_square() -> Quad {
+        Quad::new(
+            Vec3::new(),
+            Vec3::new_with_values(2.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            Arc::new(TestMaterial),
+        )
+    }
+
+    #[test]
+    fn test_quad_hit_and_uv() {
+        let quad = unit_square();
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.5, 0.25, 3.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = quad.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.t, 3.0);
+        assert_eq!(rec.u, 0.25);
+        assert_eq!(rec.v, 0.25);
+        assert!(rec.front_face);
+        assert_eq!(rec.tangent, Vec3::new_with_values(1.0, 0.0, 0.0));
+
+        let outside = Ray {
+            orig: Vec3::new_with_values(2.5, 0.25, 3.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(quad.hit(&outside, 0.001, f64::INFINITY).is_none());
+
+        let parallel = Ray {
+            orig: Vec3::new_with_values(0.5, 0.25, 3.0),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(quad.hit(&parallel, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_quad_pdf_integrates_to_solid_angle() {
+        // Uniform directions over the sphere land on the quad with probability
+        // solid_angle / 4pi, and the pdf is one over the solid angle there.
+        let quad = unit_square();
+        let origin = Vec3::new_with_values(1.0, 0.5, 1.0);
+        let samples = 200000;
+        let total: f64 = (0..samples)
+            .map(|_| quad.pdf_value(&origin, &Vec3::random_unit_vector()))
+            .sum();
+        let estimate = total / samples as f64 * 4.0 * std::f64::consts::PI;
+        assert!((estimate - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_quad_random_lands_on_quad() {
+        let quad = unit_square();
+        let origin = Vec3::new_with_values(1.0, 0.5, 1.0);
+        for _ in 0..100 {
+            let direction = quad.random(&origin);
+            assert!(quad.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+}
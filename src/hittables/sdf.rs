@@ -0,0 +1,462 @@
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::utils::vec3_utils::*;
+
+use std::f64::consts::PI;
+use std::option::Option;
+use std::sync::Arc;
+
+// Tree of signed distance functions, negative inside. Leaves are centered at the origin
+// and placed with Translate and Scale.
+#[derive(Clone)]
+pub enum SdfNode {
+    Sphere {
+        radius: f64,
+    },
+    // Box with the given half size whose edges are rounded off by radius
+    RoundedBox {
+        half_extents: Vec3,
+        radius: f64,
+    },
+    // Ring in the xz plane
+    Torus {
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    // Power 8 gives the classic bulb, which fits inside a radius of about 1.2
+    Mandelbulb {
+        power: f64,
+        iterations: usize,
+    },
+    // Any distance bound, which must not overestimate the true distance
+    Custom(Arc<dyn Fn(&Vec3) -> f64 + Sync + Send>),
+    Translate(Vec3, Box<SdfNode>),
+    Scale(f64, Box<SdfNode>),
+    Union(Box<SdfNode>, Box<SdfNode>),
+    Intersection(Box<SdfNode>, Box<SdfNode>),
+    Difference(Box<SdfNode>, Box<SdfNode>),
+    // Union blended over a distance of about k
+    SmoothUnion(Box<SdfNode>, Box<SdfNode>, f64),
+}
+
+impl SdfNode {
+    pub fn translate(self, offset: Vec3) -> SdfNode {
+        SdfNode::Translate(offset, Box::new(self))
+    }
+
+    pub fn scale(self, factor: f64) -> SdfNode {
+        SdfNode::Scale(factor, Box::new(self))
+    }
+
+    pub fn union(self, other: SdfNode) -> SdfNode {
+        SdfNode::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: SdfNode) -> SdfNode {
+        SdfNode::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn difference(self, other: SdfNode) -> SdfNode {
+        SdfNode::Difference(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: SdfNode, k: f64) -> SdfNode {
+        SdfNode::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn distance(&self, p: &Vec3) -> f64 {
+        match self {
+            SdfNode::Sphere { radius } => p.length() - radius,
+            SdfNode::RoundedBox {
+                half_extents,
+                radius,
+            } => {
+                let q = Vec3::new_with_values(
+                    p.x().abs() - half_extents.x(),
+                    p.y().abs() - half_extents.y(),
+                    p.z().abs() - half_extents.z(),
+                );
+                let outside = Vec3::new_with_values(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0));
+                outside.length() + q.x().max(q.y()).max(q.z()).min(0.0) - radius
+            }
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - major_radius;
+                (ring * ring + p.y() * p.y()).sqrt() - minor_radius
+            }
+            SdfNode::Mandelbulb { power, iterations } => {
+                SdfNode::mandelbulb_distance(p, *power, *iterations)
+            }
+            SdfNode::Custom(function) => function(p),
+            SdfNode::Translate(offset, node) => node.distance(&(*p - *offset)),
+            SdfNode::Scale(factor, node) => node.distance(&(*p / *factor)) * factor,
+            SdfNode::Union(a, b) => a.distance(p).min(b.distance(p)),
+            SdfNode::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            SdfNode::Difference(a, b) => a.distance(p).max(-b.distance(p)),
+            SdfNode::SmoothUnion(a, b, k) => {
+                let (da, db) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+        }
+    }
+
+    // Distance estimate from the running derivative of the escape time iteration
+    fn mandelbulb_distance(p: &Vec3, power: f64, iterations: usize) -> f64 {
+        let mut z = *p;
+        let mut dr = 1.0;
+        let mut r = z.length();
+        for _ in 0..iterations {
+            if r > 2.0 || r == 0.0 {
+                break;
+            }
+            let theta = (z.z() / r).acos() * power;
+            let phi = z.y().atan2(z.x()) * power;
+            dr = r.powf(power - 1.0) * power * dr + 1.0;
+            z = r.powf(power)
+                * Vec3::new_with_values(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                )
+                + *p;
+            r = z.length();
+        }
+        match r == 0.0 {
+            true => 0.0,
+            false => 0.5 * r.ln() * r / dr,
+        }
+    }
+
+    // Conservative box around the surface, None when it cannot be bounded
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        let cube = |half: f64| {
+            Aabb::new(
+                Vec3::new_with_values(-half, -half, -half),
+                Vec3::new_with_values(half, half, half),
+            )
+        };
+        match self {
+            SdfNode::Sphere { radius } => Some(cube(*radius)),
+            SdfNode::RoundedBox {
+                half_extents,
+                radius,
+            } => Some(Aabb::new(-*half_extents, *half_extents).padded(*radius)),
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let half = major_radius + minor_radius;
+                Some(Aabb::new(
+                    Vec3::new_with_values(-half, -minor_radius, -half),
+                    Vec3::new_with_values(half, *minor_radius, half),
+                ))
+            }
+            SdfNode::Mandelbulb { .. } => Some(cube(1.5)),
+            SdfNode::Custom(_) => None,
+            SdfNode::Translate(offset, node) => {
+                let bbox = node.bounding_box()?;
+                Some(Aabb::new(bbox.minimum + *offset, bbox.maximum + *offset))
+            }
+            SdfNode::Scale(factor, node) => {
+                let bbox = node.bounding_box()?;
+                Some(Aabb::from_points(&[
+                    *factor * bbox.minimum,
+                    *factor * bbox.maximum,
+                ]))
+            }
+            SdfNode::Union(a, b) => Some(Aabb::surrounding_box(
+                &a.bounding_box()?,
+                &b.bounding_box()?,
+            )),
+            SdfNode::Intersection(a, b) => a.bounding_box().or_else(|| b.bounding_box()),
+            SdfNode::Difference(a, _) => a.bounding_box(),
+            SdfNode::SmoothUnion(a, b, k) => {
+                let bbox = Aabb::surrounding_box(&a.bounding_box()?, &b.bounding_box()?);
+                Some(bbox.padded(*k))
+            }
+        }
+    }
+}
+
+// Surface of a distance field found by sphere tracing: every step advances by the
+// distance bound, which can never jump over the surface.
+pub struct Sdf {
+    root: SdfNode,
+    bbox: Option<Aabb>,
+    epsilon: f64,
+    max_steps: usize,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Sdf {
+    pub fn new(root: SdfNode, mat_ptr: Arc<dyn Material>) -> Self {
+        Sdf::with_limits(root, 1e-5, 512, mat_ptr)
+    }
+
+    // Marching stops as a hit once the distance drops below epsilon, and as a miss
+    // after max_steps
+    pub fn with_limits(
+        root: SdfNode,
+        epsilon: f64,
+        max_steps: usize,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Sdf {
+            bbox: root.bounding_box(),
+            root,
+            epsilon,
+            max_steps,
+            mat_ptr,
+        }
+    }
+
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        let h = self.epsilon;
+        let mut gradient = Vec3::new();
+        for axis in 0..3 {
+            let mut offset = Vec3::new();
+            offset[axis] = h;
+            gradient[axis] =
+                self.root.distance(&(*p + offset)) - self.root.distance(&(*p - offset));
+        }
+        match gradient.near_zero() {
+            true => Vec3::new_with_values(0.0, 1.0, 0.0),
+            false => unit_vector(gradient),
+        }
+    }
+
+    fn hit_record(&self, r: &Ray, t: f64) -> HitRecord {
+        let hit_point = r.at(t);
+        let outward_normal = self.normal(&hit_point);
+        // There is no natural parameterization, so map the normal like a sphere
+        let theta = (-outward_normal.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-outward_normal.z()).atan2(outward_normal.x()) + PI;
+        let tangent = Vec3::new_with_values(outward_normal.z(), 0.0, -outward_normal.x());
+        let mut hit_record = HitRecord {
+            t,
+            u: phi / (2.0 * PI),
+            v: theta / PI,
+            p: hit_point,
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: match tangent.near_zero() {
+                true => perpendicular(&outward_normal),
+                false => unit_vector(tangent),
+            },
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &outward_normal);
+        hit_record
+    }
+}
+
+impl Hittable for Sdf {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_start, t_end) = match self.bbox {
+            Some(bbox) => bbox.hit_range(r, t_min, t_max)?,
+            None => (t_min, t_max),
+        };
+        let speed = r.direction().length();
+
+        // March on the side of the surface the ray starts on, so rays leaving the
+        // inside of a refractive object find the far surface
+        let side_at = |t: f64| match self.root.distance(&r.at(t)) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+        let mut side = side_at(t_start);
+        let mut t = t_start;
+        for _ in 0..self.max_steps {
+            let distance = side * self.root.distance(&r.at(t));
+            if distance < self.epsilon {
+                let hit_record = self.hit_record(r, t);
+                if hit_record.mat_ptr.opaque(&hit_record) {
+                    return Some(hit_record);
+                }
+                // Skip past masked out surface, which usually takes the ray to its other
+                // side, and march on from wherever the step ended up
+                t += 2.0 * self.epsilon / speed;
+                side = side_at(t);
+            } else {
+                t += distance / speed;
+            }
+            if t > t_end {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn towards_origin(from: Vec3) -> Ray {
+        Ray {
+            orig: from,
+            dir: -from,
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_sdf_sphere_matches_analytic() {
+        let sdf = Sdf::new(
+            SdfNode::Sphere { radius: 1.0 }.translate(Vec3::new_with_values(0.0, 1.0, 0.0)),
+            Arc::new(TestMaterial),
+        );
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.0, 1.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -2.0),
+            time: 0.0,
+        };
+        let rec = sdf.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-4);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(0.0, 0.0, 1.0)).length() < 1e-4);
+
+        // From the center the far side faces away
+        let inside = Ray {
+            orig: Vec3::new_with_values(0.0, 1.0, 0.0),
+            dir: Vec3::new_with_values(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let rec = sdf.hit(&inside, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-4);
+        assert!(!rec.front_face);
+
+        let missing = Ray {
+            orig: Vec3::new_with_values(0.0, 2.1, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(sdf.hit(&missing, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_composed_distances() {
+        let rounded = SdfNode::RoundedBox {
+            half_extents: Vec3::new_with_values(1.0, 1.0, 1.0),
+            radius: 0.25,
+        };
+        assert!((rounded.distance(&Vec3::new_with_values(2.0, 0.0, 0.0)) - 0.75).abs() < 1e-12);
+        let corner = Vec3::new_with_values(2.0, 2.0, 1.0);
+        assert!((rounded.distance(&corner) - (2.0f64.sqrt() - 0.25)).abs() < 1e-12);
+
+        // The smooth union bulges out between two spheres where the plain union pinches
+        let a = SdfNode::Sphere { radius: 1.0 }.translate(Vec3::new_with_values(-1.2, 0.0, 0.0));
+        let b = SdfNode::Sphere { radius: 1.0 }.translate(Vec3::new_with_values(1.2, 0.0, 0.0));
+        let between = Vec3::new_with_values(0.0, 0.5, 0.0);
+        let smooth = a.clone().smooth_union(b.clone(), 0.5);
+        assert!(smooth.distance(&between) < a.union(b).distance(&between));
+
+        let scaled = SdfNode::Sphere { radius: 1.0 }.scale(2.0);
+        assert_eq!(scaled.distance(&Vec3::new_with_values(3.0, 0.0, 0.0)), 1.0);
+        let hollow = SdfNode::Sphere { radius: 2.0 }.difference(SdfNode::Sphere { radius: 1.0 });
+        assert_eq!(hollow.distance(&Vec3::new()), 1.0);
+    }
+
+    #[test]
+    fn test_mandelbulb_hit() {
+        let sdf = Sdf::new(
+            SdfNode::Mandelbulb {
+                power: 8.0,
+                iterations: 12,
+            },
+            Arc::new(TestMaterial),
+        );
+        let origin = Vec3::new_with_values(0.3, 0.4, 4.0);
+        let rec = sdf
+            .hit(&towards_origin(origin), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!(rec.p.length() < 1.3 && rec.p.length() > 0.3);
+        assert!(rec.front_face);
+        assert!(dot(&rec.normal, &origin) > 0.0);
+    }
+
+    #[test]
+    fn test_step_limit() {
+        // Off center, so that every step falls short of the surface
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.9, 0.0, 10.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let sphere = || SdfNode::Sphere { radius: 1.0 };
+        assert!(Sdf::new(sphere(), Arc::new(TestMaterial))
+            .hit(&ray, 0.001, f64::INFINITY)
+            .is_some());
+
+        // Too few steps to close in on the surface counts as a miss
+        let impatient = Sdf::with_limits(sphere(), 1e-9, 2, Arc::new(TestMaterial));
+        assert!(impatient.hit(&ray, 0.001, f64::INFINITY).is_none());
+
+        // A loose epsilon stops early, short of the true surface
+        let loose = Sdf::with_limits(sphere(), 0.1, 512, Arc::new(TestMaterial));
+        let rec = loose.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(rec.p.length() > 1.0 && rec.p.length() < 1.1);
+    }
+
+    #[test]
+    fn test_surface_behind_cutout() {
+        // Slab with a square hole in front of a sphere
+        struct Cutout;
+        impl Material for Cutout {
+            fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+                None
+            }
+
+            fn opaque(&self, rec: &HitRecord) -> bool {
+                rec.p.z() < -1.0 || rec.p.x().abs() > 0.5 || rec.p.y().abs() > 0.5
+            }
+        }
+        let slab = SdfNode::RoundedBox {
+            half_extents: Vec3::new_with_values(2.0, 2.0, 0.1),
+            radius: 0.0,
+        };
+        let sphere =
+            SdfNode::Sphere { radius: 1.0 }.translate(Vec3::new_with_values(0.0, 0.0, -3.0));
+        let sdf = Sdf::new(slab.union(sphere), Arc::new(Cutout));
+
+        let through_hole = Ray {
+            orig: Vec3::new_with_values(0.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = sdf.hit(&through_hole, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p.z() + 2.0).abs() < 1e-4);
+        assert!(rec.front_face);
+
+        let on_slab = Ray {
+            orig: Vec3::new_with_values(1.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = sdf.hit(&on_slab, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p.z() - 0.1).abs() < 1e-4);
+    }
+}
@@ -1,10 +1,13 @@
 use crate::rays::ray::Ray;
 use crate::vectors::vec3::Vec3;
 
+use crate::hittables::aabb::Aabb;
 use crate::hittables::hittable::*;
 use crate::materials::material::Material;
+use crate::utils::random_number_utils::random_f64;
 use crate::utils::vec3_utils::*;
 
+use std::f64::consts::PI;
 use std::option::Option;
 use std::sync::Arc;
 
@@ -22,6 +25,43 @@ impl Sphere {
             mat_ptr,
         }
     }
+
+    fn get_sphere_uv(p: &Vec3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    fn hit_record(&self, r: &Ray, root: f64) -> HitRecord {
+        let hit_point = r.at(root);
+        let outward_normal = (hit_point - self.center) / self.radius;
+        let (u, v) = Sphere::get_sphere_uv(&outward_normal);
+        let mut hit_record = HitRecord {
+            t: root,
+            u,
+            v,
+            p: hit_point,
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: Sphere::get_sphere_tangent(&outward_normal),
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &outward_normal);
+        hit_record
+    }
+
+    // Direction of increasing u, falling back to an arbitrary tangent at the poles
+    fn get_sphere_tangent(p: &Vec3) -> Vec3 {
+        let tangent = Vec3::new_with_values(p.z(), 0.0, -p.x());
+        match tangent.near_zero() {
+            true => perpendicular(p),
+            false => unit_vector(tangent),
+        }
+    }
 }
 
 impl Hittable for Sphere {
@@ -36,26 +76,107 @@ impl Hittable for Sphere {
             return None;
         }
         let sqrtd = discriminant.sqrt();
-        let mut root = (-half_b - sqrtd) / a;
 
-        if root < t_min || t_max < root {
-            root = (-half_b + sqrtd) / a;
+        for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if root < t_min || t_max < root {
-                return None;
+                continue;
+            }
+            let hit_record = self.hit_record(r, root);
+            if hit_record.mat_ptr.opaque(&hit_record) {
+                return Some(hit_record);
             }
         }
+        None
+    }
 
-        let hit_point = r.at(root);
-        let outward_normal = (hit_point - self.center) / self.radius;
-        let mut hit_record = HitRecord {
-            t: root,
-            p: hit_point,
-            normal: Vec3::new(),
-            front_face: false,
-            mat_ptr: self.mat_ptr.clone(),
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new_with_values(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    // Uniform over the cone of directions subtended by the sphere, zero from inside
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let distance_squared = (self.center - *origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 0.0;
+        }
+        let ray = Ray {
+            orig: *origin,
+            dir: *direction,
+            time: 0.0,
         };
-        hit_record.set_face_normal(r, &outward_normal);
+        match self.hit(&ray, 0.001, f64::INFINITY) {
+            None => 0.0,
+            Some(_) => {
+                let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+                1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+            }
+        }
+    }
+
+    fn random(&self, origin: &Vec3) -> Vec3 {
+        let to_center = self.center - *origin;
+        let distance_squared = to_center.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return Vec3::random_unit_vector();
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let cos_theta = 1.0 - random_f64() * (1.0 - cos_theta_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_f64();
+
+        let w = unit_vector(to_center);
+        let u = perpendicular(&w);
+        let v = cross(&w, &u);
+        sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_sphere_tangent_follows_u() {
+        let sphere = Sphere::new(Vec3::new(), 1.0, Arc::new(TestMaterial));
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.3, 0.2, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(dot(&rec.tangent, &rec.normal).abs() < 1e-12);
 
-        Some(hit_record)
+        let delta = 1e-4;
+        let (u0, _) = Sphere::get_sphere_uv(&rec.normal);
+        let (u1, _) = Sphere::get_sphere_uv(&unit_vector(rec.normal + delta * rec.tangent));
+        assert!(u1 > u0);
+    }
+
+    #[test]
+    fn test_sphere_random_hits_sphere() {
+        let sphere = Sphere::new(
+            Vec3::new_with_values(0.0, 0.0, -3.0),
+            1.0,
+            Arc::new(TestMaterial),
+        );
+        let cos_theta_max = (1.0 - 1.0 / 9.0f64).sqrt();
+        let expected = 1.0 / (2.0 * PI * (1.0 - cos_theta_max));
+        for _ in 0..100 {
+            let direction = sphere.random(&Vec3::new());
+            assert!((sphere.pdf_value(&Vec3::new(), &direction) - expected).abs() < 1e-9);
+        }
+        let away = Vec3::new_with_values(0.0, 0.0, 1.0);
+        assert_eq!(sphere.pdf_value(&Vec3::new(), &away), 0.0);
     }
 }
@@ -0,0 +1,510 @@
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::triangle_mesh::TriangleMesh;
+use crate::materials::material::Material;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+const MAX_LEVEL: usize = 6;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SubdivisionScheme {
+    // Triangle scheme, quads are split into two triangles first
+    Loop,
+    // Works on any polygons and produces quads
+    CatmullClark,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Tessellation {
+    Level(usize),
+    // Subdivides until the longest cage edge, halved at every level, projects to at most
+    // max_edge_pixels for a camera at eye with the given vertical field of view in
+    // degrees. The whole surface shares one level so that neighbouring faces never crack.
+    ScreenSpace {
+        eye: Vec3,
+        vfov: f64,
+        image_height: usize,
+        max_edge_pixels: f64,
+    },
+}
+
+#[derive(Debug)]
+pub enum SubdivisionError {
+    DegenerateFace { face: usize, vertices: usize },
+}
+
+impl fmt::Display for SubdivisionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubdivisionError::DegenerateFace { face, vertices } => write!(
+                f,
+                "face {} of the control cage has {} vertices, at least 3 are needed",
+                face, vertices
+            ),
+        }
+    }
+}
+
+impl Error for SubdivisionError {}
+
+// Control cage refined into a smooth surface. Boundary edges follow the cubic B-spline
+// boundary rules and vertices with a single face are kept as corners, so open cages
+// don't shrink away from their outline.
+#[derive(Clone)]
+pub struct SubdivisionSurface {
+    positions: Vec<Vec3>,
+    faces: Vec<Vec<usize>>,
+    scheme: SubdivisionScheme,
+}
+
+struct EdgeInfo {
+    faces: Vec<usize>,
+    // Vertex of the adjacent triangle across from the edge, per face, for Loop
+    opposite: Vec<usize>,
+    point: usize,
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+impl SubdivisionSurface {
+    pub fn new(
+        positions: Vec<Vec3>,
+        faces: Vec<Vec<usize>>,
+        scheme: SubdivisionScheme,
+    ) -> Result<Self, SubdivisionError> {
+        if let Some((face, vertices)) = faces
+            .iter()
+            .map(|face| face.len())
+            .enumerate()
+            .find(|&(_, vertices)| vertices < 3)
+        {
+            return Err(SubdivisionError::DegenerateFace { face, vertices });
+        }
+
+        let faces = match scheme {
+            SubdivisionScheme::Loop => faces
+                .iter()
+                .flat_map(|face| {
+                    (1..face.len() - 1).map(move |i| vec![face[0], face[i], face[i + 1]])
+                })
+                .collect(),
+            SubdivisionScheme::CatmullClark => faces,
+        };
+        Ok(SubdivisionSurface {
+            positions,
+            faces,
+            scheme,
+        })
+    }
+
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn faces(&self) -> &[Vec<usize>] {
+        &self.faces
+    }
+
+    fn edges(&self) -> HashMap<(usize, usize), EdgeInfo> {
+        let mut edges: HashMap<(usize, usize), EdgeInfo> = HashMap::new();
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for i in 0..face.len() {
+                let (a, b) = (face[i], face[(i + 1) % face.len()]);
+                let edge = edges.entry(edge_key(a, b)).or_insert(EdgeInfo {
+                    faces: vec![],
+                    opposite: vec![],
+                    point: 0,
+                });
+                edge.faces.push(face_index);
+                edge.opposite.push(face[(i + 2) % face.len()]);
+            }
+        }
+        edges
+    }
+
+    // Neighbours of every vertex, and the ones joined to it by boundary edges
+    fn neighbours(
+        &self,
+        edges: &HashMap<(usize, usize), EdgeInfo>,
+    ) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut neighbours = vec![vec![]; self.positions.len()];
+        let mut boundary = vec![vec![]; self.positions.len()];
+        for (&(a, b), edge) in edges {
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+            if edge.faces.len() == 1 {
+                boundary[a].push(b);
+                boundary[b].push(a);
+            }
+        }
+        (neighbours, boundary)
+    }
+
+    fn boundary_vertex(&self, vertex: usize, boundary: &[usize], face_count: usize) -> Vec3 {
+        let p = self.positions[vertex];
+        match (face_count, boundary) {
+            (1, _) => p,
+            (_, [a, b]) => 0.75 * p + 0.125 * (self.positions[*a] + self.positions[*b]),
+            _ => p,
+        }
+    }
+
+    pub fn subdivide(&self) -> SubdivisionSurface {
+        match self.scheme {
+            SubdivisionScheme::Loop => self.subdivide_loop(),
+            SubdivisionScheme::CatmullClark => self.subdivide_catmull_clark(),
+        }
+    }
+
+    fn subdivide_loop(&self) -> SubdivisionSurface {
+        let mut edges = self.edges();
+        let (neighbours, boundary) = self.neighbours(&edges);
+        let mut face_counts = vec![0; self.positions.len()];
+        for face in &self.faces {
+            for &vertex in face {
+                face_counts[vertex] += 1;
+            }
+        }
+
+        let mut positions: Vec<Vec3> = (0..self.positions.len())
+            .map(|vertex| {
+                if !boundary[vertex].is_empty() {
+                    return self.boundary_vertex(vertex, &boundary[vertex], face_counts[vertex]);
+                }
+                let n = neighbours[vertex].len() as f64;
+                let beta = (0.625 - (0.375 + 0.25 * (2.0 * PI / n).cos()).powi(2)) / n;
+                let sum = neighbours[vertex]
+                    .iter()
+                    .fold(Vec3::new(), |sum, &neighbour| {
+                        sum + self.positions[neighbour]
+                    });
+                (1.0 - n * beta) * self.positions[vertex] + beta * sum
+            })
+            .collect();
+
+        for (&(a, b), edge) in edges.iter_mut() {
+            let (pa, pb) = (self.positions[a], self.positions[b]);
+            edge.point = positions.len();
+            positions.push(match edge.opposite.as_slice() {
+                [c, d] => 0.375 * (pa + pb) + 0.125 * (self.positions[*c] + self.positions[*d]),
+                _ => 0.5 * (pa + pb),
+            });
+        }
+
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        for face in &self.faces {
+            let (a, b, c) = (face[0], face[1], face[2]);
+            let ab = edges[&edge_key(a, b)].point;
+            let bc = edges[&edge_key(b, c)].point;
+            let ca = edges[&edge_key(c, a)].point;
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![ab, b, bc]);
+            faces.push(vec![ca, bc, c]);
+            faces.push(vec![ab, bc, ca]);
+        }
+        SubdivisionSurface {
+            positions,
+            faces,
+            scheme: self.scheme,
+        }
+    }
+
+    fn subdivide_catmull_clark(&self) -> SubdivisionSurface {
+        let mut edges = self.edges();
+        let (neighbours, boundary) = self.neighbours(&edges);
+        let face_points: Vec<Vec3> = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .fold(Vec3::new(), |sum, &vertex| sum + self.positions[vertex])
+                    / face.len() as f64
+            })
+            .collect();
+
+        let mut vertex_faces = vec![vec![]; self.positions.len()];
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for &vertex in face {
+                vertex_faces[vertex].push(face_index);
+            }
+        }
+
+        // Vertex points come first, then face points, then edge points
+        let mut positions: Vec<Vec3> = (0..self.positions.len())
+            .map(|vertex| {
+                let p = self.positions[vertex];
+                if !boundary[vertex].is_empty() {
+                    return self.boundary_vertex(
+                        vertex,
+                        &boundary[vertex],
+                        vertex_faces[vertex].len(),
+                    );
+                }
+                if vertex_faces[vertex].is_empty() {
+                    return p;
+                }
+                let n = neighbours[vertex].len() as f64;
+                let q = vertex_faces[vertex]
+                    .iter()
+                    .fold(Vec3::new(), |sum, &face| sum + face_points[face])
+                    / vertex_faces[vertex].len() as f64;
+                let r = neighbours[vertex]
+                    .iter()
+                    .fold(Vec3::new(), |sum, &neighbour| {
+                        sum + 0.5 * (p + self.positions[neighbour])
+                    })
+                    / n;
+                (q + 2.0 * r + (n - 3.0) * p) / n
+            })
+            .collect();
+        let face_offset = positions.len();
+        positions.extend(face_points.iter());
+
+        for (&(a, b), edge) in edges.iter_mut() {
+            let (pa, pb) = (self.positions[a], self.positions[b]);
+            edge.point = positions.len();
+            positions.push(match edge.faces.as_slice() {
+                [f, g] => 0.25 * (pa + pb + face_points[*f] + face_points[*g]),
+                _ => 0.5 * (pa + pb),
+            });
+        }
+
+        let mut faces = vec![];
+        for (face_index, face) in self.faces.iter().enumerate() {
+            let k = face.len();
+            for i in 0..k {
+                let (previous, vertex, next) = (face[(i + k - 1) % k], face[i], face[(i + 1) % k]);
+                faces.push(vec![
+                    vertex,
+                    edges[&edge_key(vertex, next)].point,
+                    face_offset + face_index,
+                    edges[&edge_key(previous, vertex)].point,
+                ]);
+            }
+        }
+        SubdivisionSurface {
+            positions,
+            faces,
+            scheme: self.scheme,
+        }
+    }
+
+    pub fn level_for(&self, tessellation: &Tessellation) -> usize {
+        match *tessellation {
+            Tessellation::Level(level) => level.min(MAX_LEVEL),
+            Tessellation::ScreenSpace {
+                eye,
+                vfov,
+                image_height,
+                max_edge_pixels,
+            } => {
+                let pixels_per_unit = image_height as f64 / (2.0 * (vfov.to_radians() / 2.0).tan());
+                let mut level = 0;
+                for face in &self.faces {
+                    for i in 0..face.len() {
+                        let (a, b) = (
+                            self.positions[face[i]],
+                            self.positions[face[(i + 1) % face.len()]],
+                        );
+                        let distance = (0.5 * (a + b) - eye).length().max(1e-9);
+                        let pixels = (b - a).length() / distance * pixels_per_unit;
+                        let needed = (pixels / max_edge_pixels).log2().ceil();
+                        if needed > level as f64 {
+                            level = (needed as usize).min(MAX_LEVEL);
+                        }
+                    }
+                }
+                level
+            }
+        }
+    }
+
+    pub fn to_mesh(&self, tessellation: &Tessellation, mat_ptr: Arc<dyn Material>) -> TriangleMesh {
+        let mut surface = self.clone();
+        for _ in 0..self.level_for(tessellation) {
+            surface = surface.subdivide();
+        }
+        let indices: Vec<[usize; 3]> = surface
+            .faces
+            .iter()
+            .flat_map(|face| (1..face.len() - 1).map(move |i| [face[0], face[i], face[i + 1]]))
+            .collect();
+        let normals = TriangleMesh::smooth_normals(&surface.positions, &indices);
+        TriangleMesh::new(surface.positions, indices, Some(normals), None, mat_ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::hittable::{HitRecord, Hittable};
+    use crate::rays::ray::Ray;
+    use crate::utils::vec3_utils::{dot, unit_vector};
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn cube(scheme: SubdivisionScheme) -> SubdivisionSurface {
+        let positions = (0..8)
+            .map(|i| {
+                Vec3::new_with_values(
+                    (i & 1) as f64 * 2.0 - 1.0,
+                    ((i >> 1) & 1) as f64 * 2.0 - 1.0,
+                    ((i >> 2) & 1) as f64 * 2.0 - 1.0,
+                )
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        SubdivisionSurface::new(positions, faces, scheme).unwrap()
+    }
+
+    #[test]
+    fn test_catmull_clark_cube() {
+        let once = cube(SubdivisionScheme::CatmullClark).subdivide();
+        assert_eq!(once.positions().len(), 8 + 6 + 12);
+        assert_eq!(once.faces().len(), 24);
+        // Corners of valence 3 move to (Q + 2R) / 3
+        let corner = once.positions()[7];
+        let expected = Vec3::new_with_values(5.0 / 9.0, 5.0 / 9.0, 5.0 / 9.0);
+        assert!((corner - expected).length() < 1e-12);
+
+        // The limit surface is rounded, so smooth normals point away from the center
+        let mesh = cube(SubdivisionScheme::CatmullClark)
+            .to_mesh(&Tessellation::Level(3), Arc::new(TestMaterial));
+        assert_eq!(mesh.indices().len(), 6 * 64 * 2);
+        for (position, normal) in mesh.positions().iter().zip(mesh.normals().unwrap()) {
+            assert!(dot(&unit_vector(*position), normal) > 0.8);
+            assert!(position.length() < 1.0);
+        }
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(rec.front_face && rec.t > 4.0);
+    }
+
+    #[test]
+    fn test_loop_octahedron() {
+        let positions = vec![
+            Vec3::new_with_values(1.0, 0.0, 0.0),
+            Vec3::new_with_values(-1.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            Vec3::new_with_values(0.0, -1.0, 0.0),
+            Vec3::new_with_values(0.0, 0.0, 1.0),
+            Vec3::new_with_values(0.0, 0.0, -1.0),
+        ];
+        let faces = vec![
+            vec![0, 2, 4],
+            vec![2, 1, 4],
+            vec![1, 3, 4],
+            vec![3, 0, 4],
+            vec![2, 0, 5],
+            vec![1, 2, 5],
+            vec![3, 1, 5],
+            vec![0, 3, 5],
+        ];
+        let octahedron =
+            SubdivisionSurface::new(positions, faces, SubdivisionScheme::Loop).unwrap();
+        let once = octahedron.subdivide();
+        assert_eq!(once.positions().len(), 18);
+        assert_eq!(once.faces().len(), 32);
+
+        // Every vertex is symmetric, so all original vertices shrink by the same amount
+        let radius = once.positions()[0].length();
+        for vertex in 1..6 {
+            assert!((once.positions()[vertex].length() - radius).abs() < 1e-12);
+        }
+        assert!(radius < 1.0);
+
+        let twice = once.subdivide();
+        for face in twice.faces() {
+            let normal = crate::utils::vec3_utils::cross(
+                &(twice.positions()[face[1]] - twice.positions()[face[0]]),
+                &(twice.positions()[face[2]] - twice.positions()[face[0]]),
+            );
+            // Winding is preserved, so faces keep pointing outwards
+            assert!(dot(&normal, &twice.positions()[face[0]]) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_open_grid_keeps_its_outline() {
+        // A 2x2 grid of quads in the xy plane
+        let positions: Vec<Vec3> = (0..9)
+            .map(|i| Vec3::new_with_values((i % 3) as f64, (i / 3) as f64, 0.0))
+            .collect();
+        let faces = vec![
+            vec![0, 1, 4, 3],
+            vec![1, 2, 5, 4],
+            vec![3, 4, 7, 6],
+            vec![4, 5, 8, 7],
+        ];
+        for scheme in [SubdivisionScheme::CatmullClark, SubdivisionScheme::Loop] {
+            let grid = SubdivisionSurface::new(positions.clone(), faces.clone(), scheme).unwrap();
+            let refined = grid.subdivide().subdivide();
+            for position in refined.positions() {
+                assert_eq!(position.z(), 0.0);
+            }
+            // Loop sees the quad corners as two triangles, so only Catmull-Clark pins them
+            if scheme == SubdivisionScheme::CatmullClark {
+                for corner in [0, 2, 6, 8] {
+                    assert_eq!(refined.positions()[corner], grid.positions()[corner]);
+                }
+            }
+            // The middle of a straight boundary stays on it
+            assert_eq!(refined.positions()[1], Vec3::new_with_values(1.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_screen_space_level() {
+        let surface = cube(SubdivisionScheme::CatmullClark);
+        let at = |distance: f64| Tessellation::ScreenSpace {
+            eye: Vec3::new_with_values(0.0, 0.0, distance),
+            vfov: 40.0,
+            image_height: 400,
+            max_edge_pixels: 8.0,
+        };
+        let near = surface.level_for(&at(4.0));
+        let far = surface.level_for(&at(400.0));
+        assert!(near > far);
+        assert!(near <= MAX_LEVEL);
+        assert_eq!(far, 0);
+        assert_eq!(surface.level_for(&Tessellation::Level(2)), 2);
+    }
+
+    #[test]
+    fn test_degenerate_face() {
+        let positions = vec![Vec3::new(); 4];
+        for scheme in [SubdivisionScheme::Loop, SubdivisionScheme::CatmullClark] {
+            let faces = vec![vec![0, 1, 2], vec![2, 3]];
+            match SubdivisionSurface::new(positions.clone(), faces, scheme) {
+                Err(SubdivisionError::DegenerateFace { face, vertices }) => {
+                    assert_eq!((face, vertices), (1, 2))
+                }
+                _ => panic!("expected a degenerate face error"),
+            }
+        }
+    }
+}
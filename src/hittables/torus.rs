@@ -0,0 +1,221 @@
+use crate::rays::ray::Ray;
+use crate::vectors::onb::Onb;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::utils::polynomial_utils::solve_quartic;
+use crate::utils::vec3_utils::*;
+
+use std::f64::consts::PI;
+use std::option::Option;
+use std::sync::Arc;
+
+// Ring of radius major_radius around axis, swept by a tube of radius minor_radius
+pub struct Torus {
+    center: Vec3,
+    major_radius: f64,
+    minor_radius: f64,
+    frame: Onb,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Torus {
+            center,
+            major_radius,
+            minor_radius,
+            frame: Onb::from_w(&axis),
+            mat_ptr,
+        }
+    }
+
+    // Distances along the unit local direction d, increasing
+    fn local_roots(&self, o: &Vec3, d: &Vec3) -> Vec<f64> {
+        // The quartic is badly conditioned far from the torus, so start the ray at the
+        // bounding sphere and add the offset back afterwards
+        let bound = self.major_radius + self.minor_radius;
+        let closest = -dot(o, d);
+        if (*o + closest * *d).length_squared() > bound * bound {
+            return vec![];
+        }
+        let offset = (closest - bound).max(0.0);
+        let o = *o + offset * *d;
+
+        let r2 = self.major_radius * self.major_radius;
+        let od = dot(&o, d);
+        let k = o.length_squared() + r2 - self.minor_radius * self.minor_radius;
+        let d_xy = d.x() * d.x() + d.y() * d.y();
+        let od_xy = o.x() * d.x() + o.y() * d.y();
+        let o_xy = o.x() * o.x() + o.y() * o.y();
+        solve_quartic(
+            1.0,
+            4.0 * od,
+            4.0 * od * od + 2.0 * k - 4.0 * r2 * d_xy,
+            4.0 * od * k - 8.0 * r2 * od_xy,
+            k * k - 4.0 * r2 * o_xy,
+        )
+        .into_iter()
+        .map(|s| s + offset)
+        .collect()
+    }
+
+    fn hit_record(&self, r: &Ray, t: f64, local: &Vec3) -> HitRecord {
+        let rho = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        let (ring_direction, tangent) = match rho > 0.0 {
+            true => (
+                Vec3::new_with_values(local.x() / rho, local.y() / rho, 0.0),
+                Vec3::new_with_values(-local.y() / rho, local.x() / rho, 0.0),
+            ),
+            false => (
+                Vec3::new_with_values(1.0, 0.0, 0.0),
+                Vec3::new_with_values(0.0, 1.0, 0.0),
+            ),
+        };
+        let local_normal = unit_vector(*local - self.major_radius * ring_direction);
+        // u runs around the axis and v around the tube, starting at its outer equator
+        let phi = local.y().atan2(local.x()).rem_euclid(2.0 * PI);
+        let theta = local
+            .z()
+            .atan2(rho - self.major_radius)
+            .rem_euclid(2.0 * PI);
+        let mut hit_record = HitRecord {
+            t,
+            u: phi / (2.0 * PI),
+            v: theta / (2.0 * PI),
+            p: r.at(t),
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: self.frame.to_world(&tangent),
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &self.frame.to_world(&local_normal));
+        hit_record
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = self.frame.to_local(&(r.origin() - self.center));
+        let d = self.frame.to_local(&r.direction());
+        let length = d.length();
+        let d = d / length;
+
+        for distance in self.local_roots(&o, &d) {
+            let t = distance / length;
+            if t < t_min || t_max < t {
+                continue;
+            }
+            let hit_record = self.hit_record(r, t, &(o + distance * d));
+            if hit_record.mat_ptr.opaque(&hit_record) {
+                return Some(hit_record);
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut extent = Vec3::new();
+        for axis in 0..3 {
+            let w = self.frame.w[axis];
+            extent[axis] = self.major_radius * (1.0 - w * w).max(0.0).sqrt() + self.minor_radius;
+        }
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    // Lying flat in the xz plane with the ring at radius 2 and a tube of radius 0.5
+    fn flat_torus() -> Torus {
+        Torus::new(
+            Vec3::new(),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            Arc::new(TestMaterial),
+        )
+    }
+
+    #[test]
+    fn test_torus_outside_hits() {
+        let torus = flat_torus();
+        let from_side = Ray {
+            orig: Vec3::new_with_values(10.0, 0.0, 0.0),
+            dir: Vec3::new_with_values(-2.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let rec = torus.hit(&from_side, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 3.75).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new_with_values(1.0, 0.0, 0.0)).length() < 1e-9);
+
+        // From the hole the inner side of the tube faces the ray
+        let from_hole = Ray {
+            orig: Vec3::new(),
+            dir: Vec3::new_with_values(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let rec = torus.hit(&from_hole, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 1.5).abs() < 1e-9);
+        assert!(rec.front_face);
+
+        let through_hole = Ray {
+            orig: Vec3::new_with_values(0.0, 5.0, 0.0),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(torus.hit(&through_hole, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_torus_inside_and_grazing() {
+        let torus = flat_torus();
+        let inside = Ray {
+            orig: Vec3::new_with_values(2.0, 0.0, 0.0),
+            dir: Vec3::new_with_values(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let rec = torus.hit(&inside, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        // Skimming the top of the tube
+        let grazing = |y: f64| Ray {
+            orig: Vec3::new_with_values(2.0, y, 10.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(torus
+            .hit(&grazing(0.5 - 1e-4), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(torus
+            .hit(&grazing(0.5 + 1e-4), 0.001, f64::INFINITY)
+            .is_none());
+
+        let bbox = torus.bounding_box().unwrap();
+        assert!((bbox.maximum - Vec3::new_with_values(2.5, 0.5, 2.5)).length() < 1e-9);
+    }
+}
@@ -0,0 +1,542 @@
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use crate::hittables::aabb::Aabb;
+use crate::hittables::hittable::*;
+use crate::materials::material::Material;
+use crate::textures::texture::Texture;
+use crate::utils::vec3_utils::*;
+
+use std::option::Option;
+use std::sync::Arc;
+
+const MAX_TRIANGLES_PER_LEAF: usize = 4;
+
+// Moller-Trumbore, returning t and the barycentric weights of p1 and p2
+pub fn intersect_triangle(
+    p0: &Vec3,
+    p1: &Vec3,
+    p2: &Vec3,
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = *p1 - *p0;
+    let edge2 = *p2 - *p0;
+    let pvec = cross(&r.direction(), &edge2);
+    let determinant = dot(&edge1, &pvec);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inv_determinant = 1.0 / determinant;
+    let tvec = r.origin() - *p0;
+    let b1 = dot(&tvec, &pvec) * inv_determinant;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = cross(&tvec, &edge1);
+    let b2 = dot(&r.direction(), &qvec) * inv_determinant;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    let t = dot(&edge2, &qvec) * inv_determinant;
+    if t < t_min || t_max < t {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+struct MeshNode {
+    bbox: Aabb,
+    // Index of the right child for interior nodes, or of the first triangle for leaves.
+    // The left child of an interior node is always stored directly after it.
+    first: usize,
+    // Number of triangles, zero for interior nodes
+    count: usize,
+}
+
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colors: Option<Vec<Vec3>>,
+    tangents: Vec<Vec3>,
+    indices: Vec<[usize; 3]>,
+    nodes: Vec<MeshNode>,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        let tangents = TriangleMesh::compute_tangents(&positions, &indices, &normals, &uvs);
+        let mut mesh = TriangleMesh {
+            positions,
+            normals,
+            uvs,
+            colors: None,
+            tangents,
+            indices,
+            nodes: vec![],
+            mat_ptr,
+        };
+        mesh.build_bvh();
+        mesh
+    }
+
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.indices
+    }
+
+    pub fn normals(&self) -> Option<&[Vec3]> {
+        self.normals.as_deref()
+    }
+
+    pub fn uvs(&self) -> Option<&[(f64, f64)]> {
+        self.uvs.as_deref()
+    }
+
+    pub fn colors(&self) -> Option<&[Vec3]> {
+        self.colors.as_deref()
+    }
+
+    // Per vertex colors, interpolated into the hit record for materials to pick up
+    pub fn with_colors(mut self, colors: Vec<Vec3>) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    pub fn material(&self) -> Arc<dyn Material> {
+        self.mat_ptr.clone()
+    }
+
+    // Area weighted vertex normals
+    pub fn smooth_normals(positions: &[Vec3], indices: &[[usize; 3]]) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::new(); positions.len()];
+        for triangle in indices {
+            let p0 = positions[triangle[0]];
+            let face_normal = cross(
+                &(positions[triangle[1]] - p0),
+                &(positions[triangle[2]] - p0),
+            );
+            for &vertex in triangle {
+                normals[vertex] += face_normal;
+            }
+        }
+        normals
+            .into_iter()
+            .map(|n| match n.near_zero() {
+                true => Vec3::new_with_values(0.0, 1.0, 0.0),
+                false => unit_vector(n),
+            })
+            .collect()
+    }
+
+    // Moves every vertex along its normal by the height texture value, so the
+    // surface detail is real geometry rather than a shading trick.
+    pub fn displaced(&self, height: &dyn Texture, scale: f64) -> Self {
+        let normals = match &self.normals {
+            Some(normals) => normals.clone(),
+            None => TriangleMesh::smooth_normals(&self.positions, &self.indices),
+        };
+        let positions = self
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let (u, v) = self.uvs.as_ref().map_or((0.0, 0.0), |uvs| uvs[i]);
+                *p + scale * height.value(u, v, p).x() * normals[i]
+            })
+            .collect::<Vec<Vec3>>();
+        let normals = TriangleMesh::smooth_normals(&positions, &self.indices);
+        let mut mesh = TriangleMesh::new(
+            positions,
+            self.indices.clone(),
+            Some(normals),
+            self.uvs.clone(),
+            self.mat_ptr.clone(),
+        );
+        mesh.colors = self.colors.clone();
+        mesh
+    }
+
+    fn compute_tangents(
+        positions: &[Vec3],
+        indices: &[[usize; 3]],
+        normals: &Option<Vec<Vec3>>,
+        uvs: &Option<Vec<(f64, f64)>>,
+    ) -> Vec<Vec3> {
+        let mut tangents = vec![Vec3::new(); positions.len()];
+        if let Some(uvs) = uvs {
+            for triangle in indices {
+                let [i0, i1, i2] = *triangle;
+                let dp1 = positions[i1] - positions[i0];
+                let dp2 = positions[i2] - positions[i0];
+                let (du1, dv1) = (uvs[i1].0 - uvs[i0].0, uvs[i1].1 - uvs[i0].1);
+                let (du2, dv2) = (uvs[i2].0 - uvs[i0].0, uvs[i2].1 - uvs[i0].1);
+                let determinant = du1 * dv2 - du2 * dv1;
+                if determinant.abs() < 1e-12 {
+                    continue;
+                }
+                let tangent = (dv2 * dp1 - dv1 * dp2) / determinant;
+                for &vertex in triangle {
+                    tangents[vertex] += tangent;
+                }
+            }
+        }
+
+        let face_normals = TriangleMesh::smooth_normals(positions, indices);
+        tangents
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let n = normals
+                    .as_ref()
+                    .map_or(face_normals[i], |normals| normals[i]);
+                let t = t - dot(&t, &n) * n;
+                match t.near_zero() {
+                    true => perpendicular(&n),
+                    false => unit_vector(t),
+                }
+            })
+            .collect()
+    }
+
+    fn triangle_bbox(&self, triangle: usize) -> Aabb {
+        let [i0, i1, i2] = self.indices[triangle];
+        Aabb::from_points(&[self.positions[i0], self.positions[i1], self.positions[i2]])
+    }
+
+    fn build_bvh(&mut self) {
+        if self.indices.is_empty() {
+            return;
+        }
+        let bboxes = (0..self.indices.len())
+            .map(|i| self.triangle_bbox(i))
+            .collect::<Vec<Aabb>>();
+        let mut order = (0..self.indices.len()).collect::<Vec<usize>>();
+        let mut nodes = vec![];
+        TriangleMesh::build_node(&bboxes, &mut order, 0, &mut nodes);
+        self.indices = order.iter().map(|&i| self.indices[i]).collect();
+        self.nodes = nodes;
+    }
+
+    fn build_node(
+        bboxes: &[Aabb],
+        order: &mut [usize],
+        offset: usize,
+        nodes: &mut Vec<MeshNode>,
+    ) -> usize {
+        let bbox = order
+            .iter()
+            .map(|&i| bboxes[i])
+            .reduce(|a, b| Aabb::surrounding_box(&a, &b))
+            .unwrap();
+        let node_index = nodes.len();
+        nodes.push(MeshNode {
+            bbox,
+            first: offset,
+            count: order.len(),
+        });
+        if order.len() <= MAX_TRIANGLES_PER_LEAF {
+            return node_index;
+        }
+
+        let centroids = Aabb::from_points(
+            &order
+                .iter()
+                .map(|&i| bboxes[i].centroid())
+                .collect::<Vec<Vec3>>(),
+        );
+        let axis = centroids.longest_axis();
+        order.sort_by(|&a, &b| {
+            bboxes[a].centroid()[axis]
+                .partial_cmp(&bboxes[b].centroid()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mid = order.len() / 2;
+        let (left, right) = order.split_at_mut(mid);
+        TriangleMesh::build_node(bboxes, left, offset, nodes);
+        let right_index = TriangleMesh::build_node(bboxes, right, offset + mid, nodes);
+        nodes[node_index].first = right_index;
+        nodes[node_index].count = 0;
+        node_index
+    }
+
+    // Moller-Trumbore intersection, returning the distance and barycentric coordinates
+    fn intersect_triangle(
+        &self,
+        triangle: usize,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let [i0, i1, i2] = self.indices[triangle];
+        intersect_triangle(
+            &self.positions[i0],
+            &self.positions[i1],
+            &self.positions[i2],
+            r,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn hit_record(&self, triangle: usize, r: &Ray, t: f64, b1: f64, b2: f64) -> HitRecord {
+        let [i0, i1, i2] = self.indices[triangle];
+        let b0 = 1.0 - b1 - b2;
+        let interpolate = |values: &[Vec3]| b0 * values[i0] + b1 * values[i1] + b2 * values[i2];
+
+        let p0 = self.positions[i0];
+        let geometric_normal = unit_vector(cross(
+            &(self.positions[i1] - p0),
+            &(self.positions[i2] - p0),
+        ));
+        let (u, v) = match &self.uvs {
+            Some(uvs) => (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            ),
+            None => (b1, b2),
+        };
+
+        let mut hit_record = HitRecord {
+            t,
+            u,
+            v,
+            p: r.at(t),
+            normal: Vec3::new(),
+            geometric_normal: Vec3::new(),
+            tangent: Vec3::new(),
+            front_face: false,
+            mat_ptr: self.mat_ptr.clone(),
+            color: self.colors.as_deref().map(interpolate),
+            object_id: 0,
+            material_id: None,
+        };
+        hit_record.set_face_normal(r, &geometric_normal);
+
+        let mut shading_normal = match &self.normals {
+            Some(normals) => unit_vector(interpolate(normals)),
+            None => geometric_normal,
+        };
+        // Keep the shading normal on the same side as the geometric one
+        if dot(&shading_normal, &geometric_normal) < 0.0 {
+            shading_normal = -shading_normal;
+        }
+        if !hit_record.front_face {
+            shading_normal = -shading_normal;
+        }
+        hit_record.normal = shading_normal;
+
+        let tangent = interpolate(&self.tangents);
+        let tangent = tangent - dot(&tangent, &shading_normal) * shading_normal;
+        hit_record.tangent = match tangent.near_zero() {
+            true => perpendicular(&shading_normal),
+            false => unit_vector(tangent),
+        };
+        hit_record
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut closest_so_far = t_max;
+        let mut closest = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bbox.hit(r, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node_index + 1);
+                continue;
+            }
+            for triangle in node.first..node.first + node.count {
+                if let Some((t, b1, b2)) =
+                    self.intersect_triangle(triangle, r, t_min, closest_so_far)
+                {
+                    let hit_record = self.hit_record(triangle, r, t, b1, b2);
+                    if self.mat_ptr.opaque(&hit_record) {
+                        closest_so_far = t;
+                        closest = Some(hit_record);
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    fn grid(size: usize) -> TriangleMesh {
+        let mut positions = vec![];
+        let mut uvs = vec![];
+        for j in 0..=size {
+            for i in 0..=size {
+                let u = i as f64 / size as f64;
+                let v = j as f64 / size as f64;
+                positions.push(Vec3::new_with_values(u, v, 0.0));
+                uvs.push((u, v));
+            }
+        }
+        let mut indices = vec![];
+        for j in 0..size {
+            for i in 0..size {
+                let corner = j * (size + 1) + i;
+                indices.push([corner, corner + 1, corner + size + 2]);
+                indices.push([corner, corner + size + 2, corner + size + 1]);
+            }
+        }
+        TriangleMesh::new(positions, indices, None, Some(uvs), Arc::new(TestMaterial))
+    }
+
+    #[test]
+    fn test_mesh_hit() {
+        let mesh = grid(8);
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.3, 0.7, 2.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-12);
+        assert!((rec.u - 0.3).abs() < 1e-12);
+        assert!((rec.v - 0.7).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::new_with_values(0.0, 0.0, 1.0));
+        assert!((rec.tangent - Vec3::new_with_values(1.0, 0.0, 0.0)).near_zero());
+
+        let missing = Ray {
+            orig: Vec3::new_with_values(1.3, 0.7, 2.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(mesh.hit(&missing, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_mesh_back_face() {
+        let mesh = grid(2);
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.6, 0.2, -1.0),
+            dir: Vec3::new_with_values(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, Vec3::new_with_values(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_smooth_mesh_does_not_leak_at_grazing_angles() {
+        use crate::hittables::{hittable_list::HittableList, plane::Plane};
+        use crate::lights::point_light::PointLight;
+        use crate::materials::{
+            diffuse_light::DiffuseLight, lambertian::Lambertian, material::Material, metal::Metal,
+        };
+        use crate::renderers::renderer::ray_color;
+        use crate::scenes::scene::Scene;
+
+        // Flat triangle with vertex normals tilted far towards +x, with lights only
+        // below it. Mirroring a ray that grazes the shading surface about the shading
+        // normal lands below the triangle, as does the direction to the point light.
+        let tilted = unit_vector(Vec3::new_with_values(0.866, 0.0, 0.5));
+        let scene = |material: Arc<dyn Material>| {
+            let mut world = HittableList::new();
+            world.add(Box::new(TriangleMesh::new(
+                vec![
+                    Vec3::new_with_values(0.0, 0.0, 0.0),
+                    Vec3::new_with_values(1.0, 0.0, 0.0),
+                    Vec3::new_with_values(0.0, 1.0, 0.0),
+                ],
+                vec![[0, 1, 2]],
+                Some(vec![tilted, tilted, tilted]),
+                None,
+                material,
+            )));
+            world.add(Box::new(Plane::new(
+                Vec3::new_with_values(0.0, 0.0, -1.0),
+                Vec3::new_with_values(0.0, 0.0, 1.0),
+                Arc::new(DiffuseLight::new(Vec3::new_with_values(1.0, 1.0, 1.0))),
+            )));
+            let mut scene = Scene::new(world);
+            scene.add_light(Arc::new(PointLight::new(
+                Vec3::new_with_values(2.25, 0.3, -0.2),
+                Vec3::new_with_values(1.0, 1.0, 1.0),
+            )));
+            scene
+        };
+        let ray = Ray {
+            orig: Vec3::new_with_values(-0.05, 0.3, 1.0),
+            dir: Vec3::new_with_values(0.3, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        let metal = scene(Arc::new(Metal {
+            albedo: Vec3::new_with_values(1.0, 1.0, 1.0),
+            fuzz: 0.0,
+        }));
+        let rec = metal.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.geometric_normal, Vec3::new_with_values(0.0, 0.0, 1.0));
+        assert!(dot(&rec.normal, &ray.direction()) < 0.0);
+        assert_eq!(ray_color(&ray, &metal, 10), Vec3::new());
+
+        let lambertian = scene(Arc::new(Lambertian {
+            albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+        }));
+        for _ in 0..100 {
+            assert_eq!(ray_color(&ray, &lambertian, 2), Vec3::new());
+        }
+    }
+
+    #[test]
+    fn test_displaced_mesh() {
+        struct Ramp;
+        impl Texture for Ramp {
+            fn value(&self, u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+                Vec3::new_with_values(u, u, u)
+            }
+        }
+        let mesh = grid(4).displaced(&Ramp, 0.5);
+        let ray = Ray {
+            orig: Vec3::new_with_values(1.0, 0.5, 2.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p.z() - 0.5).abs() < 1e-9);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod cameras;
+pub mod denoisers;
+pub mod films;
 pub mod hittables;
+pub mod lights;
+pub mod loaders;
 pub mod materials;
 pub mod rays;
+pub mod renderers;
+pub mod scenes;
+pub mod textures;
 pub mod utils;
 pub mod vectors;
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::hittables::hittable::Hittable;
+use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+// Samples a shape with an emissive material, the radiance comes from the material so
+// the same shape seen directly by camera or BSDF rays matches what light sampling finds.
+pub struct AreaLight {
+    shape: Arc<dyn Hittable>,
+}
+
+impl AreaLight {
+    pub fn new(shape: Arc<dyn Hittable>) -> Self {
+        AreaLight { shape }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let direction = unit_vector(self.shape.random(origin));
+        let pdf = self.shape.pdf_value(origin, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let ray = Ray {
+            orig: *origin,
+            dir: direction,
+            time: 0.0,
+        };
+        let rec = self.shape.hit(&ray, 0.001, f64::INFINITY)?;
+        Some(LightSample {
+            direction,
+            distance: rec.t,
+            radiance: rec.mat_ptr.emitted(&ray, &rec),
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.shape.pdf_value(origin, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::quad::Quad;
+    use crate::materials::diffuse_light::DiffuseLight;
+
+    #[test]
+    fn test_area_light_sample() {
+        let light = AreaLight::new(Arc::new(Quad::new(
+            Vec3::new_with_values(-1.0, 2.0, -1.0),
+            Vec3::new_with_values(2.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 0.0, 2.0),
+            Arc::new(DiffuseLight::new(Vec3::new_with_values(3.0, 3.0, 3.0))),
+        )));
+        for _ in 0..100 {
+            let sample = light.sample(&Vec3::new()).unwrap();
+            assert!((sample.direction.y() * sample.distance - 2.0).abs() < 1e-9);
+            assert_eq!(sample.radiance, Vec3::new_with_values(3.0, 3.0, 3.0));
+            assert!((light.pdf(&Vec3::new(), &sample.direction) - sample.pdf).abs() < 1e-9);
+        }
+
+        // The quad faces down, so from above it only shows its unlit back
+        let sample = light.sample(&Vec3::new_with_values(0.0, 4.0, 0.0)).unwrap();
+        assert_eq!(sample.radiance, Vec3::new());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+// Light arriving from a single direction at infinity, such as an idealised sun.
+// Irradiance is measured perpendicular to the light direction.
+pub struct DirectionalLight {
+    direction: Vec3,
+    irradiance: Vec3,
+}
+
+impl DirectionalLight {
+    // Direction the light travels in, so (0, -1, 0) shines straight down
+    pub fn new(direction: Vec3, irradiance: Vec3) -> Self {
+        DirectionalLight {
+            direction: unit_vector(direction),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
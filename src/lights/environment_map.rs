@@ -0,0 +1,307 @@
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::hdr::HdrDecoder;
+
+use crate::utils::color_utils::luminance;
+use crate::utils::distribution_utils::Distribution2D;
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+#[derive(Debug)]
+pub enum EnvironmentMapError {
+    Io(std::io::Error),
+    Hdr(image::ImageError),
+    Exr(exr::error::Error),
+    UnsupportedFormat(String),
+    InvalidSize {
+        width: usize,
+        height: usize,
+        pixels: usize,
+    },
+}
+
+impl fmt::Display for EnvironmentMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvironmentMapError::Io(error) => {
+                write!(f, "could not read environment map: {}", error)
+            }
+            EnvironmentMapError::Hdr(error) => write!(f, "invalid HDR environment map: {}", error),
+            EnvironmentMapError::Exr(error) => write!(f, "invalid EXR environment map: {}", error),
+            EnvironmentMapError::UnsupportedFormat(extension) => {
+                write!(f, "unsupported environment map format '{}'", extension)
+            }
+            EnvironmentMapError::InvalidSize {
+                width,
+                height,
+                pixels,
+            } => write!(
+                f,
+                "environment map of {}x{} pixels cannot hold {} pixels",
+                width, height, pixels
+            ),
+        }
+    }
+}
+
+impl Error for EnvironmentMapError {}
+
+// Lat-long environment light, importance sampled by luminance so that small bright
+// features like the sun are found by light sampling instead of producing fireflies.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    // Pixels are stored row by row starting at the top (+y) of the sphere. The rotation
+    // about the y axis is in degrees.
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: Vec<Vec3>,
+        rotation: f64,
+        intensity: f64,
+    ) -> Result<Self, EnvironmentMapError> {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            return Err(EnvironmentMapError::InvalidSize {
+                width,
+                height,
+                pixels: pixels.len(),
+            });
+        }
+
+        let mut func = vec![0.0; width * height];
+        for row in 0..height {
+            let sin_theta = (PI * (row as f64 + 0.5) / height as f64).sin();
+            for column in 0..width {
+                func[row * width + column] = luminance(&pixels[row * width + column]) * sin_theta;
+            }
+        }
+        Ok(EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: rotation.to_radians(),
+            intensity,
+            distribution: Distribution2D::new(&func, width, height),
+        })
+    }
+
+    pub fn load(path: &str, rotation: f64, intensity: f64) -> Result<Self, EnvironmentMapError> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let (width, height, pixels) = match extension.as_str() {
+            "hdr" => EnvironmentMap::read_hdr(path)?,
+            "exr" => EnvironmentMap::read_exr(path)?,
+            _ => return Err(EnvironmentMapError::UnsupportedFormat(extension)),
+        };
+        EnvironmentMap::new(width, height, pixels, rotation, intensity)
+    }
+
+    fn read_hdr(path: &str) -> Result<(usize, usize, Vec<Vec3>), EnvironmentMapError> {
+        let reader = BufReader::new(File::open(path).map_err(EnvironmentMapError::Io)?);
+        let decoder = HdrDecoder::new(reader).map_err(EnvironmentMapError::Hdr)?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()
+            .map_err(EnvironmentMapError::Hdr)?
+            .iter()
+            .map(|pixel| Vec3::new_with_values(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64))
+            .collect();
+        Ok((metadata.width as usize, metadata.height as usize, pixels))
+    }
+
+    fn read_exr(path: &str) -> Result<(usize, usize, Vec<Vec3>), EnvironmentMapError> {
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            path,
+            |resolution, _| {
+                (
+                    resolution.width(),
+                    vec![Vec3::new(); resolution.width() * resolution.height()],
+                )
+            },
+            |(width, pixels), position, (r, g, b, _): (f32, f32, f32, f32)| {
+                pixels[position.y() * *width + position.x()] =
+                    Vec3::new_with_values(r as f64, g as f64, b as f64)
+            },
+        )
+        .map_err(EnvironmentMapError::Exr)?;
+        let size = image.layer_data.size;
+        let (_, pixels) = image.layer_data.channel_data.pixels;
+        Ok((size.width(), size.height(), pixels))
+    }
+
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let direction = unit_vector(*direction);
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+        let phi = direction.z().atan2(direction.x()) - self.rotation;
+        let u = ((phi + PI) / (2.0 * PI)).rem_euclid(1.0);
+        (u, theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let theta = v * PI;
+        let phi = u * 2.0 * PI - PI + self.rotation;
+        Vec3::new_with_values(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+
+    fn lookup(&self, u: f64, v: f64) -> Vec3 {
+        let column = ((u * self.width as f64) as usize).min(self.width - 1);
+        let row = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.intensity * self.pixels[row * self.width + column]
+    }
+}
+
+impl Light for EnvironmentMap {
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        let (u, v, pdf_uv) = self
+            .distribution
+            .sample_continuous(random_f64(), random_f64());
+        let sin_theta = (v * PI).sin();
+        if pdf_uv == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction: self.uv_to_direction(u, v),
+            distance: f64::INFINITY,
+            radiance: self.lookup(u, v),
+            pdf: pdf_uv / (2.0 * PI * PI * sin_theta),
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Vec3, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        match sin_theta == 0.0 {
+            true => 0.0,
+            false => self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta),
+        }
+    }
+
+    fn escaped_radiance(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+        self.lookup(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_file;
+
+    // A dim map with a single bright "sun" pixel
+    fn sun_map(rotation: f64) -> EnvironmentMap {
+        let (width, height) = (32, 16);
+        let mut pixels = vec![Vec3::new_with_values(0.1, 0.1, 0.1); width * height];
+        pixels[4 * width + 20] = Vec3::new_with_values(1000.0, 1000.0, 1000.0);
+        EnvironmentMap::new(width, height, pixels, rotation, 1.0).unwrap()
+    }
+
+    #[test]
+    fn test_uv_direction_round_trip() {
+        let map = sun_map(30.0);
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+            let (u2, v2) = map.direction_to_uv(&map.uv_to_direction(u, v));
+            assert!((u - u2).abs() < 1e-9);
+            assert!((v - v2).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_sample_pdf_matches_pdf() {
+        let map = sun_map(45.0);
+        for _ in 0..100 {
+            let sample = map.sample(&Vec3::new()).unwrap();
+            let pdf = map.pdf(&Vec3::new(), &sample.direction);
+            assert!((sample.pdf - pdf).abs() < 1e-6 * pdf);
+            assert_eq!(sample.radiance, map.escaped_radiance(&sample.direction));
+        }
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one() {
+        let (width, height) = (16, 8);
+        let pixels = (0..width * height)
+            .map(|i| Vec3::new_with_values(1.0, 1.0, 1.0) * (i % 5 + 1) as f64)
+            .collect();
+        let map = EnvironmentMap::new(width, height, pixels, 10.0, 1.0).unwrap();
+        let samples = 200000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            total += map.pdf(&Vec3::new(), &Vec3::random_unit_vector()) * 4.0 * PI;
+        }
+        assert!((total / samples as f64 - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_sampling_finds_the_sun() {
+        let map = sun_map(0.0);
+        let samples = 1000;
+        let sun_samples = (0..samples)
+            .filter_map(|_| map.sample(&Vec3::new()))
+            .filter(|sample| sample.radiance.x() > 1.0)
+            .count();
+        assert!(sun_samples > samples * 9 / 10);
+    }
+
+    #[test]
+    fn test_load_hdr() {
+        let path = "test_environment.hdr";
+        let pixels = vec![image::Rgb([0.5f32, 1.0, 2.0]); 8 * 4];
+        let file = File::create(path).unwrap();
+        image::codecs::hdr::HdrEncoder::new(file)
+            .encode(&pixels, 8, 4)
+            .unwrap();
+        let map = EnvironmentMap::load(path, 0.0, 2.0);
+        remove_file(path).unwrap();
+
+        let radiance = map
+            .unwrap()
+            .escaped_radiance(&Vec3::new_with_values(0.0, 1.0, 0.0));
+        assert_eq!(radiance, Vec3::new_with_values(1.0, 2.0, 4.0));
+    }
+
+    #[test]
+    fn test_load_unsupported_format() {
+        match EnvironmentMap::load("sky.png", 0.0, 1.0) {
+            Err(EnvironmentMapError::UnsupportedFormat(extension)) => assert_eq!(extension, "png"),
+            _ => panic!("expected an unsupported format error"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        let pixels = vec![Vec3::new_with_values(1.0, 1.0, 1.0); 8];
+        for (width, height) in [(0, 8), (8, 0), (4, 4)] {
+            match EnvironmentMap::new(width, height, pixels.clone(), 0.0, 1.0) {
+                Err(EnvironmentMapError::InvalidSize { pixels, .. }) => assert_eq!(pixels, 8),
+                _ => panic!("expected an invalid size error"),
+            }
+        }
+    }
+}
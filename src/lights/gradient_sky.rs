@@ -0,0 +1,42 @@
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+// The white to blue sky from the book. It is only picked up by rays that miss the
+// scene, so it is never sampled directly.
+pub struct GradientSky {
+    pub horizon: Vec3,
+    pub zenith: Vec3,
+}
+
+impl GradientSky {
+    pub fn new() -> Self {
+        GradientSky {
+            horizon: Vec3::new_with_values(1.0, 1.0, 1.0),
+            zenith: Vec3::new_with_values(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Default for GradientSky {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Light for GradientSky {
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        None
+    }
+
+    fn escaped_radiance(&self, direction: &Vec3) -> Vec3 {
+        let unit_direction = unit_vector(*direction);
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        (1.0 - t) * self.horizon + t * self.zenith
+    }
+}
@@ -0,0 +1,32 @@
+use crate::vectors::vec3::Vec3;
+
+pub struct LightSample {
+    // Unit direction from the shaded point towards the light
+    pub direction: Vec3,
+    // Distance to the sampled point on the light, infinite for lights at infinity
+    pub distance: f64,
+    pub radiance: Vec3,
+    // Solid angle density of direction, or the selection weight for delta lights
+    pub pdf: f64,
+    pub delta: bool,
+}
+
+pub trait Light: Sync + Send {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample>;
+
+    // Solid angle density with which sample would return direction from origin
+    fn pdf(&self, _origin: &Vec3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Lights at infinity are found by rays that leave the scene rather than by hitting
+    // an emissive surface
+    fn is_infinite(&self) -> bool {
+        false
+    }
+
+    // Radiance arriving along rays that leave the scene, only non zero for lights at infinity
+    fn escaped_radiance(&self, _direction: &Vec3) -> Vec3 {
+        Vec3::new()
+    }
+}
@@ -0,0 +1,9 @@
+pub mod area_light;
+pub mod directional_light;
+pub mod environment_map;
+pub mod gradient_sky;
+pub mod light;
+pub mod point_light;
+pub mod preetham_sky;
+pub mod spot_light;
+pub mod sun_light;
@@ -0,0 +1,53 @@
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+// Isotropic point light, intensity is the radiant intensity in every direction
+pub struct PointLight {
+    pub position: Vec3,
+    pub intensity: Vec3,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Vec3) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *origin;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction: unit_vector(to_light),
+            distance: distance_squared.sqrt(),
+            radiance: self.intensity / distance_squared,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inverse_square_falloff() {
+        let light = PointLight::new(Vec3::new(), Vec3::new_with_values(8.0, 8.0, 8.0));
+        let near = light.sample(&Vec3::new_with_values(0.0, 1.0, 0.0)).unwrap();
+        let far = light.sample(&Vec3::new_with_values(0.0, 2.0, 0.0)).unwrap();
+        assert_eq!(near.radiance, Vec3::new_with_values(8.0, 8.0, 8.0));
+        assert_eq!(far.radiance, Vec3::new_with_values(2.0, 2.0, 2.0));
+        assert_eq!(far.direction, Vec3::new_with_values(0.0, -1.0, 0.0));
+        assert_eq!(far.distance, 2.0);
+        assert!(far.delta);
+    }
+}
@@ -0,0 +1,315 @@
+use std::f64::consts::PI;
+
+use crate::utils::color_utils::luminance;
+use crate::utils::distribution_utils::Distribution2D;
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::{dot, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+use super::sun_light::SunLight;
+
+const TABLE_WIDTH: usize = 64;
+const TABLE_HEIGHT: usize = 32;
+
+// Coefficients of the Perez sky luminance distribution
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    fn value(&self, cos_theta: f64, gamma: f64) -> f64 {
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos() * gamma.cos())
+    }
+}
+
+// Direction towards the sun from its elevation above the horizon and its azimuth,
+// measured from +x towards +z, both in degrees
+pub fn sun_direction(elevation: f64, azimuth: f64) -> Vec3 {
+    let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+    Vec3::new_with_values(
+        elevation.cos() * azimuth.cos(),
+        elevation.sin(),
+        elevation.cos() * azimuth.sin(),
+    )
+}
+
+// Analytic daylight sky from "A Practical Analytic Model for Daylight" (Preetham et al.)
+// with y up. Directions below the horizon see a diffuse ground lit by the sky.
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    turbidity: f64,
+    perez_y: Perez,
+    perez_x: Perez,
+    perez_yy: Perez,
+    zenith: (f64, f64, f64),
+    intensity: f64,
+    ground_radiance: Vec3,
+    distribution: Distribution2D,
+}
+
+impl PreethamSky {
+    // The model works in kcd/m^2, intensity scales that to scene radiance
+    pub fn new(
+        sun_elevation: f64,
+        sun_azimuth: f64,
+        turbidity: f64,
+        ground_albedo: Vec3,
+        intensity: f64,
+    ) -> Self {
+        let t = turbidity;
+        let theta_s = PI / 2.0 - sun_elevation.to_radians().clamp(0.0, PI / 2.0);
+        let (theta2, theta3) = (theta_s * theta_s, theta_s * theta_s * theta_s);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_s + 0.26688);
+
+        let mut sky = PreethamSky {
+            sun_direction: sun_direction(sun_elevation, sun_azimuth),
+            turbidity,
+            perez_y: Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            perez_x: Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            perez_yy: Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+            zenith: (zenith_luminance.max(0.0), zenith_x, zenith_y),
+            intensity,
+            ground_radiance: Vec3::new(),
+            distribution: Distribution2D::new(&[0.0], 1, 1),
+        };
+        sky.ground_radiance = ground_albedo * sky.horizontal_irradiance() / PI;
+        sky.distribution = sky.build_distribution();
+        sky
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    // Sun disk matching this sky, with its colour given by the atmospheric transmittance
+    // along the sun direction and the irradiance it delivers at normal incidence.
+    pub fn sun_light(&self, angular_diameter: f64, irradiance: f64) -> SunLight {
+        SunLight::new(
+            self.sun_direction,
+            angular_diameter,
+            irradiance * self.sun_transmittance(),
+        )
+    }
+
+    // Rayleigh and aerosol transmittance at 680, 550 and 440 nm
+    pub fn sun_transmittance(&self) -> Vec3 {
+        let theta_s = self.sun_direction.y().clamp(0.0, 1.0).acos();
+        let relative_air_mass =
+            1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = (0.04608 * self.turbidity - 0.04586).max(0.0);
+        let transmittance = |lambda: f64| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-relative_air_mass * (rayleigh + aerosol)).exp()
+        };
+        Vec3::new_with_values(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        )
+    }
+
+    fn sky_radiance(&self, direction: &Vec3) -> Vec3 {
+        let cos_theta = direction.y().max(0.01);
+        let gamma = dot(direction, &self.sun_direction).clamp(-1.0, 1.0).acos();
+        let theta_s = self.sun_direction.y().clamp(0.0, 1.0).acos();
+
+        let relative = |perez: &Perez, zenith: f64| {
+            zenith * perez.value(cos_theta, gamma) / perez.value(1.0, theta_s)
+        };
+        let (zenith_luminance, zenith_x, zenith_y) = self.zenith;
+        let luminance = relative(&self.perez_y, zenith_luminance);
+        let x = relative(&self.perez_x, zenith_x);
+        let y = relative(&self.perez_yy, zenith_y);
+        self.intensity * xyy_to_rgb(x, y, luminance)
+    }
+
+    fn horizontal_irradiance(&self) -> Vec3 {
+        let steps = 64;
+        let mut irradiance = Vec3::new();
+        for i in 0..steps {
+            let theta = (i as f64 + 0.5) / steps as f64 * PI / 2.0;
+            for j in 0..2 * steps {
+                let phi = (j as f64 + 0.5) / (2 * steps) as f64 * 2.0 * PI;
+                let direction = Vec3::new_with_values(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                irradiance += theta.cos() * theta.sin() * self.sky_radiance(&direction);
+            }
+        }
+        (PI / 2.0 / steps as f64) * (2.0 * PI / (2 * steps) as f64) * irradiance
+    }
+
+    fn build_distribution(&self) -> Distribution2D {
+        let mut func = vec![0.0; TABLE_WIDTH * TABLE_HEIGHT];
+        for row in 0..TABLE_HEIGHT {
+            let v = (row as f64 + 0.5) / TABLE_HEIGHT as f64;
+            for column in 0..TABLE_WIDTH {
+                let u = (column as f64 + 0.5) / TABLE_WIDTH as f64;
+                let direction = uv_to_direction(u, v);
+                func[row * TABLE_WIDTH + column] =
+                    luminance(&self.escaped_radiance(&direction)) * (v * PI).sin();
+            }
+        }
+        Distribution2D::new(&func, TABLE_WIDTH, TABLE_HEIGHT)
+    }
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Vec3 {
+    if y <= 0.0 {
+        return Vec3::new();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Vec3::new_with_values(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+fn direction_to_uv(direction: &Vec3) -> (f64, f64) {
+    let theta = direction.y().clamp(-1.0, 1.0).acos();
+    let phi = direction.z().atan2(direction.x());
+    (((phi + PI) / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+}
+
+fn uv_to_direction(u: f64, v: f64) -> Vec3 {
+    let (theta, phi) = (v * PI, u * 2.0 * PI - PI);
+    Vec3::new_with_values(
+        theta.sin() * phi.cos(),
+        theta.cos(),
+        theta.sin() * phi.sin(),
+    )
+}
+
+impl Light for PreethamSky {
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        let (u, v, pdf_uv) = self
+            .distribution
+            .sample_continuous(random_f64(), random_f64());
+        let sin_theta = (v * PI).sin();
+        if pdf_uv == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+        let direction = uv_to_direction(u, v);
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.escaped_radiance(&direction),
+            pdf: pdf_uv / (2.0 * PI * PI * sin_theta),
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Vec3, direction: &Vec3) -> f64 {
+        let (u, v) = direction_to_uv(&unit_vector(*direction));
+        let sin_theta = (v * PI).sin();
+        match sin_theta == 0.0 {
+            true => 0.0,
+            false => self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta),
+        }
+    }
+
+    fn escaped_radiance(&self, direction: &Vec3) -> Vec3 {
+        let direction = unit_vector(*direction);
+        match direction.y() < 0.0 {
+            true => self.ground_radiance,
+            false => self.sky_radiance(&direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear_sky(sun_elevation: f64) -> PreethamSky {
+        PreethamSky::new(
+            sun_elevation,
+            0.0,
+            2.5,
+            Vec3::new_with_values(0.3, 0.3, 0.3),
+            1.0,
+        )
+    }
+
+    #[test]
+    fn test_clear_sky_is_blue_at_zenith() {
+        let radiance = clear_sky(45.0).escaped_radiance(&Vec3::new_with_values(0.0, 1.0, 0.0));
+        assert!(radiance.z() > radiance.x());
+        assert!(radiance.x() > 0.0);
+    }
+
+    #[test]
+    fn test_sky_brighter_around_sun() {
+        let sky = clear_sky(30.0);
+        let towards_sun = luminance(&sky.escaped_radiance(&sun_direction(35.0, 0.0)));
+        let away_from_sun = luminance(&sky.escaped_radiance(&sun_direction(35.0, 180.0)));
+        assert!(towards_sun > 2.0 * away_from_sun);
+    }
+
+    #[test]
+    fn test_ground_reflects_sky() {
+        let sky = clear_sky(30.0);
+        let ground = sky.escaped_radiance(&Vec3::new_with_values(0.0, -1.0, 0.0));
+        assert!(ground.x() > 0.0);
+        assert!(ground.z() > ground.x());
+    }
+
+    #[test]
+    fn test_sunset_is_redder() {
+        let noon = clear_sky(80.0).sun_transmittance();
+        let sunset = clear_sky(2.0).sun_transmittance();
+        assert!(noon.z() / noon.x() > sunset.z() / sunset.x());
+        assert!(noon.x() > sunset.x());
+    }
+
+    #[test]
+    fn test_sample_pdf_matches_pdf() {
+        let sky = clear_sky(20.0);
+        for _ in 0..100 {
+            let sample = sky.sample(&Vec3::new()).unwrap();
+            let pdf = sky.pdf(&Vec3::new(), &sample.direction);
+            assert!((sample.pdf - pdf).abs() < 1e-6 * pdf);
+        }
+    }
+}
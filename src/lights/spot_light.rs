@@ -0,0 +1,100 @@
+use crate::utils::vec3_utils::{dot, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+// Point light restricted to a cone. The intensity is full inside falloff_start and
+// fades smoothly to zero at cone_angle, both half angles in degrees.
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    intensity: Vec3,
+    cos_cone_angle: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Vec3,
+        target: Vec3,
+        intensity: Vec3,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> Self {
+        SpotLight {
+            position,
+            direction: unit_vector(target - position),
+            intensity,
+            cos_cone_angle: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(cone_angle).to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta < self.cos_cone_angle {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let t = (cos_theta - self.cos_cone_angle) / (self.cos_falloff_start - self.cos_cone_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *origin;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let direction = unit_vector(to_light);
+        let falloff = self.falloff(dot(&-direction, &self.direction));
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: falloff * self.intensity / distance_squared,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spot() -> SpotLight {
+        SpotLight::new(
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            Vec3::new(),
+            Vec3::new_with_values(1.0, 1.0, 1.0),
+            30.0,
+            20.0,
+        )
+    }
+
+    #[test]
+    fn test_spot_cone() {
+        let light = spot();
+        let centre = light.sample(&Vec3::new()).unwrap();
+        assert_eq!(centre.radiance, Vec3::new_with_values(1.0, 1.0, 1.0));
+
+        // 45 degrees off axis is outside the cone
+        assert!(light
+            .sample(&Vec3::new_with_values(1.0, 0.0, 0.0))
+            .is_none());
+
+        // 25 degrees off axis is inside the falloff region
+        let offset = 25.0f64.to_radians().tan();
+        let edge = light
+            .sample(&Vec3::new_with_values(offset, 0.0, 0.0))
+            .unwrap();
+        let unattenuated = 1.0 / (1.0 + offset * offset);
+        assert!(edge.radiance.x() > 0.0 && edge.radiance.x() < unattenuated);
+    }
+}
@@ -0,0 +1,98 @@
+use std::f64::consts::PI;
+
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::{cross, dot, perpendicular, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+use super::light::{Light, LightSample};
+
+// Distant disk light such as the sun. It is given by the irradiance it delivers at
+// normal incidence, so changing the angular size only changes how soft shadows are.
+pub struct SunLight {
+    direction: Vec3,
+    cos_theta_max: f64,
+    radiance: Vec3,
+}
+
+impl SunLight {
+    // Angular diameter in degrees, the real sun is about 0.53
+    pub fn new(direction: Vec3, angular_diameter: f64, irradiance: Vec3) -> Self {
+        let cos_theta_max = (angular_diameter.to_radians() / 2.0).cos();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        SunLight {
+            direction: unit_vector(direction),
+            cos_theta_max,
+            radiance: irradiance / solid_angle,
+        }
+    }
+}
+
+impl Light for SunLight {
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        let cos_theta = 1.0 - random_f64() * (1.0 - self.cos_theta_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_f64();
+
+        let u = perpendicular(&self.direction);
+        let v = cross(&self.direction, &u);
+        let direction =
+            sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * self.direction;
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.radiance,
+            pdf: 1.0 / (2.0 * PI * (1.0 - self.cos_theta_max)),
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Vec3, direction: &Vec3) -> f64 {
+        match dot(&unit_vector(*direction), &self.direction) >= self.cos_theta_max {
+            true => 1.0 / (2.0 * PI * (1.0 - self.cos_theta_max)),
+            false => 0.0,
+        }
+    }
+
+    fn escaped_radiance(&self, direction: &Vec3) -> Vec3 {
+        match dot(&unit_vector(*direction), &self.direction) >= self.cos_theta_max {
+            true => self.radiance,
+            false => Vec3::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_samples_stay_in_disk() {
+        let direction = unit_vector(Vec3::new_with_values(1.0, 1.0, 0.0));
+        let sun = SunLight::new(direction, 2.0, Vec3::new_with_values(1.0, 1.0, 1.0));
+        for _ in 0..100 {
+            let sample = sun.sample(&Vec3::new()).unwrap();
+            assert!(dot(&sample.direction, &direction) >= sun.cos_theta_max - 1e-12);
+            assert_eq!(sun.pdf(&Vec3::new(), &sample.direction), sample.pdf);
+            assert_eq!(sun.escaped_radiance(&sample.direction), sample.radiance);
+        }
+        assert_eq!(sun.escaped_radiance(&-direction), Vec3::new());
+    }
+
+    #[test]
+    fn test_sun_irradiance_independent_of_size() {
+        for angular_diameter in [0.53, 5.0] {
+            let sun = SunLight::new(
+                Vec3::new_with_values(0.0, 1.0, 0.0),
+                angular_diameter,
+                Vec3::new_with_values(3.0, 3.0, 3.0),
+            );
+            let sample = sun.sample(&Vec3::new()).unwrap();
+            let irradiance = sample.radiance.y() / sample.pdf;
+            assert!((irradiance - 3.0).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,749 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::material::AlphaMode as GltfAlphaMode;
+use gltf::mesh::Mode;
+use gltf::texture::WrappingMode;
+use gltf::Document;
+use image::RgbImage;
+
+use crate::cameras::camera::Camera;
+use crate::hittables::alpha_masked::AlphaMode;
+use crate::hittables::hittable_list::HittableList;
+use crate::hittables::triangle_mesh::TriangleMesh;
+use crate::lights::directional_light::DirectionalLight;
+use crate::lights::light::Light;
+use crate::lights::point_light::PointLight;
+use crate::lights::spot_light::SpotLight;
+use crate::materials::alpha_mask::AlphaMask;
+use crate::materials::material::Material;
+use crate::materials::metallic_roughness::MetallicRoughness;
+use crate::materials::normal_map::NormalMap;
+use crate::materials::vertex_color::VertexColor;
+use crate::scenes::scene::Scene;
+use crate::textures::image_texture::ImageTexture;
+use crate::textures::scaled_texture::ScaledTexture;
+use crate::textures::solid_color::SolidColor;
+use crate::textures::texture::Texture;
+use crate::utils::matrix_utils::{
+    determinant, multiply, transform_normal, transform_point, transform_vector, Matrix, IDENTITY,
+};
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+const SUPPORTED_EXTENSIONS: [&str; 2] = ["KHR_lights_punctual", "KHR_materials_emissive_strength"];
+
+#[derive(Debug)]
+pub enum GltfError {
+    Import(gltf::Error),
+    InvalidData(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Import(error) => write!(f, "could not import glTF file: {}", error),
+            GltfError::InvalidData(message) => write!(f, "invalid glTF data: {}", message),
+        }
+    }
+}
+
+impl Error for GltfError {}
+
+// Everything imported from a glTF file. Cameras are listed in the order their nodes are
+// visited, and parts of the file without a counterpart in this crate end up in warnings.
+pub struct GltfImport {
+    pub scene: Scene,
+    pub cameras: Vec<Camera>,
+    pub warnings: Vec<String>,
+}
+
+fn to_vec3(values: [f32; 3]) -> Vec3 {
+    Vec3::new_with_values(values[0] as f64, values[1] as f64, values[2] as f64)
+}
+
+// Converts decoded image data to 8 bit RGB, or to the alpha channel repeated in RGB
+fn rgb_image(data: &gltf::image::Data, alpha: bool) -> Option<RgbImage> {
+    let (channels, channel_size) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes: &[u8]| -> u8 {
+        match channel_size {
+            1 => bytes[0],
+            2 => (u16::from_ne_bytes([bytes[0], bytes[1]]) >> 8) as u8,
+            _ => (f32::from_ne_bytes(bytes.try_into().unwrap()).clamp(0.0, 1.0) * 255.0).round()
+                as u8,
+        }
+    };
+    let pixel_size = channels * channel_size;
+    if data.pixels.len() < (data.width * data.height) as usize * pixel_size {
+        return None;
+    }
+
+    let mut image = RgbImage::new(data.width, data.height);
+    for (pixel, bytes) in image.pixels_mut().zip(data.pixels.chunks_exact(pixel_size)) {
+        let values: Vec<u8> = bytes.chunks_exact(channel_size).map(channel).collect();
+        // Two channel images are luminance with alpha
+        let (rgb, a) = match values.as_slice() {
+            [l] => ([*l, *l, *l], 255),
+            [l, a] => ([*l, *l, *l], *a),
+            [r, g, b] => ([*r, *g, *b], 255),
+            [r, g, b, a] => ([*r, *g, *b], *a),
+            _ => return None,
+        };
+        *pixel = match alpha {
+            true => image::Rgb([a, a, a]),
+            false => image::Rgb(rgb),
+        };
+    }
+    Some(image)
+}
+
+fn scaled(texture: Option<Arc<dyn Texture>>, scale: Vec3) -> Arc<dyn Texture> {
+    match texture {
+        Some(texture) => Arc::new(ScaledTexture { texture, scale }),
+        None => Arc::new(SolidColor::new(scale)),
+    }
+}
+
+// How the channels of a texture image are read. Colors are stored as sRGB, everything
+// else as linear data.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum TextureContent {
+    Color,
+    Data,
+    Alpha,
+}
+
+struct Importer<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    aspect_ratio: f64,
+    // Keyed by image index, whether the texture repeats and what it holds
+    textures: HashMap<(usize, bool, TextureContent), Option<Arc<dyn Texture>>>,
+    materials: HashMap<Option<usize>, Arc<dyn Material>>,
+    // Materials in the order meshes first use them, which numbers them in the scene
+    mesh_materials: Vec<Arc<dyn Material>>,
+    world: HittableList,
+    lights: Vec<Arc<dyn Light>>,
+    cameras: Vec<Camera>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Importer<'a> {
+    fn texture(
+        &mut self,
+        texture: gltf::texture::Texture,
+        tex_coord: u32,
+        content: TextureContent,
+    ) -> Option<Arc<dyn Texture>> {
+        if tex_coord != 0 {
+            self.warnings.push(format!(
+                "texture {} uses texture coordinate set {}, only set 0 is imported",
+                texture.index(),
+                tex_coord
+            ));
+        }
+        let sampler = texture.sampler();
+        let repeat = sampler.wrap_s() != WrappingMode::ClampToEdge
+            || sampler.wrap_t() != WrappingMode::ClampToEdge;
+        let index = texture.source().index();
+        let key = (index, repeat, content);
+        if let Some(texture) = self.textures.get(&key) {
+            return texture.clone();
+        }
+
+        let converted = self
+            .images
+            .get(index)
+            .and_then(|data| rgb_image(data, content == TextureContent::Alpha));
+        let texture: Option<Arc<dyn Texture>> = match converted {
+            Some(image) => {
+                let mut texture = ImageTexture::new(image);
+                if repeat {
+                    texture = texture.repeating();
+                }
+                if content == TextureContent::Color {
+                    texture = texture.srgb();
+                }
+                Some(Arc::new(texture))
+            }
+            None => {
+                self.warnings.push(format!(
+                    "image {} could not be converted, using factors only",
+                    index
+                ));
+                None
+            }
+        };
+        self.textures.insert(key, texture.clone());
+        texture
+    }
+
+    fn material(&mut self, material: gltf::Material) -> Arc<dyn Material> {
+        if let Some(imported) = self.materials.get(&material.index()) {
+            return imported.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, a] = pbr.base_color_factor();
+        let base_texture = pbr.base_color_texture();
+        let base_color = base_texture
+            .as_ref()
+            .and_then(|info| self.texture(info.texture(), info.tex_coord(), TextureContent::Color));
+        let base_color = scaled(base_color, to_vec3([r, g, b]));
+
+        let metallic_roughness = pbr
+            .metallic_roughness_texture()
+            .and_then(|info| self.texture(info.texture(), info.tex_coord(), TextureContent::Data));
+        let metallic_roughness = scaled(
+            metallic_roughness,
+            Vec3::new_with_values(
+                1.0,
+                pbr.roughness_factor() as f64,
+                pbr.metallic_factor() as f64,
+            ),
+        );
+
+        let emissive = material
+            .emissive_texture()
+            .and_then(|info| self.texture(info.texture(), info.tex_coord(), TextureContent::Color));
+        let strength = material.emissive_strength().unwrap_or(1.0) as f64;
+        let emissive = scaled(emissive, strength * to_vec3(material.emissive_factor()));
+
+        let mut imported: Arc<dyn Material> = Arc::new(MetallicRoughness {
+            base_color,
+            metallic_roughness,
+            emissive,
+        });
+
+        if let Some(normal) = material.normal_texture() {
+            if let Some(map) =
+                self.texture(normal.texture(), normal.tex_coord(), TextureContent::Data)
+            {
+                imported = Arc::new(NormalMap {
+                    base: imported,
+                    map,
+                    strength: normal.scale() as f64,
+                });
+            }
+        }
+
+        // Blending is approximated by stochastically keeping hits
+        let mode = match material.alpha_mode() {
+            GltfAlphaMode::Opaque => None,
+            GltfAlphaMode::Mask => Some(AlphaMode::Cutout(
+                material.alpha_cutoff().unwrap_or(0.5) as f64
+            )),
+            GltfAlphaMode::Blend => Some(AlphaMode::Stochastic),
+        };
+        if let Some(mode) = mode {
+            let alpha = base_texture.as_ref().and_then(|info| {
+                self.texture(info.texture(), info.tex_coord(), TextureContent::Alpha)
+            });
+            imported = Arc::new(AlphaMask {
+                base: imported,
+                mask: scaled(alpha, Vec3::new_with_values(a as f64, a as f64, a as f64)),
+                mode,
+            });
+        }
+
+        self.materials.insert(material.index(), imported.clone());
+        imported
+    }
+
+    fn mesh(&mut self, mesh: gltf::Mesh, transform: &Matrix) -> Result<(), GltfError> {
+        let buffers = self.buffers;
+        let mirrored = determinant(transform) < 0.0;
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                self.warnings.push(format!(
+                    "mesh {} has a {:?} primitive, only triangles are imported",
+                    mesh.index(),
+                    primitive.mode()
+                ));
+                continue;
+            }
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &**data));
+            let positions: Vec<Vec3> = match reader.read_positions() {
+                Some(positions) => positions
+                    .map(|p| transform_point(transform, &to_vec3(p)))
+                    .collect(),
+                None => {
+                    self.warnings.push(format!(
+                        "mesh {} has a primitive without positions",
+                        mesh.index()
+                    ));
+                    continue;
+                }
+            };
+            let normals = reader.read_normals().map(|normals| {
+                normals
+                    .map(|n| transform_normal(transform, &to_vec3(n)))
+                    .collect::<Vec<Vec3>>()
+            });
+            // glTF puts the origin of texture space at the top left
+            let uvs = reader.read_tex_coords(0).map(|uvs| {
+                uvs.into_f32()
+                    .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                    .collect::<Vec<(f64, f64)>>()
+            });
+            let colors = reader
+                .read_colors(0)
+                .map(|colors| colors.into_rgb_f32().map(to_vec3).collect::<Vec<Vec3>>());
+            let flat: Vec<usize> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+                None => (0..positions.len()).collect(),
+            };
+
+            if let Some(index) = flat.iter().find(|&&index| index >= positions.len()) {
+                return Err(GltfError::InvalidData(format!(
+                    "mesh {} refers to vertex {} of {}",
+                    mesh.index(),
+                    index,
+                    positions.len()
+                )));
+            }
+            let lengths_match = normals.as_ref().is_none_or(|n| n.len() == positions.len())
+                && uvs.as_ref().is_none_or(|uvs| uvs.len() == positions.len())
+                && colors.as_ref().is_none_or(|c| c.len() == positions.len());
+            if !lengths_match {
+                return Err(GltfError::InvalidData(format!(
+                    "mesh {} has attributes of different lengths",
+                    mesh.index()
+                )));
+            }
+            let indices: Vec<[usize; 3]> = flat
+                .chunks_exact(3)
+                .map(|triangle| match mirrored {
+                    true => [triangle[0], triangle[2], triangle[1]],
+                    false => [triangle[0], triangle[1], triangle[2]],
+                })
+                .collect();
+
+            let mut material = self.material(primitive.material());
+            if colors.is_some() {
+                material = Arc::new(VertexColor { base: material });
+            }
+            self.mesh_materials.push(material.clone());
+            let triangle_mesh = TriangleMesh::new(positions, indices, normals, uvs, material);
+            self.world.add(Box::new(match colors {
+                Some(colors) => triangle_mesh.with_colors(colors),
+                None => triangle_mesh,
+            }));
+        }
+        Ok(())
+    }
+
+    fn camera(&mut self, camera: gltf::Camera, transform: &Matrix) {
+        match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => {
+                let origin = transform_point(transform, &Vec3::new());
+                let forward = transform_vector(transform, &Vec3::new_with_values(0.0, 0.0, -1.0));
+                let up = transform_vector(transform, &Vec3::new_with_values(0.0, 1.0, 0.0));
+                self.cameras.push(Camera::new(
+                    origin,
+                    origin + forward,
+                    up,
+                    (perspective.yfov() as f64).to_degrees(),
+                    self.aspect_ratio,
+                    0.0,
+                    1.0,
+                ));
+            }
+            gltf::camera::Projection::Orthographic(_) => self.warnings.push(format!(
+                "camera {} is orthographic, only perspective cameras are imported",
+                camera.index()
+            )),
+        }
+    }
+
+    // Light intensities in candela and lux are used directly as radiometric quantities
+    fn light(&mut self, light: gltf::khr_lights_punctual::Light, transform: &Matrix) {
+        let intensity = light.intensity() as f64 * to_vec3(light.color());
+        let position = transform_point(transform, &Vec3::new());
+        let direction = unit_vector(transform_vector(
+            transform,
+            &Vec3::new_with_values(0.0, 0.0, -1.0),
+        ));
+        let imported: Arc<dyn Light> = match light.kind() {
+            Kind::Directional => Arc::new(DirectionalLight::new(direction, intensity)),
+            Kind::Point => Arc::new(PointLight::new(position, intensity)),
+            Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => Arc::new(SpotLight::new(
+                position,
+                position + direction,
+                intensity,
+                (outer_cone_angle as f64).to_degrees(),
+                (inner_cone_angle as f64).to_degrees(),
+            )),
+        };
+        self.lights.push(imported);
+    }
+
+    fn node(&mut self, node: gltf::Node, parent: &Matrix) -> Result<(), GltfError> {
+        let local = node
+            .transform()
+            .matrix()
+            .map(|column| column.map(|value| value as f64));
+        let transform = multiply(parent, &local);
+        if let Some(mesh) = node.mesh() {
+            self.mesh(mesh, &transform)?;
+        }
+        if let Some(camera) = node.camera() {
+            self.camera(camera, &transform);
+        }
+        if let Some(light) = node.light() {
+            self.light(light, &transform);
+        }
+        for child in node.children() {
+            self.node(child, &transform)?;
+        }
+        Ok(())
+    }
+}
+
+fn import(
+    document: &Document,
+    buffers: &[gltf::buffer::Data],
+    images: &[gltf::image::Data],
+    aspect_ratio: f64,
+) -> Result<GltfImport, GltfError> {
+    let mut importer = Importer {
+        buffers,
+        images,
+        aspect_ratio,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        mesh_materials: vec![],
+        world: HittableList::new(),
+        lights: vec![],
+        cameras: vec![],
+        warnings: vec![],
+    };
+
+    for extension in document.extensions_used() {
+        if !SUPPORTED_EXTENSIONS.contains(&extension) {
+            importer
+                .warnings
+                .push(format!("extension {} is not supported", extension));
+        }
+    }
+    if document.animations().count() > 0 || document.skins().count() > 0 {
+        importer
+            .warnings
+            .push("animations and skins are ignored, the rest pose is imported".to_string());
+    }
+
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                importer.node(node, &IDENTITY)?;
+            }
+        }
+        None => importer.warnings.push("file has no scenes".to_string()),
+    }
+
+    let mut scene = Scene::new(importer.world);
+    scene.lights = importer.lights;
+    for material in importer.mesh_materials {
+        scene.add_material(material);
+    }
+    Ok(GltfImport {
+        scene,
+        cameras: importer.cameras,
+        warnings: importer.warnings,
+    })
+}
+
+// Imports the default scene of a .gltf or .glb file. Cameras use the aspect ratio of the
+// image being rendered rather than the one stored in the file.
+pub fn load_gltf(path: &str, aspect_ratio: f64) -> Result<GltfImport, GltfError> {
+    let (document, buffers, images) = gltf::import(path).map_err(GltfError::Import)?;
+    import(&document, &buffers, &images, aspect_ratio)
+}
+
+// Same as load_gltf for files already in memory, which must not refer to external files
+pub fn read_gltf(bytes: &[u8], aspect_ratio: f64) -> Result<GltfImport, GltfError> {
+    let (document, buffers, images) = gltf::import_slice(bytes).map_err(GltfError::Import)?;
+    import(&document, &buffers, &images, aspect_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::camera_model::CameraModel;
+    use crate::hittables::hittable::Hittable;
+    use crate::rays::ray::Ray;
+    use crate::utils::color_utils::srgb_to_linear;
+
+    // A unit quad two levels down the node hierarchy, a camera, a point light and a
+    // spot light. The quad node is scaled by 2 and its parent moved to z = -5.
+    const SAMPLE: &str = r#"{
+        "asset": {"version": "2.0"},
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {"KHR_lights_punctual": {"lights": [
+            {"type": "point", "color": [1.0, 1.0, 1.0], "intensity": 20.0},
+            {"type": "spot", "intensity": 5.0, "spot": {"innerConeAngle": 0.2, "outerConeAngle": 0.4}}
+        ]}},
+        "scene": 0,
+        "scenes": [{"nodes": [0, 2, 3, 4, 5]}],
+        "nodes": [
+            {"translation": [0.0, 0.0, -5.0], "children": [1]},
+            {"scale": [2.0, 2.0, 2.0], "mesh": 0},
+            {"camera": 0},
+            {"translation": [0.0, 3.0, 0.0], "extensions": {"KHR_lights_punctual": {"light": 0}}},
+            {"translation": [0.0, 0.0, 2.0], "extensions": {"KHR_lights_punctual": {"light": 1}}},
+            {"camera": 1}
+        ],
+        "cameras": [
+            {"type": "perspective", "perspective": {"yfov": 0.8, "znear": 0.1}},
+            {"type": "orthographic", "orthographic": {"xmag": 1.0, "ymag": 1.0, "znear": 0.1, "zfar": 10.0}}
+        ],
+        "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "TEXCOORD_0": 1}, "indices": 2, "material": 0}]}],
+        "materials": [MATERIAL],
+        TEXTURES
+        "buffers": [BUFFER],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": 48},
+            {"buffer": 0, "byteOffset": 48, "byteLength": 32},
+            {"buffer": 0, "byteOffset": 80, "byteLength": 12}
+            IMAGE_VIEW
+        ],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [-0.5, -0.5, 0.0], "max": [0.5, 0.5, 0.0]},
+            {"bufferView": 1, "componentType": 5126, "count": 4, "type": "VEC2"},
+            {"bufferView": 2, "componentType": 5123, "count": 6, "type": "SCALAR"}
+        ]
+    }"#;
+
+    fn geometry() -> Vec<u8> {
+        let mut bytes = vec![];
+        let corners = [[-0.5f32, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+        for [x, y] in corners {
+            for value in [x, y, 0.0] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        for [x, y] in corners {
+            for value in [x + 0.5, 0.5 - y] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        for index in [0u16, 1, 2, 0, 2, 3] {
+            bytes.extend(index.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut encoded = String::new();
+        for chunk in bytes.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+                bits | (byte as u32) << (16 - 8 * i)
+            });
+            for i in 0..4 {
+                encoded.push(match i <= chunk.len() {
+                    true => ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char,
+                    false => '=',
+                });
+            }
+        }
+        encoded
+    }
+
+    // Binary glTF with the image stored after the geometry in the binary chunk
+    fn glb(json: &str, binary: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut binary = binary.to_vec();
+        binary.resize(binary.len().div_ceil(4) * 4, 0);
+
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        bytes.extend((json.len() as u32).to_le_bytes());
+        bytes.extend(b"JSON");
+        bytes.extend(json);
+        bytes.extend((binary.len() as u32).to_le_bytes());
+        bytes.extend(b"BIN\0");
+        bytes.extend(binary);
+        bytes
+    }
+
+    fn ray(x: f64) -> Ray {
+        Ray {
+            orig: Vec3::new_with_values(x, 0.0, 0.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_import_gltf() {
+        let json = SAMPLE
+            .replace(
+                "MATERIAL",
+                r#"{"pbrMetallicRoughness": {"baseColorFactor": [0.8, 0.2, 0.2, 1.0], "metallicFactor": 0.0, "roughnessFactor": 0.5}}"#,
+            )
+            .replace("TEXTURES", "")
+            .replace("IMAGE_VIEW", "")
+            .replace(
+                "BUFFER",
+                &format!(
+                    r#"{{"byteLength": 92, "uri": "data:application/octet-stream;base64,{}"}}"#,
+                    base64(&geometry())
+                ),
+            );
+        let imported = read_gltf(json.as_bytes(), 1.0).unwrap();
+
+        // Node transforms are applied down the hierarchy
+        let world = &imported.scene.world;
+        let rec = world.hit(&ray(0.0), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!(world.hit(&ray(0.9), 0.001, f64::INFINITY).is_some());
+        assert!(world.hit(&ray(1.1), 0.001, f64::INFINITY).is_none());
+
+        // The base color factor shows up in the scattered light
+        let direction = Vec3::new_with_values(0.0, 0.3, 1.0);
+        let f = rec.mat_ptr.eval(&ray(0.0), &rec, &direction);
+        assert!(f.x() > 2.0 * f.y() && f.y() > 0.0);
+
+        // The orthographic camera is reported rather than imported
+        assert_eq!(imported.cameras.len(), 1);
+        assert_eq!(imported.warnings.len(), 1);
+        let center = imported.cameras[0].get_ray(0.5, 0.5).unwrap();
+        assert!((unit_vector(center.direction()) - ray(0.0).direction()).length() < 1e-9);
+
+        // The spot light points down -z towards the quad
+        assert_eq!(imported.scene.lights.len(), 2);
+        let spot = &imported.scene.lights[1];
+        let sample = spot.sample(&Vec3::new_with_values(0.0, 0.0, -5.0)).unwrap();
+        assert!(sample.radiance.x() > 0.0);
+        let outside = spot.sample(&Vec3::new_with_values(5.0, 0.0, 1.0));
+        assert!(outside.is_none_or(|sample| sample.radiance.length() == 0.0));
+    }
+
+    #[test]
+    fn test_import_glb_with_texture() {
+        // Opaque green on the left, transparent on the right
+        let mut png = vec![];
+        image::codecs::png::PngEncoder::new(&mut png)
+            .encode(
+                &[0, 255, 0, 255, 255, 0, 0, 0],
+                2,
+                1,
+                image::ColorType::Rgba8,
+            )
+            .unwrap();
+        let mut binary = geometry();
+        let image_offset = binary.len();
+        binary.extend(&png);
+
+        let json = SAMPLE
+            .replace(
+                "MATERIAL",
+                r#"{"pbrMetallicRoughness": {"baseColorTexture": {"index": 0}}, "alphaMode": "MASK"}"#,
+            )
+            .replace(
+                "TEXTURES",
+                r#""textures": [{"source": 0}], "images": [{"bufferView": 3, "mimeType": "image/png"}],"#,
+            )
+            .replace(
+                "IMAGE_VIEW",
+                &format!(
+                    r#", {{"buffer": 0, "byteOffset": {}, "byteLength": {}}}"#,
+                    image_offset,
+                    png.len()
+                ),
+            )
+            .replace("BUFFER", &format!(r#"{{"byteLength": {}}}"#, binary.len()));
+        let imported = read_gltf(&glb(&json, &binary), 1.0).unwrap();
+
+        let world = &imported.scene.world;
+        let rec = world.hit(&ray(-0.5), 0.001, f64::INFINITY).unwrap();
+        let direction = Vec3::new_with_values(0.0, 0.3, 1.0);
+        let f = rec.mat_ptr.eval(&ray(-0.5), &rec, &direction);
+        assert!(f.y() > 10.0 * f.x());
+        // The transparent half of the texture is cut out
+        assert!(world.hit(&ray(0.5), 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_import_emissive_normal_mapped_material() {
+        // One sRGB pixel used as emissive color, normal map and alpha mask
+        let mut png = vec![];
+        image::codecs::png::PngEncoder::new(&mut png)
+            .encode(&[128, 128, 255, 255], 1, 1, image::ColorType::Rgba8)
+            .unwrap();
+        let mut binary = geometry();
+        let image_offset = binary.len();
+        binary.extend(&png);
+
+        let json = SAMPLE
+            .replace(
+                "MATERIAL",
+                r#"{"pbrMetallicRoughness": {"baseColorTexture": {"index": 0}}, "normalTexture": {"index": 0}, "emissiveTexture": {"index": 0}, "emissiveFactor": [1.0, 1.0, 1.0], "alphaMode": "MASK"}"#,
+            )
+            .replace(
+                "TEXTURES",
+                r#""textures": [{"source": 0}], "images": [{"bufferView": 3, "mimeType": "image/png"}],"#,
+            )
+            .replace(
+                "IMAGE_VIEW",
+                &format!(
+                    r#", {{"buffer": 0, "byteOffset": {}, "byteLength": {}}}"#,
+                    image_offset,
+                    png.len()
+                ),
+            )
+            .replace("BUFFER", &format!(r#"{{"byteLength": {}}}"#, binary.len()));
+        let imported = read_gltf(&glb(&json, &binary), 1.0).unwrap();
+
+        // The emission makes it through the normal map and alpha mask, decoded from sRGB
+        let rec = imported
+            .scene
+            .world
+            .hit(&ray(0.0), 0.001, f64::INFINITY)
+            .unwrap();
+        let emitted = rec.mat_ptr.emitted(&ray(0.0), &rec);
+        let expected = srgb_to_linear(128.0 / 255.0);
+        assert!((emitted.x() - expected).abs() < 1e-9);
+        assert!((emitted.y() - expected).abs() < 1e-9);
+        assert!((emitted.z() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_gltf_errors() {
+        assert!(matches!(
+            read_gltf(b"not a gltf file", 1.0),
+            Err(GltfError::Import(_))
+        ));
+        assert!(matches!(
+            load_gltf("does_not_exist.gltf", 1.0),
+            Err(GltfError::Import(_))
+        ));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod gltf_loader;
+pub mod pbrt_loader;
+pub mod ply_loader;
+pub mod stl_loader;
@@ -0,0 +1,1075 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cameras::camera::Camera;
+use crate::hittables::disk::Disk;
+use crate::hittables::hittable::Hittable;
+use crate::hittables::hittable_list::HittableList;
+use crate::hittables::sphere::Sphere;
+use crate::hittables::triangle_mesh::TriangleMesh;
+use crate::lights::directional_light::DirectionalLight;
+use crate::lights::environment_map::EnvironmentMap;
+use crate::lights::point_light::PointLight;
+use crate::lights::spot_light::SpotLight;
+use crate::loaders::ply_loader::read_ply;
+use crate::materials::dielectric::Dielectric;
+use crate::materials::diffuse_light::DiffuseLight;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::material::Material;
+use crate::materials::metal::Metal;
+use crate::materials::vertex_color::VertexColor;
+use crate::scenes::scene::Scene;
+use crate::utils::matrix_utils::{
+    axes, determinant, inverse, multiply, rotation, scaling, transform_normal, transform_point,
+    transform_vector, translation, Matrix, IDENTITY,
+};
+use crate::utils::vec3_utils::{cross, dot, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum PbrtError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for PbrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PbrtError::Io(error) => write!(f, "could not read pbrt file: {}", error),
+            PbrtError::Parse { line, message } => {
+                write!(f, "invalid pbrt file at line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for PbrtError {}
+
+impl From<std::io::Error> for PbrtError {
+    fn from(error: std::io::Error) -> Self {
+        PbrtError::Io(error)
+    }
+}
+
+// Everything a pbrt file describes, with the render settings from Film, Sampler and
+// Integrator. Directives and parameters outside the supported subset end up in warnings.
+pub struct PbrtScene {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: i32,
+    pub output: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Directive(String),
+    Str(String),
+    Number(f64),
+    Open,
+    Close,
+}
+
+fn parse_error(line: usize, message: String) -> PbrtError {
+    PbrtError::Parse { line, message }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PbrtError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(&c) = chars.peek() {
+        match c {
+            '\n' => {
+                line += 1;
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '[' | ']' => {
+                chars.next();
+                tokens.push((
+                    match c {
+                        '[' => Token::Open,
+                        _ => Token::Close,
+                    },
+                    line,
+                ));
+            }
+            '"' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(parse_error(line, "unterminated string".to_string()))
+                        }
+                        Some(c) => string.push(c),
+                    }
+                }
+                tokens.push((Token::Str(string), line));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '"' || c == '[' || c == ']' || c == '#' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let token = match word.parse() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) if word.chars().next().is_some_and(char::is_alphabetic) => {
+                        Token::Directive(word)
+                    }
+                    Err(_) => return Err(parse_error(line, format!("unexpected '{}'", word))),
+                };
+                tokens.push((token, line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f64),
+    Str(String),
+}
+
+// A parameter declared as "type name" followed by its values
+#[derive(Debug, Clone)]
+struct Param {
+    kind: String,
+    name: String,
+    values: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ParamSet {
+    params: Vec<Param>,
+}
+
+impl ParamSet {
+    fn find(&self, name: &str) -> Option<&Param> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    fn numbers(&self, name: &str) -> Option<Vec<f64>> {
+        self.find(name).map(|param| {
+            param
+                .values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Number(number) => Some(*number),
+                    Value::Str(_) => None,
+                })
+                .collect()
+        })
+    }
+
+    fn float(&self, name: &str, default: f64) -> f64 {
+        self.numbers(name)
+            .and_then(|numbers| numbers.first().copied())
+            .unwrap_or(default)
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        self.find(name)
+            .and_then(|param| match param.values.first() {
+                Some(Value::Str(string)) => Some(string.clone()),
+                _ => None,
+            })
+    }
+
+    fn bool(&self, name: &str, default: bool) -> bool {
+        match self.string(name).as_deref() {
+            Some("true") => true,
+            Some("false") => false,
+            _ => default,
+        }
+    }
+
+    fn point(&self, name: &str, default: Vec3) -> Vec3 {
+        match self.numbers(name).as_deref() {
+            Some([x, y, z, ..]) => Vec3::new_with_values(*x, *y, *z),
+            _ => default,
+        }
+    }
+}
+
+// Graphics state saved by AttributeBegin
+#[derive(Clone)]
+struct GraphicsState {
+    transform: Matrix,
+    material: Arc<dyn Material>,
+    // Radiance of the diffuse area light attached to the following shapes
+    area_light: Option<Vec3>,
+}
+
+struct CameraSettings {
+    kind: String,
+    params: ParamSet,
+    camera_from_world: Matrix,
+}
+
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian {
+        albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+    })
+}
+
+// pbrt uses a left handed coordinate system, so the scene is mirrored in x on the way
+// in. Renders then show the scene the same way round as pbrt does.
+fn world_from_pbrt() -> Matrix {
+    scaling(&Vec3::new_with_values(-1.0, 1.0, 1.0))
+}
+
+// Normal incidence reflectance of a conductor
+fn conductor_reflectance(eta: &Vec3, k: &Vec3) -> Vec3 {
+    let mut reflectance = Vec3::new();
+    for channel in 0..3 {
+        let (n, k) = (eta[channel], k[channel]);
+        reflectance[channel] = ((n - 1.0).powi(2) + k * k) / ((n + 1.0).powi(2) + k * k);
+    }
+    reflectance
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    directory: PathBuf,
+    state: GraphicsState,
+    attribute_stack: Vec<GraphicsState>,
+    transform_stack: Vec<Matrix>,
+    named_materials: HashMap<String, Arc<dyn Material>>,
+    camera: Option<CameraSettings>,
+    scene: Scene,
+    width: u32,
+    height: u32,
+    samples_per_pixel: u32,
+    max_depth: i32,
+    output: Option<String>,
+    warnings: Vec<String>,
+    // Line of the directive being parsed
+    line: usize,
+}
+
+impl Parser {
+    fn warn(&mut self, message: String) {
+        self.warnings
+            .push(format!("line {}: {}", self.line, message));
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, PbrtError> {
+        match self.tokens.get(self.position) {
+            Some((token, line)) => {
+                self.position += 1;
+                self.line = *line;
+                Ok(token.clone())
+            }
+            None => Err(parse_error(self.line, "file ends early".to_string())),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, PbrtError> {
+        match self.next()? {
+            Token::Number(number) => Ok(number),
+            token => Err(parse_error(
+                self.line,
+                format!("expected a number, found {:?}", token),
+            )),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, PbrtError> {
+        match self.next()? {
+            Token::Str(string) => Ok(string),
+            token => Err(parse_error(
+                self.line,
+                format!("expected a string, found {:?}", token),
+            )),
+        }
+    }
+
+    fn vector(&mut self) -> Result<Vec3, PbrtError> {
+        Ok(Vec3::new_with_values(
+            self.number()?,
+            self.number()?,
+            self.number()?,
+        ))
+    }
+
+    // Sixteen numbers in column major order, with or without brackets
+    fn matrix(&mut self) -> Result<Matrix, PbrtError> {
+        let bracketed = self.peek() == Some(&Token::Open);
+        if bracketed {
+            self.next()?;
+        }
+        let mut m = IDENTITY;
+        for i in 0..16 {
+            m[i / 4][i % 4] = self.number()?;
+        }
+        if bracketed && self.next()? != Token::Close {
+            return Err(parse_error(
+                self.line,
+                "expected ']' after matrix".to_string(),
+            ));
+        }
+        Ok(m)
+    }
+
+    fn params(&mut self) -> Result<ParamSet, PbrtError> {
+        let mut params = ParamSet::default();
+        while let Some(Token::Str(_)) = self.peek() {
+            let declaration = self.string()?;
+            let (kind, name) = match declaration.split_whitespace().collect::<Vec<_>>()[..] {
+                [kind, name] => (kind.to_string(), name.to_string()),
+                _ => {
+                    return Err(parse_error(
+                        self.line,
+                        format!("invalid parameter declaration '{}'", declaration),
+                    ))
+                }
+            };
+            let mut values = vec![];
+            match self.next()? {
+                Token::Open => loop {
+                    match self.next()? {
+                        Token::Close => break,
+                        Token::Number(number) => values.push(Value::Number(number)),
+                        Token::Str(string) => values.push(Value::Str(string)),
+                        token => {
+                            return Err(parse_error(
+                                self.line,
+                                format!("unexpected {:?} in parameter '{}'", token, name),
+                            ))
+                        }
+                    }
+                },
+                Token::Number(number) => values.push(Value::Number(number)),
+                Token::Str(string) => values.push(Value::Str(string)),
+                token => {
+                    return Err(parse_error(
+                        self.line,
+                        format!("missing value for parameter '{}', found {:?}", name, token),
+                    ))
+                }
+            }
+            params.params.push(Param { kind, name, values });
+        }
+        Ok(params)
+    }
+
+    // Skips the arguments of a directive that is not supported
+    fn skip_arguments(&mut self) {
+        while !matches!(self.peek(), Some(Token::Directive(_)) | None) {
+            self.position += 1;
+        }
+    }
+
+    // RGB value of a spectrum parameter. Other spectrum representations and textures
+    // fall back to the default.
+    fn color(&mut self, params: &ParamSet, name: &str, default: Vec3) -> Vec3 {
+        let param = match params.find(name) {
+            Some(param) => param.clone(),
+            None => return default,
+        };
+        match (param.kind.as_str(), params.numbers(name).as_deref()) {
+            ("rgb" | "color", Some([r, g, b])) => Vec3::new_with_values(*r, *g, *b),
+            ("float", Some([value])) => Vec3::new_with_values(*value, *value, *value),
+            (kind, _) => {
+                self.warn(format!(
+                    "{} parameter '{}' is not supported, using the default",
+                    kind, name
+                ));
+                default
+            }
+        }
+    }
+
+    fn material(&mut self, kind: &str, params: &ParamSet) -> Arc<dyn Material> {
+        match kind {
+            "matte" => Arc::new(Lambertian {
+                albedo: self.color(params, "Kd", Vec3::new_with_values(0.5, 0.5, 0.5)),
+            }),
+            "metal" => {
+                // pbrt defaults to copper
+                let eta = self.color(params, "eta", Vec3::new_with_values(0.200, 0.924, 1.102));
+                let k = self.color(params, "k", Vec3::new_with_values(3.913, 2.453, 2.142));
+                let roughness = params.float("roughness", 0.01);
+                let roughness = 0.5
+                    * (params.float("uroughness", roughness)
+                        + params.float("vroughness", roughness));
+                Arc::new(Metal {
+                    albedo: conductor_reflectance(&eta, &k),
+                    fuzz: roughness.min(1.0),
+                })
+            }
+            "mirror" => Arc::new(Metal {
+                albedo: self.color(params, "Kr", Vec3::new_with_values(0.9, 0.9, 0.9)),
+                fuzz: 0.0,
+            }),
+            "glass" => Arc::new(Dielectric {
+                ir: params.float("index", params.float("eta", 1.5)),
+            }),
+            _ => {
+                self.warn(format!("material '{}' is not supported, using matte", kind));
+                Arc::new(Lambertian {
+                    albedo: self.color(params, "Kd", Vec3::new_with_values(0.5, 0.5, 0.5)),
+                })
+            }
+        }
+    }
+
+    // Material for the next shape, numbered in the scene in the order shapes use them
+    fn shape_material(&mut self) -> Arc<dyn Material> {
+        let material: Arc<dyn Material> = match self.state.area_light {
+            Some(radiance) => Arc::new(DiffuseLight::new(radiance)),
+            None => self.state.material.clone(),
+        };
+        self.scene.add_material(material)
+    }
+
+    fn add_shape(&mut self, shape: Arc<dyn Hittable>) {
+        match self.state.area_light {
+            Some(_) => self.scene.add_area_light(shape),
+            None => self.scene.world.add(Box::new(shape)),
+        }
+    }
+
+    fn mesh(
+        &mut self,
+        transform: &Matrix,
+        positions: &[Vec3],
+        indices: &[usize],
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+    ) -> Result<TriangleMesh, PbrtError> {
+        if let Some(index) = indices.iter().find(|&&index| index >= positions.len()) {
+            return Err(parse_error(
+                self.line,
+                format!("triangle refers to vertex {} of {}", index, positions.len()),
+            ));
+        }
+        let positions = positions
+            .iter()
+            .map(|p| transform_point(transform, p))
+            .collect();
+        let normals = normals.map(|normals| {
+            normals
+                .iter()
+                .map(|n| transform_normal(transform, n))
+                .collect()
+        });
+        // Mirroring reverses the winding, which is restored to keep area lights facing out
+        let indices = indices
+            .chunks_exact(3)
+            .map(|t| match determinant(transform) < 0.0 {
+                true => [t[0], t[2], t[1]],
+                false => [t[0], t[1], t[2]],
+            })
+            .collect();
+        Ok(TriangleMesh::new(
+            positions,
+            indices,
+            normals,
+            uvs,
+            self.shape_material(),
+        ))
+    }
+
+    fn shape(&mut self, kind: &str, params: &ParamSet) -> Result<(), PbrtError> {
+        let transform = multiply(&world_from_pbrt(), &self.state.transform);
+        match kind {
+            "sphere" => {
+                let lengths = axes(&transform).map(|axis| axis.length());
+                if (lengths[0] - lengths[1]).abs() > 1e-9 * lengths[0]
+                    || (lengths[0] - lengths[2]).abs() > 1e-9 * lengths[0]
+                {
+                    self.warn("non uniformly scaled sphere is imported as a sphere".to_string());
+                }
+                if ["zmin", "zmax", "phimax"]
+                    .iter()
+                    .any(|name| params.find(name).is_some())
+                {
+                    self.warn("partial spheres are imported as full spheres".to_string());
+                }
+                let radius = params.float("radius", 1.0) * determinant(&transform).abs().cbrt();
+                let sphere = Sphere::new(
+                    transform_point(&transform, &Vec3::new()),
+                    radius,
+                    self.shape_material(),
+                );
+                self.add_shape(Arc::new(sphere));
+            }
+            "disk" => {
+                if params.float("innerradius", 0.0) > 0.0 {
+                    self.warn("disk inner radius is not supported".to_string());
+                }
+                let center = Vec3::new_with_values(0.0, 0.0, params.float("height", 0.0));
+                let radius = params.float("radius", 1.0) * determinant(&transform).abs().sqrt();
+                let disk = Disk::new(
+                    transform_point(&transform, &center),
+                    transform_normal(&transform, &Vec3::new_with_values(0.0, 0.0, 1.0)),
+                    radius,
+                    self.shape_material(),
+                );
+                self.add_shape(Arc::new(disk));
+            }
+            "trianglemesh" => {
+                let points = params.numbers("P").unwrap_or_default();
+                let positions: Vec<Vec3> = points
+                    .chunks_exact(3)
+                    .map(|p| Vec3::new_with_values(p[0], p[1], p[2]))
+                    .collect();
+                let indices: Vec<usize> = match params.numbers("indices") {
+                    Some(indices) => {
+                        if let Some(index) = indices
+                            .iter()
+                            .find(|&&index| index < 0.0 || index.fract() != 0.0)
+                        {
+                            return Err(parse_error(
+                                self.line,
+                                format!("invalid vertex index {}", index),
+                            ));
+                        }
+                        indices.iter().map(|&index| index as usize).collect()
+                    }
+                    None if positions.len() == 3 => vec![0, 1, 2],
+                    None => {
+                        return Err(parse_error(
+                            self.line,
+                            "trianglemesh without indices".to_string(),
+                        ))
+                    }
+                };
+                let normals = params.numbers("N").map(|normals| {
+                    normals
+                        .chunks_exact(3)
+                        .map(|n| Vec3::new_with_values(n[0], n[1], n[2]))
+                        .collect::<Vec<Vec3>>()
+                });
+                let uvs = params
+                    .numbers("uv")
+                    .or_else(|| params.numbers("st"))
+                    .map(|uvs| {
+                        uvs.chunks_exact(2)
+                            .map(|uv| (uv[0], uv[1]))
+                            .collect::<Vec<(f64, f64)>>()
+                    });
+                let attributes_match = normals.as_ref().is_none_or(|n| n.len() == positions.len())
+                    && uvs.as_ref().is_none_or(|uvs| uvs.len() == positions.len());
+                if !attributes_match || !indices.len().is_multiple_of(3) {
+                    return Err(parse_error(
+                        self.line,
+                        "trianglemesh parameters have inconsistent lengths".to_string(),
+                    ));
+                }
+                let mesh = self.mesh(&transform, &positions, &indices, normals, uvs)?;
+                self.add_shape(Arc::new(mesh));
+            }
+            "plymesh" => {
+                let filename = params.string("filename").unwrap_or_default();
+                let path = self.directory.join(&filename);
+                let ply = File::open(&path).map_err(PbrtError::Io).and_then(|file| {
+                    read_ply(BufReader::new(file))
+                        .map_err(|error| parse_error(self.line, error.to_string()))
+                })?;
+                let indices: Vec<usize> = ply.indices.iter().flatten().copied().collect();
+                let mesh = self.mesh(&transform, &ply.positions, &indices, ply.normals, ply.uvs)?;
+                match ply.colors {
+                    Some(colors) if self.state.area_light.is_none() => {
+                        let base = self.shape_material();
+                        let material = self.scene.add_material(Arc::new(VertexColor { base }));
+                        let mesh = TriangleMesh::new(
+                            mesh.positions().to_vec(),
+                            mesh.indices().to_vec(),
+                            mesh.normals().map(<[Vec3]>::to_vec),
+                            mesh.uvs().map(<[(f64, f64)]>::to_vec),
+                            material,
+                        );
+                        self.add_shape(Arc::new(mesh.with_colors(colors)));
+                    }
+                    _ => self.add_shape(Arc::new(mesh)),
+                }
+            }
+            _ => self.warn(format!("shape '{}' is not supported", kind)),
+        }
+        Ok(())
+    }
+
+    fn light(&mut self, kind: &str, params: &ParamSet) {
+        let transform = multiply(&world_from_pbrt(), &self.state.transform);
+        let white = Vec3::new_with_values(1.0, 1.0, 1.0);
+        let scale = self.color(params, "scale", white);
+        let from = transform_point(&transform, &params.point("from", Vec3::new()));
+        let to = transform_point(
+            &transform,
+            &params.point("to", Vec3::new_with_values(0.0, 0.0, 1.0)),
+        );
+        match kind {
+            "point" => {
+                let intensity = self.color(params, "I", white) * scale;
+                self.scene
+                    .add_light(Arc::new(PointLight::new(from, intensity)));
+            }
+            "spot" => {
+                let intensity = self.color(params, "I", white) * scale;
+                let cone_angle = params.float("coneangle", 30.0);
+                let cone_delta = params.float("conedeltaangle", 5.0);
+                self.scene.add_light(Arc::new(SpotLight::new(
+                    from,
+                    to,
+                    intensity,
+                    cone_angle,
+                    cone_angle - cone_delta,
+                )));
+            }
+            "distant" => {
+                let radiance = self.color(params, "L", white) * scale;
+                self.scene
+                    .add_light(Arc::new(DirectionalLight::new(to - from, radiance)));
+            }
+            "infinite" => {
+                let radiance = self.color(params, "L", white) * scale;
+                match params.string("mapname") {
+                    Some(mapname) => {
+                        let up =
+                            transform_vector(&transform, &Vec3::new_with_values(0.0, 0.0, 1.0));
+                        if dot(&unit_vector(up), &Vec3::new_with_values(0.0, 1.0, 0.0)) < 0.999 {
+                            self.warn(
+                                "environment map orientation is ignored, +y is taken as up"
+                                    .to_string(),
+                            );
+                        }
+                        let path = self.directory.join(&mapname);
+                        let intensity = (radiance.x() + radiance.y() + radiance.z()) / 3.0;
+                        match EnvironmentMap::load(&path.to_string_lossy(), 0.0, intensity) {
+                            Ok(map) => self.scene.add_light(Arc::new(map)),
+                            Err(error) => self.warn(format!("{}, light skipped", error)),
+                        }
+                    }
+                    None => match EnvironmentMap::new(1, 1, vec![radiance], 0.0, 1.0) {
+                        Ok(map) => self.scene.add_light(Arc::new(map)),
+                        Err(error) => self.warn(format!("{}, light skipped", error)),
+                    },
+                }
+            }
+            _ => self.warn(format!("light '{}' is not supported", kind)),
+        }
+    }
+
+    fn area_light(&mut self, kind: &str, params: &ParamSet) {
+        if kind != "diffuse" {
+            self.warn(format!("area light '{}' is not supported", kind));
+            return;
+        }
+        if params.bool("twosided", false) {
+            self.warn("two sided area lights only emit from the front".to_string());
+        }
+        let white = Vec3::new_with_values(1.0, 1.0, 1.0);
+        let scale = self.color(params, "scale", white);
+        self.state.area_light = Some(self.color(params, "L", white) * scale);
+    }
+
+    fn directive(&mut self, name: &str) -> Result<(), PbrtError> {
+        match name {
+            "LookAt" => {
+                let eye = self.vector()?;
+                let look = self.vector()?;
+                let up = self.vector()?;
+                let direction = unit_vector(look - eye);
+                let right = unit_vector(cross(&unit_vector(up), &direction));
+                let new_up = cross(&direction, &right);
+                let mut camera_to_world = IDENTITY;
+                for (column, axis) in [right, new_up, direction, eye].iter().enumerate() {
+                    camera_to_world[column] = [axis.x(), axis.y(), axis.z(), 0.0];
+                }
+                camera_to_world[3][3] = 1.0;
+                match inverse(&camera_to_world) {
+                    Some(look_at) => {
+                        self.state.transform = multiply(&self.state.transform, &look_at)
+                    }
+                    None => self.warn("degenerate LookAt ignored".to_string()),
+                }
+            }
+            "Translate" => {
+                let offset = self.vector()?;
+                self.state.transform = multiply(&self.state.transform, &translation(&offset));
+            }
+            "Scale" => {
+                let scale = self.vector()?;
+                self.state.transform = multiply(&self.state.transform, &scaling(&scale));
+            }
+            "Rotate" => {
+                let angle = self.number()?;
+                let axis = self.vector()?;
+                self.state.transform = multiply(&self.state.transform, &rotation(angle, &axis));
+            }
+            "Transform" => self.state.transform = self.matrix()?,
+            "ConcatTransform" => {
+                let m = self.matrix()?;
+                self.state.transform = multiply(&self.state.transform, &m);
+            }
+            "Identity" => self.state.transform = IDENTITY,
+            "Camera" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                self.camera = Some(CameraSettings {
+                    kind,
+                    params,
+                    camera_from_world: self.state.transform,
+                });
+            }
+            "Film" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                if kind != "image" {
+                    self.warn(format!("film '{}' is treated as an image film", kind));
+                }
+                self.width = params.float("xresolution", 1280.0) as u32;
+                self.height = params.float("yresolution", 720.0) as u32;
+                self.output = params.string("filename");
+            }
+            "Sampler" => {
+                self.string()?;
+                let params = self.params()?;
+                self.samples_per_pixel = params.float("pixelsamples", 16.0) as u32;
+            }
+            "Integrator" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                if kind != "path" {
+                    self.warn(format!("integrator '{}' is rendered by path tracing", kind));
+                }
+                self.max_depth = params.float("maxdepth", 5.0) as i32;
+            }
+            "WorldBegin" => self.state.transform = IDENTITY,
+            "WorldEnd" => {}
+            "AttributeBegin" => self.attribute_stack.push(self.state.clone()),
+            "AttributeEnd" => match self.attribute_stack.pop() {
+                Some(state) => self.state = state,
+                None => self.warn("AttributeEnd without AttributeBegin".to_string()),
+            },
+            "TransformBegin" => self.transform_stack.push(self.state.transform),
+            "TransformEnd" => match self.transform_stack.pop() {
+                Some(transform) => self.state.transform = transform,
+                None => self.warn("TransformEnd without TransformBegin".to_string()),
+            },
+            "Material" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                self.state.material = self.material(&kind, &params);
+            }
+            "MakeNamedMaterial" => {
+                let name = self.string()?;
+                let params = self.params()?;
+                let kind = params.string("type").unwrap_or_default();
+                let material = self.material(&kind, &params);
+                self.named_materials.insert(name, material);
+            }
+            "NamedMaterial" => {
+                let name = self.string()?;
+                match self.named_materials.get(&name) {
+                    Some(material) => self.state.material = material.clone(),
+                    None => self.warn(format!("material '{}' is not defined", name)),
+                }
+            }
+            "Shape" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                self.shape(&kind, &params)?;
+            }
+            "LightSource" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                self.light(&kind, &params);
+            }
+            "AreaLightSource" => {
+                let kind = self.string()?;
+                let params = self.params()?;
+                self.area_light(&kind, &params);
+            }
+            _ => {
+                self.warn(format!("directive {} is not supported", name));
+                self.skip_arguments();
+            }
+        }
+        Ok(())
+    }
+
+    fn build_camera(&mut self) -> Camera {
+        let settings = self.camera.take().unwrap_or(CameraSettings {
+            kind: "perspective".to_string(),
+            params: ParamSet::default(),
+            camera_from_world: IDENTITY,
+        });
+        if settings.kind != "perspective" {
+            self.warn(format!(
+                "camera '{}' is imported as a perspective camera",
+                settings.kind
+            ));
+        }
+        let aspect_ratio = self.width as f64 / self.height as f64;
+        // The field of view spans the shorter image axis
+        let fov = settings.params.float("fov", 90.0).to_radians();
+        let vfov = match aspect_ratio >= 1.0 {
+            true => fov,
+            false => 2.0 * ((fov / 2.0).tan() / aspect_ratio).atan(),
+        };
+        let world_from_camera = multiply(
+            &world_from_pbrt(),
+            &inverse(&settings.camera_from_world).unwrap_or(IDENTITY),
+        );
+        let origin = transform_point(&world_from_camera, &Vec3::new());
+        // A pinhole camera ignores the focus distance, which would only scale the rays
+        let lens_radius = settings.params.float("lensradius", 0.0);
+        let focus_distance = match lens_radius > 0.0 {
+            true => settings.params.float("focaldistance", 1e6),
+            false => 1.0,
+        };
+        Camera::new(
+            origin,
+            transform_point(&world_from_camera, &Vec3::new_with_values(0.0, 0.0, 1.0)),
+            transform_vector(&world_from_camera, &Vec3::new_with_values(0.0, 1.0, 0.0)),
+            vfov.to_degrees(),
+            aspect_ratio,
+            2.0 * lens_radius,
+            focus_distance,
+        )
+    }
+}
+
+// Parses a pbrt-v3 scene, with file names resolved relative to directory
+pub fn parse_pbrt(text: &str, directory: &Path) -> Result<PbrtScene, PbrtError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+        directory: directory.to_path_buf(),
+        state: GraphicsState {
+            transform: IDENTITY,
+            material: default_material(),
+            area_light: None,
+        },
+        attribute_stack: vec![],
+        transform_stack: vec![],
+        named_materials: HashMap::new(),
+        camera: None,
+        scene: Scene::new(HittableList::new()),
+        width: 1280,
+        height: 720,
+        samples_per_pixel: 16,
+        max_depth: 5,
+        output: None,
+        warnings: vec![],
+        line: 1,
+    };
+
+    while parser.position < parser.tokens.len() {
+        match parser.next()? {
+            Token::Directive(name) => parser.directive(&name)?,
+            token => {
+                return Err(parse_error(
+                    parser.line,
+                    format!("expected a directive, found {:?}", token),
+                ))
+            }
+        }
+    }
+
+    let camera = parser.build_camera();
+    Ok(PbrtScene {
+        scene: parser.scene,
+        camera,
+        width: parser.width,
+        height: parser.height,
+        samples_per_pixel: parser.samples_per_pixel,
+        max_depth: parser.max_depth,
+        output: parser.output,
+        warnings: parser.warnings,
+    })
+}
+
+pub fn load_pbrt(path: &str) -> Result<PbrtScene, PbrtError> {
+    let text = std::fs::read_to_string(path)?;
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+    parse_pbrt(&text, directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::camera_model::CameraModel;
+    use crate::rays::ray::Ray;
+
+    const SCENE: &str = r#"
+# A red sphere at +x, on the left of the image as pbrt is left handed, above a glass floor
+LookAt 0 0 5  0 0 0  0 1 0
+Camera "perspective" "float fov" [ 45 ]
+Film "image" "integer xresolution" [ 200 ] "integer yresolution" [ 100 ]
+    "string filename" "spheres.png"
+Sampler "halton" "integer pixelsamples" 4
+Integrator "path" "integer maxdepth" [ 8 ]
+
+WorldBegin
+LightSource "point" "point from" [ 0 4 0 ] "rgb I" [ 10 10 10 ]
+
+AttributeBegin
+  Material "matte" "rgb Kd" [ .8 .1 .1 ]
+  Translate 1 0 0
+  Shape "sphere" "float radius" 0.5
+AttributeEnd
+
+AttributeBegin
+  Material "glass"
+  Shape "trianglemesh" "integer indices" [ 0 1 2 0 2 3 ]
+      "point P" [ -5 -1 -5  5 -1 -5  5 -1 5  -5 -1 5 ]
+AttributeEnd
+
+AttributeBegin
+  AreaLightSource "diffuse" "rgb L" [ 4 4 4 ]
+  Translate 0 3 0
+  Shape "sphere" "float radius" 0.2
+AttributeEnd
+WorldEnd
+"#;
+
+    #[test]
+    fn test_parse_scene() {
+        let pbrt = parse_pbrt(SCENE, Path::new(".")).unwrap();
+        assert!(pbrt.warnings.is_empty(), "{:?}", pbrt.warnings);
+        assert_eq!((pbrt.width, pbrt.height), (200, 100));
+        assert_eq!(pbrt.samples_per_pixel, 4);
+        assert_eq!(pbrt.max_depth, 8);
+        assert_eq!(pbrt.output.as_deref(), Some("spheres.png"));
+        assert_eq!(pbrt.scene.lights.len(), 2);
+
+        // pbrt is left handed, so +x is on the left of the image
+        let world = &pbrt.scene.world;
+        let left = pbrt.camera.get_ray(0.379, 0.5).unwrap();
+        let rec = world.hit(&left, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t * left.direction().length() - 4.6).abs() < 0.1);
+        let right = pbrt.camera.get_ray(0.621, 0.5).unwrap();
+        assert!(world.hit(&right, 0.001, f64::INFINITY).is_none());
+
+        // The floor is hit from above
+        let down = pbrt.camera.get_ray(0.5, 0.0).unwrap();
+        let floor = world.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert!((floor.p.y() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_light_directions() {
+        let text = r#"
+WorldBegin
+LightSource "distant" "point from" [ 0 0 0 ] "point to" [ 1 -1 0 ] "rgb L" [ 2 2 2 ]
+LightSource "spot" "point from" [ 0 4 0 ] "point to" [ 2 0 0 ] "float coneangle" [ 30 ]
+WorldEnd
+"#;
+        let pbrt = parse_pbrt(text, Path::new(".")).unwrap();
+        assert!(pbrt.warnings.is_empty(), "{:?}", pbrt.warnings);
+        assert_eq!(pbrt.scene.lights.len(), 2);
+
+        // Light travels from "from" to "to", with x mirrored into this crate's frame
+        let distant = &pbrt.scene.lights[0];
+        let sample = distant.sample(&Vec3::new()).unwrap();
+        let towards_light = unit_vector(Vec3::new_with_values(1.0, 1.0, 0.0));
+        assert!((unit_vector(sample.direction) - towards_light).length() < 1e-9);
+        assert_eq!(sample.radiance, Vec3::new_with_values(2.0, 2.0, 2.0));
+
+        // The spot shines down its axis towards -x and leaves the mirrored side dark
+        let spot = &pbrt.scene.lights[1];
+        let on_axis = spot.sample(&Vec3::new_with_values(-1.0, 2.0, 0.0)).unwrap();
+        assert!(on_axis.radiance.x() > 0.0);
+        let towards_spot = unit_vector(Vec3::new_with_values(1.0, 2.0, 0.0));
+        assert!((unit_vector(on_axis.direction) - towards_spot).length() < 1e-9);
+        let mirrored = spot.sample(&Vec3::new_with_values(1.0, 2.0, 0.0));
+        assert!(mirrored.is_none_or(|sample| sample.radiance.near_zero()));
+    }
+
+    #[test]
+    fn test_unsupported_directives() {
+        let text = r#"
+Camera "orthographic"
+WorldBegin
+Texture "checks" "spectrum" "checkerboard" "float uscale" [ 4 ]
+MakeNamedMaterial "red" "string type" "matte" "rgb Kd" [ 1 0 0 ]
+AttributeBegin
+  NamedMaterial "red"
+  Transform [ 1 0 0 0  0 1 0 0  0 0 1 0  0 2 0 1 ]
+  Shape "sphere"
+AttributeEnd
+Material "plastic" "spectrum Kd" "kd.spd"
+Shape "curve" "point P" [ 0 0 0 1 1 1 2 2 2 3 3 3 ]
+AttributeEnd
+"#;
+        let pbrt = parse_pbrt(text, Path::new(".")).unwrap();
+        assert_eq!(pbrt.warnings.len(), 6, "{:?}", pbrt.warnings);
+        assert!(pbrt.warnings[0].starts_with("line 4: directive Texture"));
+
+        // The named material is used and the sphere moved up by the transform
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.0, 2.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = pbrt.scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        let (_, attenuation) = rec.mat_ptr.scatter(&ray, &rec).unwrap();
+        assert_eq!(attenuation, Vec3::new_with_values(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let unterminated = "WorldBegin\nShape \"sphere";
+        assert!(matches!(
+            parse_pbrt(unterminated, Path::new(".")),
+            Err(PbrtError::Parse { line: 2, .. })
+        ));
+        let bad_declaration = "WorldBegin\n\nShape \"sphere\" \"radius\" 2";
+        assert!(matches!(
+            parse_pbrt(bad_declaration, Path::new(".")),
+            Err(PbrtError::Parse { line: 3, .. })
+        ));
+        let bad_index = "Shape \"trianglemesh\" \"integer indices\" [ 0 1 5 ] \"point P\" [ 0 0 0 1 0 0 0 1 0 ]";
+        assert!(matches!(
+            parse_pbrt(bad_index, Path::new(".")),
+            Err(PbrtError::Parse { .. })
+        ));
+        for indices in ["0 1 -2", "0 1 1.5"] {
+            let malformed = format!(
+                "Shape \"trianglemesh\" \"integer indices\" [ {} ] \"point P\" [ 0 0 0 1 0 0 0 1 0 ]",
+                indices
+            );
+            assert!(matches!(
+                parse_pbrt(&malformed, Path::new(".")),
+                Err(PbrtError::Parse { .. })
+            ));
+        }
+        assert!(matches!(
+            load_pbrt("does_not_exist.pbrt"),
+            Err(PbrtError::Io(_))
+        ));
+    }
+}
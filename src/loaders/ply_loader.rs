@@ -0,0 +1,643 @@
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use crate::hittables::triangle_mesh::TriangleMesh;
+use crate::materials::material::Material;
+use crate::vectors::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(std::io::Error),
+    InvalidHeader(String),
+    InvalidData(String),
+    MissingProperty(String),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(error) => write!(f, "could not read PLY file: {}", error),
+            PlyError::InvalidHeader(message) => write!(f, "invalid PLY header: {}", message),
+            PlyError::InvalidData(message) => write!(f, "invalid PLY data: {}", message),
+            PlyError::MissingProperty(name) => {
+                write!(f, "PLY file has no vertex property '{}'", name)
+            }
+        }
+    }
+}
+
+impl Error for PlyError {}
+
+impl From<std::io::Error> for PlyError {
+    fn from(error: std::io::Error) -> Self {
+        PlyError::Io(error)
+    }
+}
+
+// Mesh data read from a PLY file, before it is turned into a hittable
+pub struct PlyMesh {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<[usize; 3]>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f64, f64)>>,
+    pub colors: Option<Vec<Vec3>>,
+}
+
+impl PlyMesh {
+    pub fn into_mesh(self, mat_ptr: Arc<dyn Material>) -> TriangleMesh {
+        let mesh = TriangleMesh::new(
+            self.positions,
+            self.indices,
+            self.normals,
+            self.uvs,
+            mat_ptr,
+        );
+        match self.colors {
+            Some(colors) => mesh.with_colors(colors),
+            None => mesh,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, PlyError> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::Int8),
+            "uchar" | "uint8" => Ok(ScalarType::UInt8),
+            "short" | "int16" => Ok(ScalarType::Int16),
+            "ushort" | "uint16" => Ok(ScalarType::UInt16),
+            "int" | "int32" => Ok(ScalarType::Int32),
+            "uint" | "uint32" => Ok(ScalarType::UInt32),
+            "float" | "float32" => Ok(ScalarType::Float32),
+            "double" | "float64" => Ok(ScalarType::Float64),
+            _ => Err(PlyError::InvalidHeader(format!(
+                "unknown property type '{}'",
+                name
+            ))),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    // Scale that maps integer colors to [0, 1], floating point colors are kept as is
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::UInt8 => 1.0 / 255.0,
+            ScalarType::UInt16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(ScalarType, String),
+    // Count type, item type and name
+    List(ScalarType, ScalarType, String),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(_, name) | Property::List(_, _, name) => name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, names: &[&str]) -> Option<(usize, ScalarType)> {
+        self.properties
+            .iter()
+            .enumerate()
+            .find_map(|(index, property)| match property {
+                Property::Scalar(scalar_type, name) if names.contains(&name.as_str()) => {
+                    Some((index, *scalar_type))
+                }
+                _ => None,
+            })
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> Result<(Format, Vec<Element>), PlyError> {
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> Result<(), PlyError> {
+        line.clear();
+        match reader.read_line(line)? {
+            0 => Err(PlyError::InvalidHeader("missing end_header".to_string())),
+            _ => Ok(()),
+        }
+    };
+
+    next_line(&mut line)?;
+    if line.trim() != "ply" {
+        return Err(PlyError::InvalidHeader("missing 'ply' magic".to_string()));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    loop {
+        next_line(&mut line)?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["end_header"] => break,
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => {
+                        return Err(PlyError::InvalidHeader(format!(
+                            "unknown format '{}'",
+                            name
+                        )))
+                    }
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| {
+                    PlyError::InvalidHeader(format!("invalid count for element '{}'", name))
+                })?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, item_type, name] => {
+                let property = Property::List(
+                    ScalarType::parse(count_type)?,
+                    ScalarType::parse(item_type)?,
+                    name.to_string(),
+                );
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => {
+                        return Err(PlyError::InvalidHeader(
+                            "property before any element".to_string(),
+                        ))
+                    }
+                }
+            }
+            ["property", scalar_type, name] => {
+                let property = Property::Scalar(ScalarType::parse(scalar_type)?, name.to_string());
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => {
+                        return Err(PlyError::InvalidHeader(
+                            "property before any element".to_string(),
+                        ))
+                    }
+                }
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => {
+                return Err(PlyError::InvalidHeader(format!(
+                    "unexpected line '{}'",
+                    line.trim()
+                )))
+            }
+        }
+    }
+
+    match format {
+        Some(format) => Ok((format, elements)),
+        None => Err(PlyError::InvalidHeader("missing format line".to_string())),
+    }
+}
+
+// Reads the body one value at a time, whitespace separated for ASCII files
+struct ValueReader<R: BufRead> {
+    reader: R,
+    format: Format,
+    tokens: Vec<String>,
+}
+
+impl<R: BufRead> ValueReader<R> {
+    fn scalar(&mut self, scalar_type: ScalarType) -> Result<f64, PlyError> {
+        match self.format {
+            Format::Ascii => self.ascii_scalar(),
+            _ => self.binary_scalar(scalar_type),
+        }
+    }
+
+    fn ascii_scalar(&mut self) -> Result<f64, PlyError> {
+        while self.tokens.is_empty() {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(PlyError::InvalidData("file ends early".to_string()));
+            }
+            self.tokens = line.split_whitespace().rev().map(str::to_string).collect();
+        }
+        let token = self.tokens.pop().unwrap();
+        token
+            .parse()
+            .map_err(|_| PlyError::InvalidData(format!("invalid number '{}'", token)))
+    }
+
+    fn binary_scalar(&mut self, scalar_type: ScalarType) -> Result<f64, PlyError> {
+        let mut bytes = [0u8; 8];
+        let bytes = &mut bytes[..scalar_type.size()];
+        self.reader.read_exact(bytes)?;
+        if self.format == Format::BinaryBigEndian {
+            bytes.reverse();
+        }
+        let value = match scalar_type {
+            ScalarType::Int8 => bytes[0] as i8 as f64,
+            ScalarType::UInt8 => bytes[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::Float32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        };
+        Ok(value)
+    }
+
+    // Values of all scalar properties of one element, and the items of its lists
+    fn element(&mut self, element: &Element) -> Result<(Vec<f64>, Vec<Vec<f64>>), PlyError> {
+        let mut scalars = vec![0.0; element.properties.len()];
+        let mut lists = vec![];
+        for (index, property) in element.properties.iter().enumerate() {
+            match property {
+                Property::Scalar(scalar_type, _) => scalars[index] = self.scalar(*scalar_type)?,
+                Property::List(count_type, item_type, _) => {
+                    let count = to_index(self.scalar(*count_type)?)?;
+                    let items = (0..count)
+                        .map(|_| self.scalar(*item_type))
+                        .collect::<Result<Vec<f64>, PlyError>>()?;
+                    lists.push(items);
+                }
+            }
+        }
+        Ok((scalars, lists))
+    }
+}
+
+// Whether a vertex attribute is there, reporting the first missing component of a
+// partial set
+fn all_or_none<T>(names: &[&str], properties: &[Option<T>]) -> Result<bool, PlyError> {
+    match properties
+        .iter()
+        .filter(|property| property.is_some())
+        .count()
+    {
+        0 => Ok(false),
+        count if count == properties.len() => Ok(true),
+        _ => {
+            let missing = names
+                .iter()
+                .zip(properties)
+                .find(|(_, property)| property.is_none())
+                .map(|(name, _)| name.to_string())
+                .unwrap();
+            Err(PlyError::MissingProperty(missing))
+        }
+    }
+}
+
+fn to_index(value: f64) -> Result<usize, PlyError> {
+    match value >= 0.0 && value.fract() == 0.0 {
+        true => Ok(value as usize),
+        false => Err(PlyError::InvalidData(format!("invalid index {}", value))),
+    }
+}
+
+// Reads ASCII and binary PLY files. Vertex normals, colors and texture coordinates are
+// read when present, polygons are split into triangle fans and unknown elements are
+// skipped.
+pub fn read_ply<R: BufRead>(mut reader: R) -> Result<PlyMesh, PlyError> {
+    let (format, elements) = read_header(&mut reader)?;
+    let mut values = ValueReader {
+        reader,
+        format,
+        tokens: vec![],
+    };
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut colors = vec![];
+    let mut indices = vec![];
+    let mut has_vertices = false;
+    let mut layout = None;
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                has_vertices = true;
+                let position = [
+                    element.scalar(&["x"]),
+                    element.scalar(&["y"]),
+                    element.scalar(&["z"]),
+                ];
+                let normal = [
+                    element.scalar(&["nx"]),
+                    element.scalar(&["ny"]),
+                    element.scalar(&["nz"]),
+                ];
+                let uv = [
+                    element.scalar(&["u", "s", "texture_u", "texture_s"]),
+                    element.scalar(&["v", "t", "texture_v", "texture_t"]),
+                ];
+                let color = [
+                    element.scalar(&["red", "r", "diffuse_red"]),
+                    element.scalar(&["green", "g", "diffuse_green"]),
+                    element.scalar(&["blue", "b", "diffuse_blue"]),
+                ];
+                let position = match position {
+                    [Some(x), Some(y), Some(z)] => [x.0, y.0, z.0],
+                    _ => {
+                        all_or_none(&["x", "y", "z"], &position)?;
+                        return Err(PlyError::MissingProperty("x".to_string()));
+                    }
+                };
+                let has_normals = all_or_none(&["nx", "ny", "nz"], &normal)?;
+                let has_uvs = all_or_none(&["u", "v"], &uv)?;
+                let has_colors = all_or_none(&["red", "green", "blue"], &color)?;
+                for _ in 0..element.count {
+                    let (scalars, _) = values.element(element)?;
+                    positions.push(Vec3::new_with_values(
+                        scalars[position[0]],
+                        scalars[position[1]],
+                        scalars[position[2]],
+                    ));
+                    if let [Some(x), Some(y), Some(z)] = normal {
+                        normals.push(Vec3::new_with_values(
+                            scalars[x.0],
+                            scalars[y.0],
+                            scalars[z.0],
+                        ));
+                    }
+                    if let [Some(u), Some(v)] = uv {
+                        uvs.push((scalars[u.0], scalars[v.0]));
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color {
+                        colors.push(Vec3::new_with_values(
+                            scalars[r.0] * r.1.color_scale(),
+                            scalars[g.0] * g.1.color_scale(),
+                            scalars[b.0] * b.1.color_scale(),
+                        ));
+                    }
+                }
+                layout = Some((has_normals, has_uvs, has_colors));
+            }
+            "face" => {
+                let list = element
+                    .properties
+                    .iter()
+                    .filter(|property| matches!(property, Property::List(..)))
+                    .position(|property| {
+                        property.name() == "vertex_indices" || property.name() == "vertex_index"
+                    })
+                    .ok_or_else(|| {
+                        PlyError::InvalidHeader("face element has no vertex_indices".to_string())
+                    })?;
+                for _ in 0..element.count {
+                    let (_, lists) = values.element(element)?;
+                    let face = lists[list]
+                        .iter()
+                        .map(|&value| to_index(value))
+                        .collect::<Result<Vec<usize>, PlyError>>()?;
+                    for i in 1..face.len().saturating_sub(1) {
+                        indices.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    values.element(element)?;
+                }
+            }
+        }
+    }
+
+    if !has_vertices {
+        return Err(PlyError::InvalidHeader("no vertex element".to_string()));
+    }
+    if let Some(index) = indices
+        .iter()
+        .flatten()
+        .find(|&&index| index >= positions.len())
+    {
+        return Err(PlyError::InvalidData(format!(
+            "face refers to vertex {} of {}",
+            index,
+            positions.len()
+        )));
+    }
+
+    let (has_normals, has_uvs, has_colors) = layout.unwrap_or((false, false, false));
+    Ok(PlyMesh {
+        positions,
+        indices,
+        normals: match has_normals {
+            true => Some(normals),
+            false => None,
+        },
+        uvs: match has_uvs {
+            true => Some(uvs),
+            false => None,
+        },
+        colors: match has_colors {
+            true => Some(colors),
+            false => None,
+        },
+    })
+}
+
+pub fn load_ply(path: &str, mat_ptr: Arc<dyn Material>) -> Result<TriangleMesh, PlyError> {
+    let file = File::open(path)?;
+    Ok(read_ply(BufReader::new(file))?.into_mesh(mat_ptr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::hittable::{HitRecord, Hittable};
+    use crate::rays::ray::Ray;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    const ASCII_QUAD: &str = "ply
+format ascii 1.0
+comment unit square in the xy plane
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 255 0 0
+1 0 0 0 0 1 255 0 0
+1 1 0 0 0 1 0 0 255
+0 1 0 0 0 1 0 0 255
+4 0 1 2 3
+";
+
+    fn binary_quad(big_endian: bool) -> Vec<u8> {
+        let format = match big_endian {
+            true => "binary_big_endian",
+            false => "binary_little_endian",
+        };
+        let mut bytes = format!(
+            "ply\nformat {} 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+             property float z\nproperty ushort red\nproperty ushort green\nproperty ushort blue\n\
+             element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+             element face 2\nproperty list uchar uint vertex_indices\nend_header\n",
+            format
+        )
+        .into_bytes();
+        let corners = [(0.0f32, 0.0f32), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for (x, y) in corners {
+            for value in [x, y, 0.0] {
+                bytes.extend(match big_endian {
+                    true => value.to_be_bytes(),
+                    false => value.to_le_bytes(),
+                });
+            }
+            for value in [65535u16, 0, 0] {
+                bytes.extend(match big_endian {
+                    true => value.to_be_bytes(),
+                    false => value.to_le_bytes(),
+                });
+            }
+        }
+        for value in [0i32, 1] {
+            bytes.extend(match big_endian {
+                true => value.to_be_bytes(),
+                false => value.to_le_bytes(),
+            });
+        }
+        for face in [[0u32, 1, 2], [0, 2, 3]] {
+            bytes.push(3);
+            for index in face {
+                bytes.extend(match big_endian {
+                    true => index.to_be_bytes(),
+                    false => index.to_le_bytes(),
+                });
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_read_ascii_ply() {
+        let ply = read_ply(ASCII_QUAD.as_bytes()).unwrap();
+        assert_eq!(ply.positions.len(), 4);
+        assert_eq!(ply.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(
+            ply.normals.as_ref().unwrap()[2],
+            Vec3::new_with_values(0.0, 0.0, 1.0)
+        );
+        assert!(ply.uvs.is_none());
+        assert_eq!(
+            ply.colors.as_ref().unwrap()[0],
+            Vec3::new_with_values(1.0, 0.0, 0.0)
+        );
+
+        // Colors are interpolated into the hit record
+        let mesh = ply.into_mesh(Arc::new(TestMaterial));
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.5, 0.5, 1.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let color = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap().color.unwrap();
+        assert!((color - Vec3::new_with_values(0.5, 0.0, 0.5)).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_read_binary_ply() {
+        for big_endian in [false, true] {
+            let ply = read_ply(binary_quad(big_endian).as_slice()).unwrap();
+            assert_eq!(ply.positions[2], Vec3::new_with_values(1.0, 1.0, 0.0));
+            assert_eq!(ply.indices, vec![[0, 1, 2], [0, 2, 3]]);
+            assert!(ply.normals.is_none());
+            assert_eq!(ply.colors.unwrap()[3], Vec3::new_with_values(1.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_ply_errors() {
+        assert!(matches!(
+            read_ply("solid cube\n".as_bytes()),
+            Err(PlyError::InvalidHeader(_))
+        ));
+        let no_z = ASCII_QUAD.replace("property float z\n", "");
+        assert!(matches!(
+            read_ply(no_z.as_bytes()),
+            Err(PlyError::MissingProperty(name)) if name == "z"
+        ));
+        let no_nz = ASCII_QUAD.replace("property float nz\n", "");
+        assert!(matches!(
+            read_ply(no_nz.as_bytes()),
+            Err(PlyError::MissingProperty(name)) if name == "nz"
+        ));
+        let no_blue = ASCII_QUAD.replace("property uchar blue\n", "");
+        assert!(matches!(
+            read_ply(no_blue.as_bytes()),
+            Err(PlyError::MissingProperty(name)) if name == "blue"
+        ));
+        let bad_index = ASCII_QUAD.replace("4 0 1 2 3", "3 0 1 7");
+        assert!(matches!(
+            read_ply(bad_index.as_bytes()),
+            Err(PlyError::InvalidData(_))
+        ));
+        let truncated = binary_quad(false);
+        assert!(matches!(
+            read_ply(&truncated[..truncated.len() - 2]),
+            Err(PlyError::Io(_))
+        ));
+        assert!(matches!(
+            load_ply("does_not_exist.ply", Arc::new(TestMaterial)),
+            Err(PlyError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::hittables::triangle_mesh::TriangleMesh;
+use crate::materials::material::Material;
+use crate::utils::vec3_utils::{cross, dot};
+use crate::vectors::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(std::io::Error),
+    InvalidData(String),
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(error) => write!(f, "could not read STL file: {}", error),
+            StlError::InvalidData(message) => write!(f, "invalid STL data: {}", message),
+        }
+    }
+}
+
+impl Error for StlError {}
+
+impl From<std::io::Error> for StlError {
+    fn from(error: std::io::Error) -> Self {
+        StlError::Io(error)
+    }
+}
+
+const BINARY_HEADER_SIZE: usize = 84;
+const BINARY_FACET_SIZE: usize = 50;
+
+// Mesh data read from an STL file, with identical corners welded into shared vertices
+pub struct StlMesh {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl StlMesh {
+    // STL parts are faceted, so the mesh is left without smooth normals
+    pub fn into_mesh(self, mat_ptr: Arc<dyn Material>) -> TriangleMesh {
+        TriangleMesh::new(self.positions, self.indices, None, None, mat_ptr)
+    }
+
+    fn add_facet(
+        &mut self,
+        welded: &mut HashMap<[u64; 3], usize>,
+        normal: Vec3,
+        corners: [Vec3; 3],
+    ) {
+        let mut triangle = [0; 3];
+        for (index, corner) in triangle.iter_mut().zip(corners.iter()) {
+            let key = [
+                corner.x().to_bits(),
+                corner.y().to_bits(),
+                corner.z().to_bits(),
+            ];
+            let positions = &mut self.positions;
+            *index = *welded.entry(key).or_insert_with(|| {
+                positions.push(*corner);
+                positions.len() - 1
+            });
+        }
+        // Trust the stored normal over the winding when the two disagree
+        let winding = cross(&(corners[1] - corners[0]), &(corners[2] - corners[0]));
+        if dot(&winding, &normal) < 0.0 {
+            triangle.swap(1, 2);
+        }
+        self.indices.push(triangle);
+    }
+}
+
+fn read_binary(bytes: &[u8]) -> StlMesh {
+    let value =
+        |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as f64;
+    let vector =
+        |offset: usize| Vec3::new_with_values(value(offset), value(offset + 4), value(offset + 8));
+
+    let mut mesh = StlMesh {
+        positions: vec![],
+        indices: vec![],
+    };
+    let mut welded = HashMap::new();
+    for facet in 0..(bytes.len() - BINARY_HEADER_SIZE) / BINARY_FACET_SIZE {
+        let offset = BINARY_HEADER_SIZE + facet * BINARY_FACET_SIZE;
+        mesh.add_facet(
+            &mut welded,
+            vector(offset),
+            [
+                vector(offset + 12),
+                vector(offset + 24),
+                vector(offset + 36),
+            ],
+        );
+    }
+    mesh
+}
+
+fn read_ascii(text: &str) -> Result<StlMesh, StlError> {
+    let mut mesh = StlMesh {
+        positions: vec![],
+        indices: vec![],
+    };
+    let mut welded = HashMap::new();
+    let mut normal = Vec3::new();
+    let mut corners = vec![];
+    for (line_number, line) in text.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let numbers = |values: &[&str]| -> Result<Vec3, StlError> {
+            let mut vector = Vec3::new();
+            for (axis, value) in values.iter().enumerate() {
+                vector[axis] = value.parse().map_err(|_| {
+                    StlError::InvalidData(format!(
+                        "invalid number '{}' on line {}",
+                        value,
+                        line_number + 1
+                    ))
+                })?;
+            }
+            Ok(vector)
+        };
+        match tokens.as_slice() {
+            ["facet", "normal", x, y, z] => {
+                normal = numbers(&[x, y, z])?;
+                corners.clear();
+            }
+            ["vertex", x, y, z] => corners.push(numbers(&[x, y, z])?),
+            ["endfacet"] => match corners.as_slice() {
+                [a, b, c] => mesh.add_facet(&mut welded, normal, [*a, *b, *c]),
+                _ => {
+                    return Err(StlError::InvalidData(format!(
+                        "facet ending on line {} has {} vertices",
+                        line_number + 1,
+                        corners.len()
+                    )))
+                }
+            },
+            ["solid", ..] | ["outer", "loop"] | ["endloop"] | ["endsolid", ..] | [] => {}
+            _ => {
+                return Err(StlError::InvalidData(format!(
+                    "unexpected line {}: '{}'",
+                    line_number + 1,
+                    line.trim()
+                )))
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+// Reads ASCII and binary STL files. Binary files are recognised by their facet count
+// matching the file size, since some exporters also start binary headers with "solid".
+pub fn read_stl<R: Read>(mut reader: R) -> Result<StlMesh, StlError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.len() >= BINARY_HEADER_SIZE {
+        let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+        if bytes.len() == BINARY_HEADER_SIZE + count * BINARY_FACET_SIZE {
+            return Ok(read_binary(&bytes));
+        }
+    }
+    match bytes.starts_with(b"solid") {
+        true => {
+            let text = std::str::from_utf8(&bytes)
+                .map_err(|_| StlError::InvalidData("ASCII STL is not valid UTF-8".to_string()))?;
+            read_ascii(text)
+        }
+        false => Err(StlError::InvalidData(
+            "file is neither ASCII STL nor binary STL of the expected size".to_string(),
+        )),
+    }
+}
+
+pub fn load_stl(path: &str, mat_ptr: Arc<dyn Material>) -> Result<TriangleMesh, StlError> {
+    Ok(read_stl(File::open(path)?)?.into_mesh(mat_ptr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::hittable::{HitRecord, Hittable};
+    use crate::rays::ray::Ray;
+
+    struct TestMaterial;
+
+    impl Material for TestMaterial {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+    }
+
+    // Unit square in the xy plane facing +z, the second facet wound the wrong way
+    const ASCII_SQUARE: &str = "solid square
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 1 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    fn binary_square() -> Vec<u8> {
+        // Binary header that starts like an ASCII file
+        let mut bytes = b"solid exported by a CAD tool".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend(2u32.to_le_bytes());
+        let facets = [
+            [
+                [0.0f32, 0.0, 1.0],
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+            ],
+            [
+                [0.0, 0.0, 1.0],
+                [0.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+        ];
+        for facet in facets {
+            for vector in facet {
+                for value in vector {
+                    bytes.extend(value.to_le_bytes());
+                }
+            }
+            bytes.extend(0u16.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn assert_square(stl: StlMesh) {
+        assert_eq!(stl.positions.len(), 4);
+        assert_eq!(stl.indices.len(), 2);
+        for triangle in &stl.indices {
+            let [a, b, c] = triangle.map(|index| stl.positions[index]);
+            assert!(cross(&(b - a), &(c - a)).z() > 0.0);
+        }
+
+        let mesh = stl.into_mesh(Arc::new(TestMaterial));
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.25, 0.75, 1.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(rec.front_face);
+        assert!((rec.t - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_read_ascii_stl() {
+        assert_square(read_stl(ASCII_SQUARE.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_read_binary_stl() {
+        assert_square(read_stl(binary_square().as_slice()).unwrap());
+    }
+
+    #[test]
+    fn test_stl_errors() {
+        let two_corners = ASCII_SQUARE.replacen("      vertex 1 1 0\n", "", 1);
+        assert!(matches!(
+            read_stl(two_corners.as_bytes()),
+            Err(StlError::InvalidData(_))
+        ));
+        let bad_number = ASCII_SQUARE.replacen("vertex 1 0 0", "vertex 1 zero 0", 1);
+        assert!(matches!(
+            read_stl(bad_number.as_bytes()),
+            Err(StlError::InvalidData(_))
+        ));
+        let truncated = binary_square();
+        assert!(matches!(
+            read_stl(&truncated[..truncated.len() - 1]),
+            Err(StlError::InvalidData(_))
+        ));
+        assert!(matches!(
+            load_stl("does_not_exist.stl", Arc::new(TestMaterial)),
+            Err(StlError::Io(_))
+        ));
+    }
+}
@@ -1,69 +1,41 @@
-use image::RgbImage;
 use indicatif::ProgressBar;
-use std::sync::mpsc;
 use std::sync::Arc;
 
 use rayon::prelude::*;
 
-mod utils;
-use utils::color_utils::*;
-use utils::image_utils::write_image;
-use utils::vec3_utils::*;
-use utils::random_number_utils::{random_f64, random_f64_range};
-
-mod vectors;
-use vectors::vec3::Vec3;
-
-mod rays;
-use rays::ray::Ray;
-
-mod hittables;
-use hittables::hittable::*;
-use hittables::hittable_list::*;
-use hittables::sphere::Sphere;
-
-mod cameras;
-use cameras::camera::Camera;
-
-mod materials;
-use materials::dielectric::Dielectric;
-use materials::lambertian::Lambertian;
-use materials::metal::Metal;
-
-use std::f64::INFINITY;
+use raytracing_in_one_weekend::cameras::camera::Camera;
+use raytracing_in_one_weekend::cameras::camera_model::CameraModel;
+use raytracing_in_one_weekend::denoisers::atrous_denoiser::AtrousDenoiser;
+use raytracing_in_one_weekend::films::aov_film::AovFilm;
+use raytracing_in_one_weekend::films::film::Film;
+use raytracing_in_one_weekend::films::gaussian_filter::GaussianFilter;
+use raytracing_in_one_weekend::hittables::hittable_list::*;
+use raytracing_in_one_weekend::hittables::plane::Plane;
+use raytracing_in_one_weekend::hittables::sphere::Sphere;
+use raytracing_in_one_weekend::lights::environment_map::EnvironmentMap;
+use raytracing_in_one_weekend::lights::gradient_sky::GradientSky;
+use raytracing_in_one_weekend::lights::preetham_sky::PreethamSky;
+use raytracing_in_one_weekend::materials::dielectric::Dielectric;
+use raytracing_in_one_weekend::materials::lambertian::Lambertian;
+use raytracing_in_one_weekend::materials::metal::Metal;
+use raytracing_in_one_weekend::renderers::aov::{Aov, AovSample};
+use raytracing_in_one_weekend::renderers::renderer::ray_color_with_aovs;
+use raytracing_in_one_weekend::scenes::scene::Scene;
+use raytracing_in_one_weekend::utils::image_utils::{linear_to_image, write_image};
+use raytracing_in_one_weekend::utils::random_number_utils::{random_f64, random_f64_range};
+use raytracing_in_one_weekend::vectors::vec3::Vec3;
 
 use rand::Rng;
 
-fn ray_color(r: &Ray, world: &dyn Hittable, depth: i32) -> Vec3 {
-    if depth <= 0 {
-        return Vec3::new();
-    }
-
-    match world.hit(r, 0.001, INFINITY) {
-        None => {
-            let unit_direction = unit_vector(r.direction());
-            let t = 0.5 * (unit_direction.y() + 1.0);
-            (1.0 - t) * Vec3::new_with_values(1.0, 1.0, 1.0)
-                + t * Vec3::new_with_values(0.5, 0.7, 1.0)
-        }
-        Some(rec) => match rec.mat_ptr.scatter(r, &rec) {
-            None => Vec3::new(),
-            Some((scattered, attenuation)) => {
-                attenuation * ray_color(&scattered, world, depth - 1)
-            }
-        },
-    }
-}
-
-fn random_scene() -> HittableList {
-    let mut world = HittableList::new();
+fn random_scene() -> Scene {
+    let mut scene = Scene::new(HittableList::new());
 
-    let ground_material = Arc::new(Lambertian {
+    let ground_material = scene.add_material(Arc::new(Lambertian {
         albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
-    });
-    world.add(Box::new(Sphere::new(
-        Vec3::new_with_values(0.0, -1000.0, 0.0),
-        1000.0,
+    }));
+    scene.world.add(Box::new(Plane::new(
+        Vec3::new(),
+        Vec3::new_with_values(0.0, 1.0, 0.0),
         ground_material,
     )));
 
@@ -76,10 +48,10 @@ fn random_scene() -> HittableList {
                 
                 if choose_mat < 0.8 {
                     let albedo = Vec3::random_vec3() * Vec3::random_vec3();
-                    let sphere_material  = Arc::new(Lambertian {
+                    let sphere_material  = scene.add_material(Arc::new(Lambertian {
                         albedo,
-                    });
-                    world.add(Box::new(Sphere::new(
+                    }));
+                    scene.world.add(Box::new(Sphere::new(
                         center,
                         0.2,
                         sphere_material.clone(),
@@ -87,20 +59,20 @@ fn random_scene() -> HittableList {
                 } else if choose_mat < 0.95 {
                     let albedo = Vec3::random_vec3_min_max(0.5, 1.0);
                     let fuzz = random_f64_range(0.0, 0.5);
-                    let sphere_material  = Arc::new(Metal {
+                    let sphere_material  = scene.add_material(Arc::new(Metal {
                         albedo,
                         fuzz,
-                    });
-                    world.add(Box::new(Sphere::new(
+                    }));
+                    scene.world.add(Box::new(Sphere::new(
                         center,
                         0.2,
                         sphere_material.clone(),
                     )));
                 } else {
-                    let sphere_material = Arc::new(Dielectric {
+                    let sphere_material = scene.add_material(Arc::new(Dielectric {
                         ir: 1.5,
-                    });
-                    world.add(Box::new(Sphere::new(
+                    }));
+                    scene.world.add(Box::new(Sphere::new(
                         center,
                         0.2,
                         sphere_material.clone(),
@@ -110,33 +82,33 @@ fn random_scene() -> HittableList {
         }
     }
 
-    let material1 = Arc::new(Dielectric { ir: 1.5 });
-    world.add(Box::new(Sphere::new(
+    let material1 = scene.add_material(Arc::new(Dielectric { ir: 1.5 }));
+    scene.world.add(Box::new(Sphere::new(
         Vec3::new_with_values(0.0, 1.0, 0.0),
         1.0,
         material1,
     )));
 
-    let material2  = Arc::new(Lambertian {
+    let material2  = scene.add_material(Arc::new(Lambertian {
         albedo: Vec3::new_with_values(0.4, 0.2, 0.1),
-    });
-    world.add(Box::new(Sphere::new(
+    }));
+    scene.world.add(Box::new(Sphere::new(
         Vec3::new_with_values(-4.0, 1.0, 0.0),
         1.0,
         material2,
     )));
 
-    let material3 = Arc::new(Metal {
+    let material3 = scene.add_material(Arc::new(Metal {
         albedo: Vec3::new_with_values(0.7, 0.6, 0.5),
         fuzz: 0.0,
-    });
-    world.add(Box::new(Sphere::new(
+    }));
+    scene.world.add(Box::new(Sphere::new(
         Vec3::new_with_values(4.0, 1.0, 0.0),
         1.0,
         material3,
     )));
 
-    world
+    scene
 }
 
 fn main() {
@@ -149,7 +121,25 @@ fn main() {
 
     println!("Image dimensions are {} X {}", IMAGE_WIDTH, IMAGE_HEIGHT);
 
-    let world = random_scene();
+    // The default sky can be replaced by a physical sky or an .hdr or .exr environment map
+    let mut scene = random_scene();
+    match std::env::args().nth(1) {
+        Some(arg) if arg == "--physical-sky" => {
+            let sky = PreethamSky::new(
+                35.0,
+                60.0,
+                2.5,
+                Vec3::new_with_values(0.3, 0.3, 0.3),
+                0.1,
+            );
+            scene.add_light(Arc::new(sky.sun_light(0.53, 3.0)));
+            scene.add_light(Arc::new(sky));
+        }
+        Some(path) => scene.add_light(Arc::new(
+            EnvironmentMap::load(&path, 0.0, 1.0).expect("Could not load environment map"),
+        )),
+        None => scene.add_light(Arc::new(GradientSky::new())),
+    }
 
     let lookfrom = Vec3::new_with_values(13.0, 2.0, 3.0);
     let lookat = Vec3::new_with_values(0.0, 0.0, 0.0);
@@ -157,7 +147,7 @@ fn main() {
     let dist_to_focus = 10.0;
     let aperture = 0.1;
 
-    let cam = Camera::new(
+    let cam: Box<dyn CameraModel> = Box::new(Camera::new(
         lookfrom,
         lookat,
         vup,
@@ -165,38 +155,55 @@ fn main() {
         ASPECT_RATIO,
         aperture,
         dist_to_focus,
+    ));
+    // Samples are spread over neighboring pixels by the reconstruction filter
+    let film = Film::new(
+        IMAGE_WIDTH,
+        IMAGE_HEIGHT,
+        Box::new(GaussianFilter::new(1.5, 0.5)),
     );
-    let mut img: RgbImage = RgbImage::new(IMAGE_WIDTH, IMAGE_HEIGHT);
+    // Depth, normals, ids and lighting passes from each pixel's first hits
+    let aovs = AovFilm::new(IMAGE_WIDTH, IMAGE_HEIGHT);
     let bar = ProgressBar::new((IMAGE_WIDTH * IMAGE_HEIGHT) as u64);
-    let (pixel_sender, pixel_receiver) = mpsc::channel();
-
-    (0..IMAGE_HEIGHT)
-        .into_par_iter()
-        .for_each_with(pixel_sender, |s, y| {
-            (0..IMAGE_WIDTH).into_iter().for_each(|x| {
-                let mut rng = rand::thread_rng();
-                let mut total_color = Vec3::new();
-                for _i in 0..SAMPLES_PER_PIXEL {
-                    let u: f64 = (x as f64 + rng.gen::<f64>()) / ((IMAGE_WIDTH - 1) as f64);
-                    let v: f64 = (y as f64 + rng.gen::<f64>()) / ((IMAGE_HEIGHT - 1) as f64);
-                    let ray = cam.get_ray(u, v);
-                    total_color += ray_color(&ray, &world, MAX_DEPTH);
-                }
-                s.send((x as u32, y as u32, total_color / SAMPLES_PER_PIXEL as f64))
-                    .unwrap();
-                bar.inc(1);
-            })
-        });
-
-    for i in pixel_receiver {
-        let vec3 = gamma_correct(&i.2, 0.5);
-        img.put_pixel(
-            i.0,
-            IMAGE_HEIGHT - 1 - i.1,
-            image::Rgb(convert_vec3_to_color(vec3)),
-        );
-    }
 
-    write_image(img, "output.png");
+    (0..IMAGE_HEIGHT).into_par_iter().for_each(|y| {
+        (0..IMAGE_WIDTH).for_each(|x| {
+            let mut rng = rand::thread_rng();
+            for _i in 0..SAMPLES_PER_PIXEL {
+                let px = x as f64 + rng.gen::<f64>();
+                let py = y as f64 + rng.gen::<f64>();
+                let ray = cam.get_ray(px / IMAGE_WIDTH as f64, py / IMAGE_HEIGHT as f64);
+                let sample = match ray {
+                    Some(ray) => {
+                        ray_color_with_aovs(&ray, &scene, MAX_DEPTH).scaled(cam.exposure())
+                    }
+                    None => AovSample::new(),
+                };
+                film.add_sample(px, py, sample.beauty());
+                aovs.add_sample(x, y, &sample);
+            }
+            bar.inc(1);
+        })
+    });
+
+    write_image(film.to_image(), "output.png");
+    for aov in Aov::ALL.iter() {
+        write_image(aovs.to_image(*aov), &format!("output_{}.png", aov.name()));
+    }
+    aovs.write_exr(&film, "output.exr")
+        .expect("Could not write multi-layer EXR");
+
+    // Low sample count previews read better once the lighting noise is filtered out
+    let denoised = AtrousDenoiser::new().denoise(
+        IMAGE_WIDTH,
+        IMAGE_HEIGHT,
+        &film.pixels(),
+        &aovs.layer(Aov::Albedo),
+        &aovs.layer(Aov::Normal),
+    ).expect("Could not denoise the image");
+    write_image(
+        linear_to_image(IMAGE_WIDTH, IMAGE_HEIGHT, &denoised),
+        "output_denoised.png",
+    );
     bar.finish();
 }
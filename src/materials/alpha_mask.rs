@@ -0,0 +1,49 @@
+use crate::{
+    hittables::{alpha_masked::AlphaMode, hittable::HitRecord},
+    rays::ray::Ray,
+    textures::texture::Texture,
+    vectors::vec3::Vec3,
+};
+
+use super::material::Material;
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Cutout material for leaves and fences. Primitives skip hits on the transparent
+// parts, so the base material only ever scatters on the opaque parts.
+pub struct AlphaMask {
+    pub base: Arc<dyn Material>,
+    pub mask: Arc<dyn Texture>,
+    pub mode: AlphaMode,
+}
+
+impl Material for AlphaMask {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        self.base.scatter(r_in, rec)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        self.mode.passes(self.mask.as_ref(), rec) && self.base.opaque(rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        self.base.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r_in, rec, direction)
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        self.base.scatter_with_pdf(r_in, rec)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base.albedo(rec)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    textures::texture::Texture,
+    utils::vec3_utils::{cross, unit_vector},
+    vectors::vec3::Vec3,
+};
+
+use super::material::{
+    eval_with_shading_normal, pdf_with_shading_normal, scatter_with_shading_normal, Material,
+};
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Perturbs the shading normal with the uv gradient of a grayscale height texture
+pub struct BumpMap {
+    pub base: Arc<dyn Material>,
+    pub height: Arc<dyn Texture>,
+    pub scale: f64,
+}
+
+impl BumpMap {
+    const DELTA: f64 = 1e-3;
+
+    fn height_at(&self, u: f64, v: f64, p: &Vec3) -> f64 {
+        self.height.value(u, v, p).x()
+    }
+
+    pub fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let h = self.height_at(rec.u, rec.v, &rec.p);
+        let dh_du = (self.height_at(rec.u + BumpMap::DELTA, rec.v, &rec.p) - h) / BumpMap::DELTA;
+        let dh_dv = (self.height_at(rec.u, rec.v + BumpMap::DELTA, &rec.p) - h) / BumpMap::DELTA;
+
+        let normal = match rec.front_face {
+            true => rec.normal,
+            false => -rec.normal,
+        };
+        let bitangent = cross(&normal, &rec.tangent);
+        let perturbed =
+            unit_vector(normal - self.scale * (dh_du * rec.tangent + dh_dv * bitangent));
+        match rec.front_face {
+            true => perturbed,
+            false => -perturbed,
+        }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        scatter_with_shading_normal(self.base.as_ref(), r_in, rec, self.shading_normal(rec))
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        scatter_with_shading_normal(self.base.as_ref(), r_in, rec, self.shading_normal(rec))
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        self.base.opaque(rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        let shading_normal = self.shading_normal(rec);
+        eval_with_shading_normal(self.base.as_ref(), r_in, rec, shading_normal, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let shading_normal = self.shading_normal(rec);
+        pdf_with_shading_normal(self.base.as_ref(), r_in, rec, shading_normal, direction)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base.albedo(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use crate::textures::solid_color::SolidColor;
+    use crate::utils::vec3_utils::dot;
+
+    // Height rising along u, so bumps slope up towards the tangent
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn value(&self, u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+            Vec3::new_with_values(u, u, u)
+        }
+    }
+
+    struct MirrorMaterial;
+
+    impl Material for MirrorMaterial {
+        fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            let d = r_in.direction();
+            Some((
+                Ray {
+                    orig: rec.p,
+                    dir: d - 2.0 * dot(&d, &rec.normal) * rec.normal,
+                    time: 0.0,
+                },
+                Vec3::new_with_values(1.0, 1.0, 1.0),
+            ))
+        }
+    }
+
+    fn record(front_face: bool) -> HitRecord {
+        let normal = match front_face {
+            true => Vec3::new_with_values(0.0, 0.0, 1.0),
+            false => Vec3::new_with_values(0.0, 0.0, -1.0),
+        };
+        HitRecord {
+            p: Vec3::new(),
+            normal,
+            geometric_normal: normal,
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face,
+            mat_ptr: Arc::new(MirrorMaterial),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    fn bump_map(base: Arc<dyn Material>) -> BumpMap {
+        BumpMap {
+            base,
+            height: Arc::new(Ramp),
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_bump_map_tilts_down_the_slope() {
+        let material = bump_map(Arc::new(MirrorMaterial));
+        let downhill = unit_vector(Vec3::new_with_values(-1.0, 0.0, 1.0));
+        assert!((material.shading_normal(&record(true)) - downhill).length() < 1e-6);
+        // Seen from behind the surface bends the same way, so the normal flips with it
+        assert!((material.shading_normal(&record(false)) + downhill).length() < 1e-6);
+
+        let flat = BumpMap {
+            base: Arc::new(MirrorMaterial),
+            height: Arc::new(SolidColor::new_with_values(0.5, 0.5, 0.5)),
+            scale: 1.0,
+        };
+        assert_eq!(
+            flat.shading_normal(&record(true)),
+            Vec3::new_with_values(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_bump_map_does_not_leak() {
+        // Mirroring about the tilted normal would send this ray below the surface
+        let material = bump_map(Arc::new(MirrorMaterial));
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.5, 0.0, 1.0),
+            dir: Vec3::new_with_values(-0.5, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(material.scatter(&ray, &record(true)).is_none());
+
+        let ray = Ray {
+            orig: Vec3::new_with_values(-0.5, 0.0, 1.0),
+            dir: Vec3::new_with_values(0.5, 0.0, -1.0),
+            time: 0.0,
+        };
+        let (scattered, _) = material.scatter(&ray, &record(true)).unwrap();
+        assert!(scattered.direction().z() > 0.0);
+
+        // Below the surface but above the tilted shading surface
+        let material = bump_map(Arc::new(Lambertian {
+            albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+        }));
+        let below = Vec3::new_with_values(-1.0, 0.0, -0.2);
+        let above = Vec3::new_with_values(-1.0, 0.0, 0.2);
+        assert_eq!(material.eval(&ray, &record(true), &below), Vec3::new());
+        assert!(material.eval(&ray, &record(true), &above).x() > 0.0);
+    }
+}
@@ -17,7 +17,7 @@ pub struct Dielectric {
 }
 
 impl Dielectric {
-    fn reflectance(&self, cosine: f64, ref_idx: f64) -> f64 {
+    pub fn reflectance(&self, cosine: f64, ref_idx: f64) -> f64 {
         let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         r0 = r0 * r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
@@ -35,18 +35,23 @@ impl Material for Dielectric {
         let cos_theta = min(dot(&-unit_direction, &rec.normal), 1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction: Vec3;
-
-        if cannot_refract || self.reflectance(cos_theta, refraction_ratio) > random_f64() {
-            direction = reflect(&unit_direction, &rec.normal);
-        } else {
-            direction = refract(&unit_direction, &rec.normal, refraction_ratio)
-        }
+        let direction =
+            if cannot_refract || self.reflectance(cos_theta, refraction_ratio) > random_f64() {
+                reflect(&unit_direction, &rec.normal)
+            } else {
+                refract(&unit_direction, &rec.normal, refraction_ratio)
+            };
 
         let scattered = Ray {
             orig: rec.p,
             dir: direction,
+            time: r_in.time(),
         };
         Some((scattered, attenuation))
     }
+
+    // Clear glass lets all light through one way or another
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::new_with_values(1.0, 1.0, 1.0)
+    }
 }
@@ -0,0 +1,37 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    textures::{solid_color::SolidColor, texture::Texture},
+    vectors::vec3::Vec3,
+};
+
+use super::material::Material;
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Emits from the front face only, so quads used as lights shine along their normal
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(color: Vec3) -> Self {
+        DiffuseLight {
+            emit: Arc::new(SolidColor::new(color)),
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        None
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        match rec.front_face {
+            true => self.emit.value(rec.u, rec.v, &rec.p),
+            false => Vec3::new(),
+        }
+    }
+}
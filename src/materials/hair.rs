@@ -0,0 +1,526 @@
+use crate::hittables::hittable::HitRecord;
+use crate::rays::ray::Ray;
+use crate::utils::color_utils::luminance;
+use crate::utils::random_number_utils::random_f64;
+use crate::utils::vec3_utils::{cross, dot, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+use std::f64::consts::PI;
+use std::option::Option;
+
+use super::material::Material;
+
+// Number of scattering lobes evaluated exactly (R, TT, TRT), the rest are lumped together
+const P_MAX: usize = 3;
+const SQRT_PI_OVER_8: f64 = 0.626_657_068_657_750_1;
+
+// Hair fiber BSDF after Chiang et al. 2016, "A Practical and Controllable Hair and Fur
+// Model for Production Path Tracing". It expects the tangent of the hit to follow the
+// fiber and v to run across it, as curves provide.
+pub struct Hair {
+    sigma_a: Vec3,
+    // Color the absorption gives after multiple scattering, as from_color inverts
+    color: Vec3,
+    eta: f64,
+    // Longitudinal variance of each lobe
+    v: [f64; P_MAX + 1],
+    // Azimuthal logistic scale
+    s: f64,
+    sin_2k_alpha: [f64; 3],
+    cos_2k_alpha: [f64; 3],
+}
+
+// Geometry of a single evaluation in the fiber frame, where x runs along the fiber
+struct HairFrame {
+    h: f64,
+    gamma_o: f64,
+    sin_theta_o: f64,
+    cos_theta_o: f64,
+    phi_o: f64,
+}
+
+fn safe_sqrt(x: f64) -> f64 {
+    x.max(0.0).sqrt()
+}
+
+fn safe_asin(x: f64) -> f64 {
+    x.clamp(-1.0, 1.0).asin()
+}
+
+fn exp3(v: Vec3) -> Vec3 {
+    Vec3::new_with_values(v.x().exp(), v.y().exp(), v.z().exp())
+}
+
+fn fr_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    let (cos_theta_i, eta_i, eta_t) = match cos_theta_i > 0.0 {
+        true => (cos_theta_i, 1.0, eta),
+        false => (-cos_theta_i, eta, 1.0),
+    };
+    let sin_theta_t = eta_i / eta_t * safe_sqrt(1.0 - cos_theta_i * cos_theta_i);
+    if sin_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+    let parallel =
+        (eta_t * cos_theta_i - eta_i * cos_theta_t) / (eta_t * cos_theta_i + eta_i * cos_theta_t);
+    let perpendicular =
+        (eta_i * cos_theta_i - eta_t * cos_theta_t) / (eta_i * cos_theta_i + eta_t * cos_theta_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+// Relates the absorption to the color of the fiber for an azimuthal roughness
+fn color_exponent(beta_n: f64) -> f64 {
+    5.969 - 0.215 * beta_n + 2.532 * beta_n.powi(2) - 10.73 * beta_n.powi(3)
+        + 5.574 * beta_n.powi(4)
+        + 0.245 * beta_n.powi(5)
+}
+
+// Modified Bessel function of the first kind
+fn i0(x: f64) -> f64 {
+    let mut value = 0.0;
+    let mut x2i = 1.0;
+    let mut factorial: f64 = 1.0;
+    let mut four_i = 1.0;
+    for i in 0..10 {
+        if i > 1 {
+            factorial *= i as f64;
+        }
+        value += x2i / (four_i * factorial * factorial);
+        x2i *= x * x;
+        four_i *= 4.0;
+    }
+    value
+}
+
+fn log_i0(x: f64) -> f64 {
+    match x > 12.0 {
+        true => x + 0.5 * (-(2.0 * PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x)),
+        false => i0(x).ln(),
+    }
+}
+
+// Longitudinal scattering
+fn mp(cos_theta_i: f64, cos_theta_o: f64, sin_theta_i: f64, sin_theta_o: f64, v: f64) -> f64 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    match v <= 0.1 {
+        true => (log_i0(a) - b - 1.0 / v + std::f64::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp(),
+        false => ((-b).exp() * i0(a)) / ((1.0 / v).sinh() * 2.0 * v),
+    }
+}
+
+// Attenuation of each lobe from Fresnel reflection and absorption inside the fiber
+fn ap(cos_theta_o: f64, eta: f64, h: f64, transmittance: Vec3) -> [Vec3; P_MAX + 1] {
+    let cos_gamma_o = safe_sqrt(1.0 - h * h);
+    let f = fr_dielectric(cos_theta_o * cos_gamma_o, eta);
+    let mut ap = [Vec3::new(); P_MAX + 1];
+    ap[0] = Vec3::new_with_values(f, f, f);
+    ap[1] = (1.0 - f) * (1.0 - f) * transmittance;
+    for p in 2..P_MAX {
+        ap[p] = f * ap[p - 1] * transmittance;
+    }
+    let one = Vec3::new_with_values(1.0, 1.0, 1.0);
+    let denominator = one - f * transmittance;
+    ap[P_MAX] = f * ap[P_MAX - 1] * transmittance;
+    for channel in 0..3 {
+        ap[P_MAX][channel] /= denominator[channel];
+    }
+    ap
+}
+
+fn phi(p: usize, gamma_o: f64, gamma_t: f64) -> f64 {
+    2.0 * p as f64 * gamma_t - 2.0 * gamma_o + p as f64 * PI
+}
+
+fn logistic(x: f64, s: f64) -> f64 {
+    let x = x.abs();
+    (-x / s).exp() / (s * (1.0 + (-x / s).exp()).powi(2))
+}
+
+fn logistic_cdf(x: f64, s: f64) -> f64 {
+    1.0 / (1.0 + (-x / s).exp())
+}
+
+fn trimmed_logistic(x: f64, s: f64, a: f64, b: f64) -> f64 {
+    logistic(x, s) / (logistic_cdf(b, s) - logistic_cdf(a, s))
+}
+
+fn sample_trimmed_logistic(u: f64, s: f64, a: f64, b: f64) -> f64 {
+    let k = logistic_cdf(b, s) - logistic_cdf(a, s);
+    let x = -s * (1.0 / (u * k + logistic_cdf(a, s)) - 1.0).ln();
+    x.clamp(a, b)
+}
+
+// Azimuthal scattering
+fn np(phi_angle: f64, p: usize, s: f64, gamma_o: f64, gamma_t: f64) -> f64 {
+    let mut dphi = phi_angle - phi(p, gamma_o, gamma_t);
+    while dphi > PI {
+        dphi -= 2.0 * PI;
+    }
+    while dphi < -PI {
+        dphi += 2.0 * PI;
+    }
+    trimmed_logistic(dphi, s, -PI, PI)
+}
+
+impl Hair {
+    // beta_m and beta_n are the longitudinal and azimuthal roughness in [0, 1], alpha
+    // the tilt of the cuticle scales in degrees, typically 2
+    pub fn new(sigma_a: Vec3, beta_m: f64, beta_n: f64, alpha: f64, eta: f64) -> Self {
+        let v0 = (0.726 * beta_m + 0.812 * beta_m * beta_m + 3.7 * beta_m.powi(20)).powi(2);
+        let mut v = [4.0 * v0; P_MAX + 1];
+        v[0] = v0;
+        v[1] = 0.25 * v0;
+
+        let s =
+            SQRT_PI_OVER_8 * (0.265 * beta_n + 1.194 * beta_n * beta_n + 5.372 * beta_n.powi(22));
+
+        let mut sin_2k_alpha = [0.0; 3];
+        let mut cos_2k_alpha = [0.0; 3];
+        sin_2k_alpha[0] = alpha.to_radians().sin();
+        cos_2k_alpha[0] = safe_sqrt(1.0 - sin_2k_alpha[0] * sin_2k_alpha[0]);
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1] * cos_2k_alpha[i - 1]
+                - sin_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+        }
+
+        let exponent = color_exponent(beta_n);
+        let mut color = Vec3::new();
+        for channel in 0..3 {
+            color[channel] = (-sigma_a[channel].sqrt() * exponent).exp();
+        }
+
+        Hair {
+            sigma_a,
+            color,
+            eta,
+            v,
+            s,
+            sin_2k_alpha,
+            cos_2k_alpha,
+        }
+    }
+
+    // Natural hair colors from the concentrations of the two melanin pigments, about 8
+    // for black hair, 1.3 for brown and 0.3 for blonde
+    pub fn from_melanin(eumelanin: f64, pheomelanin: f64, beta_m: f64, beta_n: f64) -> Self {
+        let eumelanin_sigma_a = Vec3::new_with_values(0.419, 0.697, 1.37);
+        let pheomelanin_sigma_a = Vec3::new_with_values(0.187, 0.4, 1.05);
+        Hair::new(
+            eumelanin * eumelanin_sigma_a + pheomelanin * pheomelanin_sigma_a,
+            beta_m,
+            beta_n,
+            2.0,
+            1.55,
+        )
+    }
+
+    // Absorption that gives roughly the requested color after multiple scattering
+    pub fn from_color(color: Vec3, beta_m: f64, beta_n: f64) -> Self {
+        let denominator = color_exponent(beta_n);
+        let mut sigma_a = Vec3::new();
+        for channel in 0..3 {
+            sigma_a[channel] = (color[channel].max(1e-4).ln() / denominator).powi(2);
+        }
+        Hair::new(sigma_a, beta_m, beta_n, 2.0, 1.55)
+    }
+
+    fn to_local(rec: &HitRecord, w: &Vec3) -> Vec3 {
+        let y = cross(&rec.normal, &rec.tangent);
+        let w = unit_vector(*w);
+        Vec3::new_with_values(dot(&w, &rec.tangent), dot(&w, &y), dot(&w, &rec.normal))
+    }
+
+    fn to_world(rec: &HitRecord, w: &Vec3) -> Vec3 {
+        let y = cross(&rec.normal, &rec.tangent);
+        w.x() * rec.tangent + w.y() * y + w.z() * rec.normal
+    }
+
+    fn frame(rec: &HitRecord, wo: &Vec3) -> HairFrame {
+        let h = -1.0 + 2.0 * rec.v;
+        let sin_theta_o = wo.x();
+        HairFrame {
+            h,
+            gamma_o: safe_asin(h),
+            sin_theta_o,
+            cos_theta_o: safe_sqrt(1.0 - sin_theta_o * sin_theta_o),
+            phi_o: wo.z().atan2(wo.y()),
+        }
+    }
+
+    // Transmittance of one pass through the fiber and the refracted azimuth gamma_t
+    fn transmittance(&self, frame: &HairFrame) -> (Vec3, f64) {
+        let sin_theta_t = frame.sin_theta_o / self.eta;
+        let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+        let etap = (self.eta * self.eta - frame.sin_theta_o * frame.sin_theta_o).sqrt()
+            / frame.cos_theta_o;
+        let sin_gamma_t = frame.h / etap;
+        let cos_gamma_t = safe_sqrt(1.0 - sin_gamma_t * sin_gamma_t);
+        let transmittance = exp3(-(2.0 * cos_gamma_t / cos_theta_t) * self.sigma_a);
+        (transmittance, safe_asin(sin_gamma_t))
+    }
+
+    // Outgoing angle of lobe p, shifted by the tilt of the scales
+    fn tilted(&self, p: usize, frame: &HairFrame) -> (f64, f64) {
+        let (sin_o, cos_o) = (frame.sin_theta_o, frame.cos_theta_o);
+        let (sin_theta, cos_theta) = match p {
+            0 => (
+                sin_o * self.cos_2k_alpha[1] - cos_o * self.sin_2k_alpha[1],
+                cos_o * self.cos_2k_alpha[1] + sin_o * self.sin_2k_alpha[1],
+            ),
+            1 => (
+                sin_o * self.cos_2k_alpha[0] + cos_o * self.sin_2k_alpha[0],
+                cos_o * self.cos_2k_alpha[0] - sin_o * self.sin_2k_alpha[0],
+            ),
+            2 => (
+                sin_o * self.cos_2k_alpha[2] + cos_o * self.sin_2k_alpha[2],
+                cos_o * self.cos_2k_alpha[2] - sin_o * self.sin_2k_alpha[2],
+            ),
+            _ => (sin_o, cos_o),
+        };
+        (sin_theta, cos_theta.abs())
+    }
+
+    // Probability of picking each lobe when sampling
+    fn lobe_pdfs(&self, frame: &HairFrame) -> [f64; P_MAX + 1] {
+        let (transmittance, _) = self.transmittance(frame);
+        let ap = ap(frame.cos_theta_o, self.eta, frame.h, transmittance);
+        let weights = ap.map(|a| luminance(&a));
+        let total: f64 = weights.iter().sum();
+        weights.map(|weight| weight / total)
+    }
+
+    // BSDF times the cosine, which the model includes by construction
+    fn f(&self, frame: &HairFrame, wi: &Vec3) -> Vec3 {
+        let sin_theta_i = wi.x();
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+        let phi_angle = wi.z().atan2(wi.y()) - frame.phi_o;
+        let (transmittance, gamma_t) = self.transmittance(frame);
+        let ap = ap(frame.cos_theta_o, self.eta, frame.h, transmittance);
+
+        let mut f = Vec3::new();
+        for (p, ap) in ap.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilted(p, frame);
+            f += mp(
+                cos_theta_i,
+                cos_theta_op,
+                sin_theta_i,
+                sin_theta_op,
+                self.v[p],
+            ) * np(phi_angle, p, self.s, frame.gamma_o, gamma_t)
+                * *ap;
+        }
+        f += mp(
+            cos_theta_i,
+            frame.cos_theta_o,
+            sin_theta_i,
+            frame.sin_theta_o,
+            self.v[P_MAX],
+        ) / (2.0 * PI)
+            * ap[P_MAX];
+        f
+    }
+
+    fn local_pdf(&self, frame: &HairFrame, wi: &Vec3) -> f64 {
+        let sin_theta_i = wi.x();
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+        let phi_angle = wi.z().atan2(wi.y()) - frame.phi_o;
+        let (_, gamma_t) = self.transmittance(frame);
+        let lobe_pdfs = self.lobe_pdfs(frame);
+
+        let mut pdf = 0.0;
+        for (p, lobe_pdf) in lobe_pdfs.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilted(p, frame);
+            pdf += mp(
+                cos_theta_i,
+                cos_theta_op,
+                sin_theta_i,
+                sin_theta_op,
+                self.v[p],
+            ) * lobe_pdf
+                * np(phi_angle, p, self.s, frame.gamma_o, gamma_t);
+        }
+        pdf += mp(
+            cos_theta_i,
+            frame.cos_theta_o,
+            sin_theta_i,
+            frame.sin_theta_o,
+            self.v[P_MAX],
+        ) * lobe_pdfs[P_MAX]
+            / (2.0 * PI);
+        pdf
+    }
+
+    fn sample(&self, frame: &HairFrame) -> Vec3 {
+        // Pick a lobe, then sample its longitudinal and azimuthal terms
+        let lobe_pdfs = self.lobe_pdfs(frame);
+        let mut choice = random_f64();
+        let mut p = 0;
+        while p < P_MAX && choice >= lobe_pdfs[p] {
+            choice -= lobe_pdfs[p];
+            p += 1;
+        }
+        let (sin_theta_op, cos_theta_op) = self.tilted(p, frame);
+
+        let u = random_f64().max(1e-5);
+        let cos_theta = 1.0 + self.v[p] * (u + (1.0 - u) * (-2.0 / self.v[p]).exp()).ln();
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let cos_phi = (2.0 * PI * random_f64()).cos();
+        let sin_theta_i = -cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op;
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+
+        let (_, gamma_t) = self.transmittance(frame);
+        let dphi = match p < P_MAX {
+            true => {
+                phi(p, frame.gamma_o, gamma_t)
+                    + sample_trimmed_logistic(random_f64(), self.s, -PI, PI)
+            }
+            false => 2.0 * PI * random_f64(),
+        };
+        let phi_i = frame.phi_o + dphi;
+        Vec3::new_with_values(
+            sin_theta_i,
+            cos_theta_i * phi_i.cos(),
+            cos_theta_i * phi_i.sin(),
+        )
+    }
+}
+
+impl Material for Hair {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        let wo = Hair::to_local(rec, &-r_in.direction());
+        let frame = Hair::frame(rec, &wo);
+        let wi = self.sample(&frame);
+        let pdf = self.local_pdf(&frame, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let scattered = Ray {
+            orig: rec.p,
+            dir: Hair::to_world(rec, &wi),
+            time: r_in.time(),
+        };
+        Some((scattered, self.f(&frame, &wi) / pdf))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        let wo = Hair::to_local(rec, &-r_in.direction());
+        self.f(&Hair::frame(rec, &wo), &Hair::to_local(rec, direction))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let wo = Hair::to_local(rec, &-r_in.direction());
+        self.local_pdf(&Hair::frame(rec, &wo), &Hair::to_local(rec, direction))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        self.color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn fiber_hit(v: f64, material: Arc<Hair>) -> HitRecord {
+        HitRecord {
+            p: Vec3::new(),
+            normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            geometric_normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.5,
+            v,
+            front_face: true,
+            mat_ptr: material,
+            color: None,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    fn incoming(wo: Vec3) -> Ray {
+        Ray {
+            orig: wo,
+            dir: -wo,
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // Without absorption every lobe together conserves energy
+        for (beta_m, beta_n) in [(0.2, 0.3), (0.5, 0.5), (0.9, 0.8)] {
+            let hair = Arc::new(Hair::new(Vec3::new(), beta_m, beta_n, 2.0, 1.55));
+            let samples = 400000;
+            let mut total = 0.0;
+            for _ in 0..samples {
+                let rec = fiber_hit(random_f64(), hair.clone());
+                let r_in = incoming(Vec3::random_unit_vector());
+                let f = hair.eval(&r_in, &rec, &Vec3::random_unit_vector());
+                total += f.y() * 4.0 * PI;
+            }
+            let average = total / samples as f64;
+            assert!(
+                (average - 1.0).abs() < 0.05,
+                "{} {} {}",
+                beta_m,
+                beta_n,
+                average
+            );
+        }
+    }
+
+    #[test]
+    fn test_sampling_matches_pdf() {
+        let hair = Arc::new(Hair::from_melanin(1.3, 0.0, 0.3, 0.3));
+        let r_in = incoming(unit_vector(Vec3::new_with_values(0.3, 0.5, 0.8)));
+        let rec = fiber_hit(0.3, hair.clone());
+
+        // The density integrates to one over the sphere
+        let samples = 200000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            total += hair.pdf(&r_in, &rec, &Vec3::random_unit_vector()) * 4.0 * PI;
+        }
+        assert!((total / samples as f64 - 1.0).abs() < 0.05);
+
+        // Scatter weights are the evaluated BSDF over the reported density
+        for _ in 0..100 {
+            let (scattered, attenuation) = hair.scatter(&r_in, &rec).unwrap();
+            let direction = scattered.direction();
+            let expected = hair.eval(&r_in, &rec, &direction) / hair.pdf(&r_in, &rec, &direction);
+            assert!((attenuation - expected).length() < 1e-9 * expected.length().max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_melanin_darkens_hair() {
+        let r_in = incoming(unit_vector(Vec3::new_with_values(0.1, 0.2, 1.0)));
+        let mut albedos = vec![];
+        for eumelanin in [0.3, 8.0] {
+            let hair = Arc::new(Hair::from_melanin(eumelanin, 0.0, 0.3, 0.3));
+            let samples = 20000;
+            let mut total = Vec3::new();
+            for _ in 0..samples {
+                let rec = fiber_hit(random_f64(), hair.clone());
+                total += hair.scatter(&r_in, &rec).unwrap().1;
+            }
+            albedos.push(total / samples as f64);
+        }
+        assert!(albedos[1].x() < albedos[0].x());
+        // Blonde hair reflects more red than blue
+        assert!(albedos[0].x() > albedos[0].z());
+    }
+
+    #[test]
+    fn test_albedo_is_the_requested_color() {
+        let color = Vec3::new_with_values(0.6, 0.4, 0.2);
+        let hair = Arc::new(Hair::from_color(color, 0.3, 0.3));
+        let rec = fiber_hit(0.5, hair.clone());
+        assert!((hair.albedo(&rec) - color).length() < 1e-12);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::hittables::hittable::HitRecord;
 use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::{dot, unit_vector};
 use crate::vectors::vec3::Vec3;
 
+use std::f64::consts::PI;
+
 use super::material::Material;
 
 pub struct Lambertian {
@@ -17,8 +20,25 @@ impl Material for Lambertian {
         let scattered = Ray {
             orig: rec.p,
             dir: scatter_direction,
+            time: r_in.time(),
         };
         let attenuation = self.albedo;
         Some((scattered, attenuation))
     }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        self.albedo * self.pdf(r_in, rec, direction)
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let cosine = dot(&rec.normal, &unit_vector(*direction));
+        match cosine > 0.0 {
+            true => cosine / PI,
+            false => 0.0,
+        }
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        self.albedo
+    }
 }
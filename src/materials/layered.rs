@@ -0,0 +1,178 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    utils::{
+        random_number_utils::random_f64,
+        vec3_utils::{dot, min, reflect, unit_vector},
+    },
+    vectors::vec3::Vec3,
+};
+
+use super::{dielectric::Dielectric, material::Material};
+
+use std::option::Option;
+use std::sync::Arc;
+
+pub struct Layered {
+    pub coat: Dielectric,
+    pub base: Arc<dyn Material>,
+}
+
+impl Layered {
+    pub fn new(coat_ir: f64, base: Arc<dyn Material>) -> Self {
+        Layered {
+            coat: Dielectric { ir: coat_ir },
+            base,
+        }
+    }
+
+    // Fraction of the incoming light that the coat lets through to the base
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> f64 {
+        let cos_theta = min(dot(&-unit_vector(r_in.direction()), &rec.normal), 1.0);
+        1.0 - self.coat.reflectance(cos_theta, 1.0 / self.coat.ir)
+    }
+}
+
+impl Material for Layered {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        self.scatter_with_pdf(r_in, rec)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        self.base.opaque(rec)
+    }
+
+    // Only the base can be light sampled, the coat reflection is specular
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        match rec.front_face {
+            true => self.transmittance(r_in, rec) * self.base.eval(r_in, rec, direction),
+            false => self.base.eval(r_in, rec, direction),
+        }
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        match rec.front_face {
+            true => self.transmittance(r_in, rec) * self.base.pdf(r_in, rec, direction),
+            false => self.base.pdf(r_in, rec, direction),
+        }
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        if !rec.front_face {
+            return self.base.scatter_with_pdf(r_in, rec);
+        }
+
+        let transmittance = self.transmittance(r_in, rec);
+        if transmittance < random_f64() {
+            let scattered = Ray {
+                orig: rec.p,
+                dir: reflect(&unit_vector(r_in.direction()), &rec.normal),
+                time: r_in.time(),
+            };
+            return Some((scattered, Vec3::new_with_values(1.0, 1.0, 1.0), 0.0));
+        }
+
+        // The coat is treated as an infinitely thin slab, so the refraction in and
+        // out of it cancels and the base sees the original incoming direction.
+        let (scattered, attenuation, pdf) = self.base.scatter_with_pdf(r_in, rec)?;
+        Some((scattered, attenuation, transmittance * pdf))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base.albedo(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+
+    fn hit(material: Arc<dyn Material>) -> HitRecord {
+        HitRecord {
+            p: Vec3::new(),
+            normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            geometric_normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face: true,
+            mat_ptr: material,
+            color: None,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    // Ray arriving at the origin with the given cosine to the normal
+    fn incoming(cos_theta: f64) -> Ray {
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        Ray {
+            orig: Vec3::new_with_values(-sin_theta, 0.0, cos_theta),
+            dir: Vec3::new_with_values(sin_theta, 0.0, -cos_theta),
+            time: 0.0,
+        }
+    }
+
+    fn layered(albedo: f64) -> Arc<Layered> {
+        Arc::new(Layered::new(
+            1.5,
+            Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(albedo, albedo, albedo),
+            }),
+        ))
+    }
+
+    // Fraction of samples reflected by the coat and average scattering weight
+    fn scatter_statistics(material: Arc<Layered>, cos_theta: f64) -> (f64, f64) {
+        let rec = hit(material.clone());
+        let r_in = incoming(cos_theta);
+        let mirror = reflect(&r_in.direction(), &rec.normal);
+        let samples = 20000;
+        let mut reflected = 0;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            let (scattered, attenuation) = material.scatter(&r_in, &rec).unwrap();
+            if (scattered.direction() - mirror).length() < 1e-12 {
+                reflected += 1;
+            }
+            total += attenuation.x();
+        }
+        (reflected as f64 / samples as f64, total / samples as f64)
+    }
+
+    #[test]
+    fn test_coat_reflectance() {
+        // About four percent at normal incidence, most of the light at grazing angles
+        let material = layered(0.5);
+        for cos_theta in [1.0, 0.5, 0.05] {
+            let (reflected, _) = scatter_statistics(material.clone(), cos_theta);
+            let expected = material.coat.reflectance(cos_theta, 1.0 / 1.5);
+            assert!((reflected - expected).abs() < 0.02);
+        }
+        let (normal, _) = scatter_statistics(material.clone(), 1.0);
+        let (grazing, _) = scatter_statistics(material, 0.05);
+        assert!((normal - 0.04).abs() < 0.01);
+        assert!(grazing > 0.7);
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // Over a white base nothing is lost, whichever layer the light bounces off
+        for cos_theta in [1.0, 0.5, 0.05] {
+            let (_, average) = scatter_statistics(layered(1.0), cos_theta);
+            assert!((average - 1.0).abs() < 1e-12);
+        }
+
+        // Over a grey base the coat adds to the base but never creates energy
+        for cos_theta in [1.0, 0.5, 0.05] {
+            let material = layered(0.5);
+            let fresnel = material.coat.reflectance(cos_theta, 1.0 / 1.5);
+            let (_, average) = scatter_statistics(material, cos_theta);
+            assert!(average > 0.5 && average <= 1.0);
+            assert!((average - (fresnel + (1.0 - fresnel) * 0.5)).abs() < 0.02);
+        }
+    }
+}
@@ -1,9 +1,115 @@
 use crate::hittables::hittable::HitRecord;
 use crate::rays::ray::Ray;
+use crate::utils::vec3_utils::{dot, unit_vector};
 use crate::vectors::vec3::Vec3;
 
 use std::option::Option;
 
 pub trait Material: Sync + Send {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)>;
+
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Vec3 {
+        Vec3::new()
+    }
+
+    // Hits on transparent parts of cutout materials are skipped by the primitives
+    fn opaque(&self, _rec: &HitRecord) -> bool {
+        true
+    }
+
+    // BSDF times the cosine term for scattering towards direction. Materials that only
+    // scatter into discrete directions leave this at zero and are not light sampled.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> Vec3 {
+        Vec3::new()
+    }
+
+    // Solid angle density with which scatter picks direction, zero for specular materials
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Scatters and also returns the density of the sample, which is zero when it came
+    // from a specular lobe that light sampling cannot reproduce. Materials that mix
+    // specular and non specular lobes override this to tell the two apart.
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        let (scattered, attenuation) = self.scatter(r_in, rec)?;
+        let pdf = self.pdf(r_in, rec, &scattered.direction());
+        Some((scattered, attenuation, pdf))
+    }
+
+    // Overall color of the surface as seen by denoisers and the albedo AOV. Unlike the
+    // attenuation of a single scatter it does not depend on which lobe was sampled.
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::new()
+    }
+}
+
+// Record with the normal replaced by a perturbed shading normal. The geometric normal
+// is kept if the shading normal faces away from the incoming ray.
+fn shaded_record(r_in: &Ray, rec: &HitRecord, shading_normal: Vec3) -> HitRecord {
+    let shading_normal = match dot(&r_in.direction(), &shading_normal) < 0.0 {
+        true => shading_normal,
+        false => rec.geometric_normal,
+    };
+
+    let mut shaded = rec.clone();
+    shaded.normal = shading_normal;
+    shaded.tangent = unit_vector(rec.tangent - dot(&rec.tangent, &shading_normal) * shading_normal);
+    shaded
+}
+
+// Directions that end up on different sides of the geometric and shading surfaces
+// would leak light through the surface.
+fn leaks(direction: &Vec3, geometric_normal: &Vec3, shading_normal: &Vec3) -> bool {
+    dot(direction, geometric_normal) * dot(direction, shading_normal) <= 0.0
+}
+
+// Same check for the shading normal a primitive interpolated itself, which every
+// material sees. Renderers drop these directions whatever material scattered them.
+pub fn leaks_through_surface(rec: &HitRecord, direction: &Vec3) -> bool {
+    leaks(direction, &rec.geometric_normal, &rec.normal)
+}
+
+// Scatters with the base material using a perturbed shading normal, absorbing the
+// samples that would leak through the surface.
+pub fn scatter_with_shading_normal(
+    base: &dyn Material,
+    r_in: &Ray,
+    rec: &HitRecord,
+    shading_normal: Vec3,
+) -> Option<(Ray, Vec3, f64)> {
+    let shaded = shaded_record(r_in, rec, shading_normal);
+    let (scattered, attenuation, pdf) = base.scatter_with_pdf(r_in, &shaded)?;
+    match leaks(
+        &scattered.direction(),
+        &rec.geometric_normal,
+        &shaded.normal,
+    ) {
+        true => None,
+        false => Some((scattered, attenuation, pdf)),
+    }
+}
+
+pub fn eval_with_shading_normal(
+    base: &dyn Material,
+    r_in: &Ray,
+    rec: &HitRecord,
+    shading_normal: Vec3,
+    direction: &Vec3,
+) -> Vec3 {
+    let shaded = shaded_record(r_in, rec, shading_normal);
+    match leaks(direction, &rec.geometric_normal, &shaded.normal) {
+        true => Vec3::new(),
+        false => base.eval(r_in, &shaded, direction),
+    }
+}
+
+pub fn pdf_with_shading_normal(
+    base: &dyn Material,
+    r_in: &Ray,
+    rec: &HitRecord,
+    shading_normal: Vec3,
+    direction: &Vec3,
+) -> f64 {
+    base.pdf(r_in, &shaded_record(r_in, rec, shading_normal), direction)
 }
@@ -20,6 +20,7 @@ impl Material for Metal {
         let scattered = Ray {
             orig: rec.p,
             dir: (reflected + self.fuzz * Vec3::random_in_unit_sphere()),
+            time: r_in.time(),
         };
         let attenuation = self.albedo;
         match dot(&scattered.direction(), &rec.normal) > 0.0 {
@@ -27,4 +28,8 @@ impl Material for Metal {
             false => None,
         }
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        self.albedo
+    }
 }
@@ -0,0 +1,260 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    textures::{solid_color::SolidColor, texture::Texture},
+    utils::{
+        random_number_utils::random_f64,
+        vec3_utils::{dot, unit_vector},
+    },
+    vectors::{onb::Onb, vec3::Vec3},
+};
+
+use super::material::Material;
+
+use std::f64::consts::PI;
+use std::option::Option;
+use std::sync::Arc;
+
+const MIN_ALPHA: f64 = 1e-3;
+
+// The glTF metallic-roughness BRDF: a GGX specular lobe with Schlick Fresnel over a
+// Lambertian base, where metals tint the specular lobe and lose the diffuse one.
+pub struct MetallicRoughness {
+    pub base_color: Arc<dyn Texture>,
+    // Roughness in the green channel and metalness in the blue one, as in glTF
+    pub metallic_roughness: Arc<dyn Texture>,
+    pub emissive: Arc<dyn Texture>,
+}
+
+struct Lobes {
+    diffuse: Vec3,
+    f0: Vec3,
+    alpha: f64,
+    specular_probability: f64,
+}
+
+impl MetallicRoughness {
+    pub fn new(base_color: Vec3, metallic: f64, roughness: f64) -> Self {
+        MetallicRoughness {
+            base_color: Arc::new(SolidColor::new(base_color)),
+            metallic_roughness: Arc::new(SolidColor::new_with_values(0.0, roughness, metallic)),
+            emissive: Arc::new(SolidColor::new(Vec3::new())),
+        }
+    }
+
+    fn lobes(&self, rec: &HitRecord) -> Lobes {
+        let base_color = self.base_color.value(rec.u, rec.v, &rec.p);
+        let metallic_roughness = self.metallic_roughness.value(rec.u, rec.v, &rec.p);
+        let metallic = metallic_roughness.z().clamp(0.0, 1.0);
+        let roughness = metallic_roughness.y().clamp(0.0, 1.0);
+        let dielectric_f0 = Vec3::new_with_values(0.04, 0.04, 0.04);
+        Lobes {
+            diffuse: (1.0 - metallic) * base_color,
+            f0: (1.0 - metallic) * dielectric_f0 + metallic * base_color,
+            alpha: (roughness * roughness).max(MIN_ALPHA),
+            specular_probability: 0.5 * (1.0 + metallic),
+        }
+    }
+
+    fn ggx(alpha: f64, cos_h: f64) -> f64 {
+        let alpha2 = alpha * alpha;
+        let d = cos_h * cos_h * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * d * d)
+    }
+
+    // Height correlated Smith visibility, which includes the 1 / (4 cos_v cos_l) term
+    fn visibility(alpha: f64, cos_v: f64, cos_l: f64) -> f64 {
+        let alpha2 = alpha * alpha;
+        let v = cos_l * (cos_v * cos_v * (1.0 - alpha2) + alpha2).sqrt();
+        let l = cos_v * (cos_l * cos_l * (1.0 - alpha2) + alpha2).sqrt();
+        0.5 / (v + l)
+    }
+}
+
+impl Material for MetallicRoughness {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        let lobes = self.lobes(rec);
+        let wo = -unit_vector(r_in.direction());
+        let direction = match random_f64() < lobes.specular_probability {
+            true => {
+                // Sample the half vector from the GGX distribution of normals
+                let xi = random_f64();
+                let phi = 2.0 * PI * random_f64();
+                let tan2_theta = lobes.alpha * lobes.alpha * xi / (1.0 - xi);
+                let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let h = Onb::from_w(&rec.normal).to_world(&Vec3::new_with_values(
+                    sin_theta * phi.cos(),
+                    sin_theta * phi.sin(),
+                    cos_theta,
+                ));
+                2.0 * dot(&wo, &h) * h - wo
+            }
+            false => {
+                let direction = rec.normal + Vec3::random_unit_vector();
+                match direction.near_zero() {
+                    true => rec.normal,
+                    false => direction,
+                }
+            }
+        };
+
+        let pdf = self.pdf(r_in, rec, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let scattered = Ray {
+            orig: rec.p,
+            dir: direction,
+            time: r_in.time(),
+        };
+        Some((scattered, self.eval(r_in, rec, &direction) / pdf))
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.emissive.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        let wo = -unit_vector(r_in.direction());
+        let wi = unit_vector(*direction);
+        let cos_v = dot(&rec.normal, &wo);
+        let cos_l = dot(&rec.normal, &wi);
+        if cos_v <= 0.0 || cos_l <= 0.0 {
+            return Vec3::new();
+        }
+
+        let lobes = self.lobes(rec);
+        let h = unit_vector(wo + wi);
+        let schlick = (1.0 - dot(&wo, &h).clamp(0.0, 1.0)).powi(5);
+        let white = Vec3::new_with_values(1.0, 1.0, 1.0);
+        let fresnel = lobes.f0 + schlick * (white - lobes.f0);
+        let specular = Self::ggx(lobes.alpha, dot(&rec.normal, &h))
+            * Self::visibility(lobes.alpha, cos_v, cos_l)
+            * fresnel;
+        let diffuse = (white - fresnel) * lobes.diffuse / PI;
+        (diffuse + specular) * cos_l
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let wo = -unit_vector(r_in.direction());
+        let wi = unit_vector(*direction);
+        let cos_l = dot(&rec.normal, &wi);
+        if cos_l <= 0.0 {
+            return 0.0;
+        }
+
+        let lobes = self.lobes(rec);
+        let h = unit_vector(wo + wi);
+        let cos_h = dot(&rec.normal, &h);
+        let specular = match cos_h > 0.0 {
+            true => Self::ggx(lobes.alpha, cos_h) * cos_h / (4.0 * dot(&wo, &h).abs()),
+            false => 0.0,
+        };
+        lobes.specular_probability * specular + (1.0 - lobes.specular_probability) * cos_l / PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base_color.value(rec.u, rec.v, &rec.p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::vec3_utils::reflect;
+
+    fn hit(material: Arc<dyn Material>) -> HitRecord {
+        HitRecord {
+            p: Vec3::new(),
+            normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            geometric_normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face: true,
+            mat_ptr: material,
+            color: None,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    fn incoming(direction: Vec3) -> Ray {
+        Ray {
+            orig: direction,
+            dir: -direction,
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_sampling_matches_pdf() {
+        let r_in = incoming(unit_vector(Vec3::new_with_values(0.4, 0.1, 1.0)));
+        for (metallic, roughness) in [(0.0, 0.8), (1.0, 0.3), (0.5, 0.05)] {
+            let material = Arc::new(MetallicRoughness::new(
+                Vec3::new_with_values(0.9, 0.6, 0.3),
+                metallic,
+                roughness,
+            ));
+            let rec = hit(material.clone());
+
+            // Importance sampled estimate of the albedo agrees with uniform sampling,
+            // and stays below one
+            let samples = 200000;
+            let mut sampled = Vec3::new();
+            let mut uniform = Vec3::new();
+            for _ in 0..samples {
+                if let Some((_, attenuation)) = material.scatter(&r_in, &rec) {
+                    sampled += attenuation;
+                }
+                let direction = Vec3::random_unit_vector();
+                uniform += material.eval(&r_in, &rec, &direction) * 4.0 * PI;
+            }
+            let sampled = sampled / samples as f64;
+            let uniform = uniform / samples as f64;
+            assert!(sampled.x() <= 1.0);
+            if roughness > 0.1 {
+                assert!(
+                    (sampled - uniform).length() < 0.05,
+                    "{:?} {:?}",
+                    sampled,
+                    uniform
+                );
+            }
+
+            for _ in 0..100 {
+                if let Some((scattered, attenuation)) = material.scatter(&r_in, &rec) {
+                    let direction = scattered.direction();
+                    let expected = material.eval(&r_in, &rec, &direction)
+                        / material.pdf(&r_in, &rec, &direction);
+                    assert!((attenuation - expected).length() < 1e-9 * expected.length().max(1.0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_smooth_metal_is_a_mirror() {
+        let material = Arc::new(MetallicRoughness::new(
+            Vec3::new_with_values(1.0, 0.8, 0.4),
+            1.0,
+            0.0,
+        ));
+        let rec = hit(material.clone());
+        let r_in = incoming(unit_vector(Vec3::new_with_values(0.3, 0.0, 1.0)));
+        let mirror = reflect(&r_in.direction(), &rec.normal);
+        // GGX keeps long tails even at the smallest roughness, so a few samples may stray
+        let mut mirrored = 0;
+        for _ in 0..100 {
+            let (scattered, attenuation) = material.scatter(&r_in, &rec).unwrap();
+            if dot(&unit_vector(scattered.direction()), &mirror) > 0.999 {
+                mirrored += 1;
+            }
+            // Gold colored metal reflects less blue
+            assert!(attenuation.z() < attenuation.x());
+        }
+        assert!(mirrored >= 95);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    textures::{solid_color::SolidColor, texture::Texture},
+    utils::random_number_utils::random_f64,
+    vectors::vec3::Vec3,
+};
+
+use super::material::Material;
+
+use std::option::Option;
+use std::sync::Arc;
+
+pub struct Mix {
+    pub first: Arc<dyn Material>,
+    pub second: Arc<dyn Material>,
+    pub weight: Arc<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(first: Arc<dyn Material>, second: Arc<dyn Material>, weight: f64) -> Self {
+        Mix {
+            first,
+            second,
+            weight: Arc::new(SolidColor::new_with_values(weight, weight, weight)),
+        }
+    }
+
+    pub fn with_texture(
+        first: Arc<dyn Material>,
+        second: Arc<dyn Material>,
+        weight: Arc<dyn Texture>,
+    ) -> Self {
+        Mix {
+            first,
+            second,
+            weight,
+        }
+    }
+
+    pub fn weight_at(&self, rec: &HitRecord) -> f64 {
+        let value = self.weight.value(rec.u, rec.v, &rec.p);
+        ((value.x() + value.y() + value.z()) / 3.0).clamp(0.0, 1.0)
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        match random_f64() < self.weight_at(rec) {
+            true => self.second.scatter(r_in, rec),
+            false => self.first.scatter(r_in, rec),
+        }
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        let weight = self.weight_at(rec);
+        (1.0 - weight) * self.first.emitted(r_in, rec) + weight * self.second.emitted(r_in, rec)
+    }
+
+    // Picks a child the same way scatter does, so a cutout mixed with a solid material
+    // lets through the expected fraction of rays.
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        match random_f64() < self.weight_at(rec) {
+            true => self.second.opaque(rec),
+            false => self.first.opaque(rec),
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        let weight = self.weight_at(rec);
+        (1.0 - weight) * self.first.eval(r_in, rec, direction)
+            + weight * self.second.eval(r_in, rec, direction)
+    }
+
+    // Specular children contribute nothing here, so light sampling only covers the
+    // lobes of the other child, weighted by how often it is picked.
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let weight = self.weight_at(rec);
+        (1.0 - weight) * self.first.pdf(r_in, rec, direction)
+            + weight * self.second.pdf(r_in, rec, direction)
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        let (scattered, attenuation, pdf) = match random_f64() < self.weight_at(rec) {
+            true => self.second.scatter_with_pdf(r_in, rec)?,
+            false => self.first.scatter_with_pdf(r_in, rec)?,
+        };
+        // Either child could have picked a non specular direction
+        let pdf = match pdf > 0.0 {
+            true => self.pdf(r_in, rec, &scattered.direction()),
+            false => 0.0,
+        };
+        Some((scattered, attenuation, pdf))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        let weight = self.weight_at(rec);
+        (1.0 - weight) * self.first.albedo(rec) + weight * self.second.albedo(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ConstantMaterial {
+        attenuation: Vec3,
+    }
+
+    struct GlowingCutout {
+        emission: Vec3,
+    }
+
+    impl Material for GlowingCutout {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            None
+        }
+
+        fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Vec3 {
+            self.emission
+        }
+
+        fn opaque(&self, _rec: &HitRecord) -> bool {
+            false
+        }
+    }
+
+    impl Material for ConstantMaterial {
+        fn scatter(&self, r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            Some((
+                Ray {
+                    orig: r_in.origin(),
+                    dir: r_in.direction(),
+                    time: 0.0,
+                },
+                self.attenuation,
+            ))
+        }
+    }
+
+    fn test_record(material: Arc<dyn Material>) -> HitRecord {
+        HitRecord {
+            p: Vec3::new(),
+            normal: Vec3::new_with_values(0.0, 1.0, 0.0),
+            geometric_normal: Vec3::new_with_values(0.0, 1.0, 0.0),
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face: true,
+            mat_ptr: material,
+            color: None,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    fn mixed(weight: f64) -> Arc<dyn Material> {
+        Arc::new(Mix::new(
+            Arc::new(ConstantMaterial {
+                attenuation: Vec3::new_with_values(1.0, 0.0, 0.0),
+            }),
+            Arc::new(ConstantMaterial {
+                attenuation: Vec3::new_with_values(0.0, 1.0, 0.0),
+            }),
+            weight,
+        ))
+    }
+
+    #[test]
+    fn test_mix_extreme_weights() {
+        let ray = Ray {
+            orig: Vec3::new(),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        for (weight, expected) in [
+            (0.0, Vec3::new_with_values(1.0, 0.0, 0.0)),
+            (1.0, Vec3::new_with_values(0.0, 1.0, 0.0)),
+        ] {
+            let material = mixed(weight);
+            let rec = test_record(material.clone());
+            for _ in 0..32 {
+                let (_, attenuation) = material.scatter(&ray, &rec).unwrap();
+                assert_eq!(attenuation, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mix_average() {
+        let ray = Ray {
+            orig: Vec3::new(),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let material = mixed(0.25);
+        let rec = test_record(material.clone());
+        let samples = 20000;
+        let mut total = Vec3::new();
+        for _ in 0..samples {
+            total += material.scatter(&ray, &rec).unwrap().1;
+        }
+        let average = total / samples as f64;
+        assert!((average.x() - 0.75).abs() < 0.03);
+        assert!((average.y() - 0.25).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_mix_emitted_and_opaque() {
+        let ray = Ray {
+            orig: Vec3::new(),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let material = Mix::new(
+            Arc::new(ConstantMaterial {
+                attenuation: Vec3::new_with_values(1.0, 1.0, 1.0),
+            }),
+            Arc::new(GlowingCutout {
+                emission: Vec3::new_with_values(4.0, 2.0, 0.0),
+            }),
+            0.25,
+        );
+        let rec = test_record(Arc::new(ConstantMaterial {
+            attenuation: Vec3::new(),
+        }));
+        assert_eq!(
+            material.emitted(&ray, &rec),
+            Vec3::new_with_values(1.0, 0.5, 0.0)
+        );
+
+        let samples = 20000;
+        let opaque = (0..samples).filter(|_| material.opaque(&rec)).count();
+        assert!((opaque as f64 / samples as f64 - 0.75).abs() < 0.02);
+    }
+}
@@ -1,4 +1,14 @@
+pub mod alpha_mask;
+pub mod bump_map;
 pub mod dielectric;
+pub mod diffuse_light;
+pub mod hair;
 pub mod lambertian;
+pub mod layered;
 pub mod material;
 pub mod metal;
+pub mod metallic_roughness;
+pub mod mix;
+pub mod normal_map;
+pub mod subsurface;
+pub mod vertex_color;
@@ -0,0 +1,166 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    textures::texture::Texture,
+    utils::vec3_utils::{cross, unit_vector},
+    vectors::vec3::Vec3,
+};
+
+use super::material::{
+    eval_with_shading_normal, pdf_with_shading_normal, scatter_with_shading_normal, Material,
+};
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Tangent space normal map, with the usual encoding of the normal as 0.5 * (n + 1)
+pub struct NormalMap {
+    pub base: Arc<dyn Material>,
+    pub map: Arc<dyn Texture>,
+    pub strength: f64,
+}
+
+impl NormalMap {
+    pub fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let encoded = self.map.value(rec.u, rec.v, &rec.p);
+        let x = self.strength * (2.0 * encoded.x() - 1.0);
+        let y = self.strength * (2.0 * encoded.y() - 1.0);
+        let z = 2.0 * encoded.z() - 1.0;
+
+        // The tangent frame is defined on the outside of the surface
+        let normal = match rec.front_face {
+            true => rec.normal,
+            false => -rec.normal,
+        };
+        let bitangent = cross(&normal, &rec.tangent);
+        let perturbed = unit_vector(x * rec.tangent + y * bitangent + z * normal);
+        match rec.front_face {
+            true => perturbed,
+            false => -perturbed,
+        }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        scatter_with_shading_normal(self.base.as_ref(), r_in, rec, self.shading_normal(rec))
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        scatter_with_shading_normal(self.base.as_ref(), r_in, rec, self.shading_normal(rec))
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        self.base.opaque(rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        let shading_normal = self.shading_normal(rec);
+        eval_with_shading_normal(self.base.as_ref(), r_in, rec, shading_normal, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        let shading_normal = self.shading_normal(rec);
+        pdf_with_shading_normal(self.base.as_ref(), r_in, rec, shading_normal, direction)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base.albedo(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textures::solid_color::SolidColor;
+    use crate::utils::vec3_utils::dot;
+
+    struct MirrorMaterial;
+
+    impl Material for MirrorMaterial {
+        fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+            let d = r_in.direction();
+            Some((
+                Ray {
+                    orig: rec.p,
+                    dir: d - 2.0 * dot(&d, &rec.normal) * rec.normal,
+                    time: 0.0,
+                },
+                Vec3::new_with_values(1.0, 1.0, 1.0),
+            ))
+        }
+    }
+
+    fn record(front_face: bool) -> HitRecord {
+        let outward = Vec3::new_with_values(0.0, 0.0, 1.0);
+        HitRecord {
+            p: Vec3::new(),
+            normal: match front_face {
+                true => outward,
+                false => -outward,
+            },
+            geometric_normal: match front_face {
+                true => outward,
+                false => -outward,
+            },
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face,
+            mat_ptr: Arc::new(MirrorMaterial),
+            color: None,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    fn normal_map(r: f64, g: f64, b: f64) -> NormalMap {
+        NormalMap {
+            base: Arc::new(MirrorMaterial),
+            map: Arc::new(SolidColor::new_with_values(r, g, b)),
+            strength: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_flat_normal_map_keeps_normal() {
+        let material = normal_map(0.5, 0.5, 1.0);
+        for front_face in [true, false] {
+            let rec = record(front_face);
+            assert!((material.shading_normal(&rec) - rec.normal).near_zero());
+        }
+    }
+
+    #[test]
+    fn test_normal_map_tilts_towards_tangent() {
+        let material = normal_map(1.0, 0.5, 1.0);
+        let n = material.shading_normal(&record(true));
+        assert!(n.x() > 0.7 && n.z() > 0.7);
+    }
+
+    #[test]
+    fn test_normal_map_does_not_leak() {
+        // Mirroring about the tilted normal would send this ray below the surface
+        let material = normal_map(1.0, 0.5, 1.0);
+        let ray = Ray {
+            orig: Vec3::new_with_values(-0.5, 0.0, 1.0),
+            dir: Vec3::new_with_values(0.5, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(material.scatter(&ray, &record(true)).is_none());
+
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.5, 0.0, 1.0),
+            dir: Vec3::new_with_values(-0.5, 0.0, -1.0),
+            time: 0.0,
+        };
+        let (scattered, _) = material.scatter(&ray, &record(true)).unwrap();
+        assert!(scattered.direction().z() > 0.0);
+    }
+}
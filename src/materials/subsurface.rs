@@ -0,0 +1,190 @@
+use crate::{
+    hittables::hittable::HitRecord,
+    rays::ray::Ray,
+    utils::{
+        random_number_utils::random_f64,
+        vec3_utils::{dot, min, reflect, refract, unit_vector},
+    },
+    vectors::vec3::Vec3,
+};
+
+use super::{dielectric::Dielectric, material::Material};
+
+use std::f64::consts::PI;
+use std::option::Option;
+
+// Random walk subsurface scattering. Rays refract into the object and then perform an
+// isotropic random walk through a homogeneous medium until they leave through the
+// boundary again. The object must be closed and must not contain other geometry.
+// Walks leave the object diffusely rather than refracting out, which lets the exit
+// points be light sampled, so point and directional lights show through the medium.
+pub struct Subsurface {
+    pub albedo: Vec3,
+    pub mean_free_path: f64,
+    pub ir: f64,
+}
+
+impl Subsurface {
+    fn enter(&self, r_in: &Ray, rec: &HitRecord) -> Ray {
+        let refraction_ratio = 1.0 / self.ir;
+        let unit_direction = unit_vector(r_in.direction());
+        let cos_theta = min(dot(&-unit_direction, &rec.normal), 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let coat = Dielectric { ir: self.ir };
+
+        let direction =
+            if cannot_refract || coat.reflectance(cos_theta, refraction_ratio) > random_f64() {
+                reflect(&unit_direction, &rec.normal)
+            } else {
+                refract(&unit_direction, &rec.normal, refraction_ratio)
+            };
+        Ray {
+            orig: rec.p,
+            dir: direction,
+            time: r_in.time(),
+        }
+    }
+
+    // Chance that the walk reaches the boundary hit without scattering on the way
+    fn exit_probability(&self, r_in: &Ray, rec: &HitRecord) -> f64 {
+        (-rec.t * r_in.direction().length() / self.mean_free_path).exp()
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        self.scatter_with_pdf(r_in, rec)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    // Leaving the medium at a boundary hit from inside, with a cosine lobe about the
+    // outward normal
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        self.pdf(r_in, rec, direction) * Vec3::new_with_values(1.0, 1.0, 1.0)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        if rec.front_face {
+            return 0.0;
+        }
+        let cosine = dot(&-rec.normal, &unit_vector(*direction));
+        match cosine > 0.0 {
+            true => self.exit_probability(r_in, rec) * cosine / PI,
+            false => 0.0,
+        }
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        let white = Vec3::new_with_values(1.0, 1.0, 1.0);
+        if rec.front_face {
+            return Some((self.enter(r_in, rec), white, 0.0));
+        }
+
+        // The ray travelled inside the medium up to the boundary hit, so sample a free
+        // flight distance and scatter inside if it ends before the boundary.
+        let direction_length = r_in.direction().length();
+        let free_flight = -(1.0 - random_f64()).ln() * self.mean_free_path;
+        if free_flight < rec.t * direction_length {
+            let scattered = Ray {
+                orig: r_in.at(free_flight / direction_length),
+                dir: Vec3::random_unit_vector(),
+                time: r_in.time(),
+            };
+            return Some((scattered, self.albedo, 0.0));
+        }
+
+        let mut direction = -rec.normal + Vec3::random_unit_vector();
+        if direction.near_zero() {
+            direction = -rec.normal;
+        }
+        let scattered = Ray {
+            orig: rec.p,
+            dir: direction,
+            time: r_in.time(),
+        };
+        let pdf = self.pdf(r_in, rec, &direction);
+        Some((scattered, white, pdf))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        self.albedo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::{hittable::Hittable, sphere::Sphere};
+    use std::sync::Arc;
+
+    const IMAGE_SIZE: u32 = 16;
+    const SAMPLES_PER_PIXEL: u32 = 64;
+
+    // Renders the unit sphere framed with a margin under a uniform white environment.
+    fn render_sphere(material: Arc<dyn Material>) -> Vec<f64> {
+        let sphere = Sphere::new(Vec3::new(), 1.0, material);
+        let mut pixels = vec![];
+        for y in 0..IMAGE_SIZE {
+            for x in 0..IMAGE_SIZE {
+                let mut total = Vec3::new();
+                for _ in 0..SAMPLES_PER_PIXEL {
+                    let u = (x as f64 + random_f64()) / IMAGE_SIZE as f64;
+                    let v = (y as f64 + random_f64()) / IMAGE_SIZE as f64;
+                    let ray = Ray {
+                        orig: Vec3::new_with_values(2.4 * u - 1.2, 2.4 * v - 1.2, 5.0),
+                        dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+                        time: 0.0,
+                    };
+                    total += trace(&ray, &sphere, 1000);
+                }
+                pixels.push(total.x() / SAMPLES_PER_PIXEL as f64);
+            }
+        }
+        pixels
+    }
+
+    fn trace(r: &Ray, world: &dyn Hittable, depth: i32) -> Vec3 {
+        if depth <= 0 {
+            return Vec3::new();
+        }
+        match world.hit(r, 0.0001, f64::INFINITY) {
+            None => Vec3::new_with_values(1.0, 1.0, 1.0),
+            Some(rec) => match rec.mat_ptr.scatter(r, &rec) {
+                None => Vec3::new(),
+                Some((scattered, attenuation)) => attenuation * trace(&scattered, world, depth - 1),
+            },
+        }
+    }
+
+    fn mean(pixels: &[f64]) -> f64 {
+        pixels.iter().sum::<f64>() / pixels.len() as f64
+    }
+
+    #[test]
+    fn test_non_absorbing_sphere_matches_environment() {
+        let material = Arc::new(Subsurface {
+            albedo: Vec3::new_with_values(1.0, 1.0, 1.0),
+            mean_free_path: 0.2,
+            ir: 1.3,
+        });
+        let pixels = render_sphere(material);
+        assert!((mean(&pixels) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_absorbing_sphere_is_darker_at_centre() {
+        let material = Arc::new(Subsurface {
+            albedo: Vec3::new_with_values(0.8, 0.8, 0.8),
+            mean_free_path: 0.1,
+            ir: 1.3,
+        });
+        let pixels = render_sphere(material);
+        let centre = IMAGE_SIZE / 2;
+        let centre_pixel = pixels[(centre * IMAGE_SIZE + centre) as usize];
+        let corner_pixel = pixels[0];
+        assert!(mean(&pixels) < 0.9);
+        assert!(centre_pixel > 0.0 && centre_pixel < 0.9);
+        assert_eq!(corner_pixel, 1.0);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::hittables::hittable::HitRecord;
+use crate::rays::ray::Ray;
+use crate::vectors::vec3::Vec3;
+
+use super::material::Material;
+
+use std::option::Option;
+use std::sync::Arc;
+
+// Tints the base material by the vertex color of the hit, as read from scanned meshes.
+// Surfaces without vertex colors keep the base material's own color.
+pub struct VertexColor {
+    pub base: Arc<dyn Material>,
+}
+
+impl VertexColor {
+    fn tint(rec: &HitRecord) -> Vec3 {
+        rec.color
+            .unwrap_or_else(|| Vec3::new_with_values(1.0, 1.0, 1.0))
+    }
+}
+
+impl Material for VertexColor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+        let (scattered, attenuation) = self.base.scatter(r_in, rec)?;
+        Some((scattered, attenuation * VertexColor::tint(rec)))
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        self.base.opaque(rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Vec3 {
+        self.base.eval(r_in, rec, direction) * VertexColor::tint(rec)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+        self.base.pdf(r_in, rec, direction)
+    }
+
+    fn scatter_with_pdf(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3, f64)> {
+        let (scattered, attenuation, pdf) = self.base.scatter_with_pdf(r_in, rec)?;
+        Some((scattered, attenuation * VertexColor::tint(rec), pdf))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.base.albedo(rec) * VertexColor::tint(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+
+    fn record(color: Option<Vec3>) -> HitRecord {
+        HitRecord {
+            p: Vec3::new(),
+            normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            geometric_normal: Vec3::new_with_values(0.0, 0.0, 1.0),
+            tangent: Vec3::new_with_values(1.0, 0.0, 0.0),
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            mat_ptr: Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+            }),
+            color,
+            object_id: 0,
+            material_id: None,
+        }
+    }
+
+    #[test]
+    fn test_vertex_color_tints_base() {
+        let material = VertexColor {
+            base: Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+            }),
+        };
+        let r_in = Ray {
+            orig: Vec3::new_with_values(0.0, 0.0, 1.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let direction = Vec3::new_with_values(0.0, 0.0, 1.0);
+
+        let colored = record(Some(Vec3::new_with_values(1.0, 0.5, 0.0)));
+        let (_, attenuation) = material.scatter(&r_in, &colored).unwrap();
+        assert_eq!(attenuation, Vec3::new_with_values(0.5, 0.25, 0.0));
+        let f = material.eval(&r_in, &colored, &direction);
+        assert!(f.x() > 0.0 && (f.y() - 0.5 * f.x()).abs() < 1e-12 && f.z() == 0.0);
+
+        let plain = record(None);
+        let (_, attenuation) = material.scatter(&r_in, &plain).unwrap();
+        assert_eq!(attenuation, Vec3::new_with_values(0.5, 0.5, 0.5));
+    }
+}
@@ -3,6 +3,8 @@ use crate::vectors::vec3::Vec3;
 pub struct Ray {
     pub orig: Vec3,
     pub dir: Vec3,
+    // Moment within the shutter interval the ray was sent at, in seconds
+    pub time: f64,
 }
 
 impl Ray {
@@ -14,6 +16,10 @@ impl Ray {
         self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
     pub fn at(&self, t: f64) -> Vec3 {
         self.orig + t * self.dir
     }
@@ -27,6 +33,7 @@ mod tests {
         let ray = Ray {
             orig: Vec3::new_with_values(0f64, 0f64, 0f64),
             dir: Vec3::new_with_values(1f64, 0f64, 0f64),
+            time: 0.0,
         };
         assert_eq!(ray.origin(), Vec3::new());
     }
@@ -36,6 +43,7 @@ mod tests {
         let ray = Ray {
             orig: Vec3::new_with_values(0f64, 0f64, 0f64),
             dir: Vec3::new_with_values(1f64, 0f64, 0f64),
+            time: 0.0,
         };
         assert_eq!(ray.direction(), Vec3::new_with_values(1f64, 0f64, 0f64));
     }
@@ -45,6 +53,7 @@ mod tests {
         let ray = Ray {
             orig: Vec3::new_with_values(0f64, 0f64, 0f64),
             dir: Vec3::new_with_values(1f64, 0f64, 0f64),
+            time: 0.0,
         };
         assert_eq!(ray.at(2f64), Vec3::new_with_values(2f64, 0f64, 0f64));
     }
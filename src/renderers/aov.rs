@@ -0,0 +1,101 @@
+use crate::vectors::vec3::Vec3;
+
+// Arbitrary output variables written next to the beauty image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Depth,
+    Position,
+    Normal,
+    Albedo,
+    MaterialId,
+    ObjectId,
+    Direct,
+    Indirect,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth,
+        Aov::Position,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::MaterialId,
+        Aov::ObjectId,
+        Aov::Direct,
+        Aov::Indirect,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+        }
+    }
+
+    // Channel names within the layer. Scalar layers only use the first component.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Position | Aov::Normal => &["X", "Y", "Z"],
+            Aov::MaterialId | Aov::ObjectId => &["id"],
+            Aov::Albedo | Aov::Direct | Aov::Indirect => &["R", "G", "B"],
+        }
+    }
+}
+
+// What a single camera sample saw at its first hit, along with its lighting split into
+// light that reached the camera after at most one bounce and everything else.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AovSample {
+    // Distance from the ray origin, infinite when the ray escaped
+    pub depth: f64,
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub albedo: Vec3,
+    // Index of the material in the scene's registry, None for unregistered materials
+    pub material: Option<usize>,
+    pub object: Option<usize>,
+    pub direct: Vec3,
+    pub indirect: Vec3,
+}
+
+impl AovSample {
+    // Sample of a ray that never left the camera or escaped without lighting
+    pub fn new() -> Self {
+        AovSample {
+            depth: f64::INFINITY,
+            position: Vec3::new(),
+            normal: Vec3::new(),
+            albedo: Vec3::new(),
+            material: None,
+            object: None,
+            direct: Vec3::new(),
+            indirect: Vec3::new(),
+        }
+    }
+
+    pub fn beauty(&self) -> Vec3 {
+        self.direct + self.indirect
+    }
+
+    // Same sample with the lighting passes scaled, such as by the camera exposure
+    pub fn scaled(&self, scale: f64) -> Self {
+        AovSample {
+            direct: scale * self.direct,
+            indirect: scale * self.indirect,
+            ..*self
+        }
+    }
+}
+
+impl Default for AovSample {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,2 @@
+pub mod aov;
+pub mod renderer;
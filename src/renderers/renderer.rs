@@ -0,0 +1,456 @@
+use crate::hittables::hittable::{HitRecord, Hittable};
+use crate::materials::material::leaks_through_surface;
+use crate::rays::ray::Ray;
+use crate::renderers::aov::AovSample;
+use crate::scenes::scene::Scene;
+use crate::utils::vec3_utils::unit_vector;
+use crate::vectors::vec3::Vec3;
+
+const SHADOW_EPSILON: f64 = 0.001;
+
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let pdf2 = pdf * pdf;
+    let other_pdf2 = other_pdf * other_pdf;
+    match pdf2 + other_pdf2 > 0.0 {
+        true => pdf2 / (pdf2 + other_pdf2),
+        false => 0.0,
+    }
+}
+
+// Light sampling through the non specular lobes of the hit material, weighted against
+// the BSDF samples that could have found the same lights.
+fn sample_lights(r: &Ray, rec: &HitRecord, scene: &Scene) -> Vec3 {
+    let mut direct = Vec3::new();
+    for light in &scene.lights {
+        let sample = match light.sample(&rec.p) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        if leaks_through_surface(rec, &sample.direction) {
+            continue;
+        }
+        let f = rec.mat_ptr.eval(r, rec, &sample.direction);
+        if f.near_zero() || sample.pdf <= 0.0 {
+            continue;
+        }
+
+        let shadow_ray = Ray {
+            orig: rec.p,
+            dir: sample.direction,
+            time: r.time(),
+        };
+        if scene
+            .world
+            .hit(
+                &shadow_ray,
+                SHADOW_EPSILON,
+                sample.distance - SHADOW_EPSILON,
+            )
+            .is_some()
+        {
+            continue;
+        }
+
+        let weight = match sample.delta {
+            true => 1.0,
+            false => power_heuristic(sample.pdf, rec.mat_ptr.pdf(r, rec, &sample.direction)),
+        };
+        direct += weight / sample.pdf * f * sample.radiance;
+    }
+    direct
+}
+
+pub fn ray_color(r: &Ray, scene: &Scene, depth: i32) -> Vec3 {
+    ray_color_with_aovs(r, scene, depth).beauty()
+}
+
+// Light reaching the camera after at most one bounce counts as direct
+fn pass(bounces: i32) -> usize {
+    match bounces > 1 {
+        true => 1,
+        false => 0,
+    }
+}
+
+// Traces a camera ray like ray_color, also recording what it saw at its first hit
+pub fn ray_color_with_aovs(r: &Ray, scene: &Scene, depth: i32) -> AovSample {
+    let mut sample = AovSample::new();
+    let mut lighting = [Vec3::new(), Vec3::new()];
+    let mut throughput = Vec3::new_with_values(1.0, 1.0, 1.0);
+    let mut ray = Ray {
+        orig: r.origin(),
+        dir: r.direction(),
+        time: r.time(),
+    };
+    // Density of the BSDF sample that produced the current ray, zero for camera rays
+    // and specular bounces which light sampling cannot reproduce.
+    let mut scatter_pdf = 0.0;
+
+    for bounces in 0..depth {
+        let rec = match scene.hit(&ray, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => {
+                let direction = unit_vector(ray.direction());
+                for light in scene.lights.iter().filter(|light| light.is_infinite()) {
+                    let radiance = light.escaped_radiance(&direction);
+                    if radiance.near_zero() {
+                        continue;
+                    }
+                    let weight = match scatter_pdf > 0.0 {
+                        true => power_heuristic(scatter_pdf, light.pdf(&ray.origin(), &direction)),
+                        false => 1.0,
+                    };
+                    lighting[pass(bounces)] += weight * throughput * radiance;
+                }
+                break;
+            }
+        };
+
+        if bounces == 0 {
+            sample.depth = (rec.p - ray.origin()).length();
+            sample.position = rec.p;
+            sample.normal = rec.normal;
+            sample.material = rec.material_id;
+            sample.albedo = rec.mat_ptr.albedo(&rec);
+            sample.object = Some(rec.object_id);
+        }
+
+        let emitted = rec.mat_ptr.emitted(&ray, &rec);
+        if !emitted.near_zero() {
+            let weight = match scatter_pdf > 0.0 {
+                true => {
+                    let direction = unit_vector(ray.direction());
+                    let light_pdf = scene
+                        .lights
+                        .iter()
+                        .filter(|light| !light.is_infinite())
+                        .map(|light| light.pdf(&ray.origin(), &direction))
+                        .sum();
+                    power_heuristic(scatter_pdf, light_pdf)
+                }
+                false => 1.0,
+            };
+            lighting[pass(bounces)] += weight * throughput * emitted;
+        }
+
+        let (scattered, attenuation, pdf) = match rec.mat_ptr.scatter_with_pdf(&ray, &rec) {
+            Some(scattered) => scattered,
+            None => break,
+        };
+        if leaks_through_surface(&rec, &scattered.direction()) {
+            break;
+        }
+        // Lights are sampled even after a specular bounce, as the material may also have
+        // non specular lobes that eval picks up.
+        lighting[pass(bounces + 1)] += throughput * sample_lights(&ray, &rec, scene);
+        scatter_pdf = pdf;
+
+        throughput = throughput * attenuation;
+        ray = scattered;
+    }
+
+    sample.direct = lighting[0];
+    sample.indirect = lighting[1];
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::{
+        hittable_list::HittableList, quad::Quad, sphere::Sphere, triangle_mesh::TriangleMesh,
+    };
+    use crate::lights::{
+        directional_light::DirectionalLight, environment_map::EnvironmentMap, light::Light,
+        point_light::PointLight,
+    };
+    use crate::materials::{
+        dielectric::Dielectric, diffuse_light::DiffuseLight, lambertian::Lambertian,
+        layered::Layered, material::Material, metal::Metal, mix::Mix,
+    };
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    fn furnace(albedo: f64, environment_width: usize) -> Scene {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Vec3::new(),
+            1.0,
+            Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(albedo, albedo, albedo),
+            }),
+        )));
+        let mut scene = Scene::new(world);
+        let pixels = vec![Vec3::new_with_values(1.0, 1.0, 1.0); environment_width * 4];
+        scene.add_light(Arc::new(
+            EnvironmentMap::new(environment_width, 4, pixels, 0.0, 1.0).unwrap(),
+        ));
+        scene
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // A convex diffuse object under a uniform environment reflects exactly its albedo
+        let scene = furnace(0.5, 8);
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.2, 0.3, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let samples = 20000;
+        let mut total = Vec3::new();
+        for _ in 0..samples {
+            total += ray_color(&ray, &scene, 10);
+        }
+        let average = total / samples as f64;
+        assert!((average.x() - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_escaped_camera_ray_sees_environment() {
+        let scene = furnace(0.5, 8);
+        let ray = Ray {
+            orig: Vec3::new_with_values(0.0, 0.0, 5.0),
+            dir: Vec3::new_with_values(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert_eq!(
+            ray_color(&ray, &scene, 10),
+            Vec3::new_with_values(1.0, 1.0, 1.0)
+        );
+    }
+
+    fn floor_with_light(light: Arc<dyn Light>) -> Scene {
+        let material = Arc::new(Lambertian {
+            albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+        });
+        material_floor_with_light(material, light)
+    }
+
+    fn material_floor_with_light(material: Arc<dyn Material>, light: Arc<dyn Light>) -> Scene {
+        let mut scene = Scene::new(HittableList::new());
+        let material = scene.add_material(material);
+        scene.world.add(Box::new(TriangleMesh::new(
+            vec![
+                Vec3::new_with_values(-10.0, 0.0, -10.0),
+                Vec3::new_with_values(10.0, 0.0, -10.0),
+                Vec3::new_with_values(10.0, 0.0, 10.0),
+                Vec3::new_with_values(-10.0, 0.0, 10.0),
+            ],
+            vec![[0, 2, 1], [0, 3, 2]],
+            None,
+            None,
+            material,
+        )));
+        scene.add_light(light);
+        scene
+    }
+
+    fn looking_down_at(x: f64) -> Ray {
+        Ray {
+            orig: Vec3::new_with_values(x, 1.0, 0.0),
+            dir: Vec3::new_with_values(0.0, -1.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_point_light_on_floor() {
+        let scene = floor_with_light(Arc::new(PointLight::new(
+            Vec3::new_with_values(0.0, 2.0, 0.0),
+            Vec3::new_with_values(4.0, 4.0, 4.0),
+        )));
+        let color = ray_color(&looking_down_at(0.0), &scene, 10);
+        assert!((color.x() - 0.5 / PI).abs() < 1e-12);
+
+        // Off to the side the light arrives at an angle and from further away
+        let color = ray_color(&looking_down_at(2.0), &scene, 10);
+        let cosine = 1.0 / 2.0f64.sqrt();
+        assert!((color.x() - 0.5 / PI * 4.0 * cosine / 8.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_delta_lights_are_shadowed() {
+        let mut scene = floor_with_light(Arc::new(DirectionalLight::new(
+            Vec3::new_with_values(0.0, -1.0, 0.0),
+            Vec3::new_with_values(1.0, 1.0, 1.0),
+        )));
+        let lit = ray_color(&looking_down_at(0.0), &scene, 10);
+        assert!((lit.x() - 0.5 / PI).abs() < 1e-12);
+
+        scene.world.add(Box::new(Sphere::new(
+            Vec3::new_with_values(0.0, 3.0, 0.0),
+            0.5,
+            Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+            }),
+        )));
+        assert_eq!(ray_color(&looking_down_at(0.0), &scene, 1), Vec3::new());
+    }
+
+    #[test]
+    fn test_point_light_on_partly_specular_floors() {
+        // Only the diffuse part of these materials sees the light, but it must see it
+        let light = || -> Arc<dyn Light> {
+            Arc::new(PointLight::new(
+                Vec3::new_with_values(0.0, 2.0, 0.0),
+                Vec3::new_with_values(4.0, 4.0, 4.0),
+            ))
+        };
+        let diffuse = || -> Arc<dyn Material> {
+            Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+            })
+        };
+
+        // The coat reflects four percent at normal incidence and passes on the rest
+        let layered = Arc::new(Layered::new(1.5, diffuse()));
+        let scene = material_floor_with_light(layered, light());
+        let color = ray_color(&looking_down_at(0.0), &scene, 1);
+        assert!((color.x() - 0.96 * 0.5 / PI).abs() < 1e-12);
+
+        let mirror = Arc::new(Metal {
+            albedo: Vec3::new_with_values(1.0, 1.0, 1.0),
+            fuzz: 0.0,
+        });
+        let mix = Arc::new(Mix::new(mirror, diffuse(), 0.5));
+        let scene = material_floor_with_light(mix, light());
+        let color = ray_color(&looking_down_at(0.0), &scene, 1);
+        assert!((color.x() - 0.5 * 0.5 / PI).abs() < 1e-12);
+    }
+
+    fn floor_under_quad_light(register_light: bool) -> Scene {
+        let mut world = HittableList::new();
+        world.add(Box::new(Quad::new(
+            Vec3::new_with_values(-10.0, 0.0, 10.0),
+            Vec3::new_with_values(20.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 0.0, -20.0),
+            Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(0.5, 0.5, 0.5),
+            }),
+        )));
+        let mut scene = Scene::new(world);
+        let light = Arc::new(Quad::new(
+            Vec3::new_with_values(-0.5, 2.0, -0.5),
+            Vec3::new_with_values(1.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 0.0, 1.0),
+            Arc::new(DiffuseLight::new(Vec3::new_with_values(4.0, 4.0, 4.0))),
+        ));
+        match register_light {
+            true => scene.add_area_light(light),
+            false => scene.world.add(Box::new(light)),
+        }
+        scene
+    }
+
+    #[test]
+    fn test_area_light_sampling_matches_bsdf_sampling() {
+        let ray = looking_down_at(0.3);
+        let average = |scene: &Scene, samples: usize| {
+            let mut total = Vec3::new();
+            for _ in 0..samples {
+                total += ray_color(&ray, scene, 2);
+            }
+            total.x() / samples as f64
+        };
+        let sampled = average(&floor_under_quad_light(true), 20000);
+        let unsampled = average(&floor_under_quad_light(false), 200000);
+        assert!((sampled - unsampled).abs() < 0.02 * unsampled.max(sampled));
+
+        // Looking straight at the light sees its radiance with full weight
+        let scene = floor_under_quad_light(true);
+        let up = Ray {
+            orig: Vec3::new_with_values(0.0, 1.0, 0.0),
+            dir: Vec3::new_with_values(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        assert_eq!(
+            ray_color(&up, &scene, 1),
+            Vec3::new_with_values(4.0, 4.0, 4.0)
+        );
+    }
+
+    #[test]
+    fn test_aovs() {
+        let mut scene = floor_with_light(Arc::new(PointLight::new(
+            Vec3::new_with_values(0.0, 2.0, 0.0),
+            Vec3::new_with_values(4.0, 4.0, 4.0),
+        )));
+        let sphere_material = scene.add_material(Arc::new(Lambertian {
+            albedo: Vec3::new_with_values(0.8, 0.8, 0.8),
+        }));
+        scene.world.add(Box::new(Sphere::new(
+            Vec3::new_with_values(1.5, 0.5, 0.0),
+            0.5,
+            sphere_material,
+        )));
+
+        let sample = ray_color_with_aovs(&looking_down_at(0.5), &scene, 1);
+        assert_eq!(sample.depth, 1.0);
+        assert_eq!(sample.position, Vec3::new_with_values(0.5, 0.0, 0.0));
+        assert_eq!(sample.normal, Vec3::new_with_values(0.0, 1.0, 0.0));
+        assert_eq!(sample.albedo, Vec3::new_with_values(0.5, 0.5, 0.5));
+        assert_eq!(sample.material, Some(0));
+        assert_eq!(sample.object, Some(0));
+        assert_eq!(sample.indirect, Vec3::new());
+        assert!(sample.direct.x() > 0.0);
+
+        // Light reflected off the sphere onto the floor is indirect
+        let mut indirect = 0.0;
+        for _ in 0..1000 {
+            let sample = ray_color_with_aovs(&looking_down_at(0.5), &scene, 10);
+            assert!(
+                (sample.direct.x() - ray_color(&looking_down_at(0.5), &scene, 1).x()).abs() < 1e-12
+            );
+            indirect += sample.indirect.x();
+        }
+        assert!(indirect > 0.0);
+
+        let sphere = ray_color_with_aovs(&looking_down_at(1.5), &scene, 10);
+        assert_eq!(sphere.object, Some(1));
+        assert_eq!(sphere.material, Some(1));
+
+        let miss = ray_color_with_aovs(
+            &Ray {
+                orig: Vec3::new_with_values(0.0, 1.0, 0.0),
+                dir: Vec3::new_with_values(0.0, 1.0, 0.0),
+                time: 0.0,
+            },
+            &scene,
+            10,
+        );
+        assert_eq!(miss, AovSample::new());
+    }
+
+    #[test]
+    fn test_albedo_aov_does_not_depend_on_the_sampled_lobe() {
+        let light = || -> Arc<dyn Light> {
+            Arc::new(PointLight::new(
+                Vec3::new_with_values(0.0, 2.0, 0.0),
+                Vec3::new_with_values(4.0, 4.0, 4.0),
+            ))
+        };
+        let base = Arc::new(Lambertian {
+            albedo: Vec3::new_with_values(0.5, 0.25, 0.125),
+        });
+        let layered = material_floor_with_light(Arc::new(Layered::new(1.5, base)), light());
+        let glass = material_floor_with_light(Arc::new(Dielectric { ir: 1.5 }), light());
+        for _ in 0..100 {
+            assert_eq!(
+                ray_color_with_aovs(&looking_down_at(0.5), &layered, 1).albedo,
+                Vec3::new_with_values(0.5, 0.25, 0.125)
+            );
+            assert_eq!(
+                ray_color_with_aovs(&looking_down_at(0.5), &glass, 1).albedo,
+                Vec3::new_with_values(1.0, 1.0, 1.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_power_heuristic() {
+        assert_eq!(power_heuristic(1.0, 0.0), 1.0);
+        assert_eq!(power_heuristic(1.0, 1.0), 0.5);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::hittables::hittable::{HitRecord, Hittable};
+use crate::hittables::hittable_list::HittableList;
+use crate::lights::area_light::AreaLight;
+use crate::lights::light::Light;
+use crate::materials::material::Material;
+use crate::rays::ray::Ray;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub struct Scene {
+    pub world: HittableList,
+    pub lights: Vec<Arc<dyn Light>>,
+    materials: Vec<Arc<dyn Material>>,
+    // Registry index of each material, keyed by the address it is shared under
+    material_ids: HashMap<usize, usize>,
+}
+
+fn address(material: &Arc<dyn Material>) -> usize {
+    Arc::as_ptr(material) as *const () as usize
+}
+
+impl Scene {
+    pub fn new(world: HittableList) -> Self {
+        Scene {
+            world,
+            lights: vec![],
+            materials: vec![],
+            material_ids: HashMap::new(),
+        }
+    }
+
+    pub fn add_light(&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    // Adds an emissive shape to the world and registers it for light sampling
+    pub fn add_area_light(&mut self, shape: Arc<dyn Hittable>) {
+        self.world.add(Box::new(shape.clone()));
+        self.lights.push(Arc::new(AreaLight::new(shape)));
+    }
+
+    // Numbers a material in the order it was added, so that its id stays the same from
+    // render to render. Adding the same material again keeps its first id.
+    pub fn add_material(&mut self, material: Arc<dyn Material>) -> Arc<dyn Material> {
+        let materials = &mut self.materials;
+        self.material_ids
+            .entry(address(&material))
+            .or_insert_with(|| {
+                materials.push(material.clone());
+                materials.len() - 1
+            });
+        material
+    }
+
+    pub fn materials(&self) -> &[Arc<dyn Material>] {
+        &self.materials
+    }
+
+    pub fn material_id(&self, material: &Arc<dyn Material>) -> Option<usize> {
+        self.material_ids.get(&address(material)).copied()
+    }
+
+    // Intersects the world, filling in the registry index of the material that was hit
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.world.hit(r, t_min, t_max)?;
+        rec.material_id = self.material_id(&rec.mat_ptr);
+        Some(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::sphere::Sphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vectors::vec3::Vec3;
+
+    #[test]
+    fn test_material_ids() {
+        let mut scene = Scene::new(HittableList::new());
+        let lambertian = |albedo: f64| -> Arc<dyn Material> {
+            Arc::new(Lambertian {
+                albedo: Vec3::new_with_values(albedo, albedo, albedo),
+            })
+        };
+        let first = scene.add_material(lambertian(0.5));
+        let second = scene.add_material(lambertian(0.8));
+        scene.add_material(first.clone());
+        let unregistered = lambertian(0.2);
+        assert_eq!(scene.materials().len(), 2);
+        assert_eq!(scene.material_id(&first), Some(0));
+        assert_eq!(scene.material_id(&second), Some(1));
+        assert_eq!(scene.material_id(&unregistered), None);
+
+        scene.world.add(Box::new(Sphere::new(
+            Vec3::new_with_values(0.0, 0.0, -2.0),
+            0.5,
+            second,
+        )));
+        let ray = Ray {
+            orig: Vec3::new(),
+            dir: Vec3::new_with_values(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert_eq!(
+            scene.hit(&ray, 0.001, f64::INFINITY).unwrap().material_id,
+            Some(1)
+        );
+    }
+}
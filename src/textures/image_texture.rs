@@ -0,0 +1,110 @@
+use image::{ImageResult, RgbImage};
+
+use crate::utils::color_utils::srgb_to_linear;
+use crate::vectors::vec3::Vec3;
+
+use super::texture::Texture;
+
+pub struct ImageTexture {
+    image: RgbImage,
+    repeat: bool,
+    srgb: bool,
+}
+
+impl ImageTexture {
+    pub fn new(image: RgbImage) -> Self {
+        ImageTexture {
+            image,
+            repeat: false,
+            srgb: false,
+        }
+    }
+
+    pub fn load(path: &str) -> ImageResult<Self> {
+        Ok(ImageTexture::new(image::open(path)?.to_rgb8()))
+    }
+
+    // Tiles the image outside [0, 1] instead of clamping to the edge pixels
+    pub fn repeating(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+
+    // Decodes the pixels from sRGB, as used by color textures, instead of reading them
+    // as linear values
+    pub fn srgb(mut self) -> Self {
+        self.srgb = true;
+        self
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Vec3 {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return Vec3::new_with_values(0.0, 1.0, 1.0);
+        }
+
+        let (u, v) = match self.repeat {
+            true => (u.rem_euclid(1.0), v.rem_euclid(1.0)),
+            false => (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)),
+        };
+        let v = 1.0 - v;
+        let i = ((u * width as f64) as u32).min(width - 1);
+        let j = ((v * height as f64) as u32).min(height - 1);
+
+        let pixel = self.image.get_pixel(i, j);
+        let color_scale = 1.0 / 255.0;
+        let channel = |value: u8| match self.srgb {
+            true => srgb_to_linear(color_scale * value as f64),
+            false => color_scale * value as f64,
+        };
+        Vec3::new_with_values(channel(pixel[0]), channel(pixel[1]), channel(pixel[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_texture_orientation() {
+        let mut image = RgbImage::new(2, 2);
+        image.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        image.put_pixel(1, 1, image::Rgb([0, 0, 255]));
+        let texture = ImageTexture::new(image);
+        assert_eq!(
+            texture.value(0.0, 1.0, &Vec3::new()),
+            Vec3::new_with_values(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            texture.value(1.0, 0.0, &Vec3::new()),
+            Vec3::new_with_values(0.0, 0.0, 1.0)
+        );
+
+        let repeating = ImageTexture::new(texture.image.clone()).repeating();
+        assert_eq!(
+            repeating.value(-1.0, 2.9, &Vec3::new()),
+            Vec3::new_with_values(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            texture.value(-1.0, 2.9, &Vec3::new()),
+            Vec3::new_with_values(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            repeating.value(1.5, 0.7, &Vec3::new()),
+            Vec3::new_with_values(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_srgb_image_texture() {
+        let mut image = RgbImage::new(1, 1);
+        image.put_pixel(0, 0, image::Rgb([0, 188, 255]));
+        let texture = ImageTexture::new(image).srgb();
+        let value = texture.value(0.5, 0.5, &Vec3::new());
+        assert_eq!(value.x(), 0.0);
+        assert!((value.y() - 0.5).abs() < 0.005);
+        assert!((value.z() - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod image_texture;
+pub mod scaled_texture;
+pub mod solid_color;
+pub mod texture;
@@ -0,0 +1,17 @@
+use crate::vectors::vec3::Vec3;
+
+use super::texture::Texture;
+
+use std::sync::Arc;
+
+// Texture multiplied per channel by a constant factor, as glTF combines factors and maps
+pub struct ScaledTexture {
+    pub texture: Arc<dyn Texture>,
+    pub scale: Vec3,
+}
+
+impl Texture for ScaledTexture {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        self.texture.value(u, v, p) * self.scale
+    }
+}
@@ -0,0 +1,25 @@
+use crate::vectors::vec3::Vec3;
+
+use super::texture::Texture;
+
+pub struct SolidColor {
+    pub color_value: Vec3,
+}
+
+impl SolidColor {
+    pub fn new(color_value: Vec3) -> Self {
+        SolidColor { color_value }
+    }
+
+    pub fn new_with_values(red: f64, green: f64, blue: f64) -> Self {
+        SolidColor {
+            color_value: Vec3::new_with_values(red, green, blue),
+        }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+        self.color_value
+    }
+}
@@ -0,0 +1,5 @@
+use crate::vectors::vec3::Vec3;
+
+pub trait Texture: Sync + Send {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Vec3;
+}
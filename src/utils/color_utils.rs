@@ -17,3 +17,15 @@ pub fn convert_vec3_to_color(vec3: Vec3) -> [u8; 3] {
         (vec3.z() * 255.0) as u8,
     ]
 }
+
+pub fn luminance(vec3: &Vec3) -> f64 {
+    0.2126 * vec3.x() + 0.7152 * vec3.y() + 0.0722 * vec3.z()
+}
+
+// Decodes an sRGB encoded channel in [0, 1] to linear
+pub fn srgb_to_linear(value: f64) -> f64 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
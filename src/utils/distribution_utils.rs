@@ -0,0 +1,132 @@
+// Piecewise constant distributions used to importance sample tabulated functions
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_integral: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].abs() / n as f64;
+        }
+        let func_integral = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate().skip(1) {
+            *value = match func_integral == 0.0 {
+                true => i as f64 / n as f64,
+                false => *value / func_integral,
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            func_integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.func_integral
+    }
+
+    // Returns the sampled position in [0, 1), its density and the bucket it fell in
+    pub fn sample_continuous(&self, xi: f64) -> (f64, f64, usize) {
+        let n = self.count();
+        let offset = self.cdf.partition_point(|&c| c <= xi).clamp(1, n) - 1;
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = match width > 0.0 {
+            true => (xi - self.cdf[offset]) / width,
+            false => 0.0,
+        };
+        let x = ((offset as f64 + du) / n as f64).min(1.0 - f64::EPSILON);
+        (x, self.pdf_at(offset), offset)
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        let n = self.count();
+        self.pdf_at(((x * n as f64) as usize).min(n - 1))
+    }
+
+    fn pdf_at(&self, offset: usize) -> f64 {
+        match self.func_integral == 0.0 {
+            true => 1.0,
+            false => self.func[offset].abs() / self.func_integral,
+        }
+    }
+}
+
+// Rows are sampled from the marginal distribution and columns from the row's conditional
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        let conditional = (0..height)
+            .map(|row| Distribution1D::new(func[row * width..(row + 1) * width].to_vec()))
+            .collect::<Vec<Distribution1D>>();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    // Returns the sampled (u, v) in [0, 1)^2 and its density
+    pub fn sample_continuous(&self, xi_u: f64, xi_v: f64) -> (f64, f64, f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(xi_v);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(xi_u);
+        (u, v, pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let rows = self.marginal.count();
+        let row = ((v * rows as f64) as usize).min(rows - 1);
+        self.conditional[row].pdf(u) * self.marginal.pdf(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_1d() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0]);
+        assert_eq!(distribution.integral(), 2.0);
+
+        let (x, pdf, offset) = distribution.sample_continuous(0.1);
+        assert!((x - 0.2).abs() < 1e-12);
+        assert_eq!(pdf, 0.5);
+        assert_eq!(offset, 0);
+
+        let (x, pdf, offset) = distribution.sample_continuous(0.625);
+        assert!((x - 0.75).abs() < 1e-12);
+        assert_eq!(pdf, 1.5);
+        assert_eq!(offset, 1);
+        assert_eq!(distribution.pdf(0.75), 1.5);
+    }
+
+    #[test]
+    fn test_zero_distribution_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.0; 4]);
+        let (x, pdf, _) = distribution.sample_continuous(0.3);
+        assert!((x - 0.3).abs() < 1e-12);
+        assert_eq!(pdf, 1.0);
+    }
+
+    #[test]
+    fn test_distribution_2d() {
+        let distribution = Distribution2D::new(&[0.0, 0.0, 0.0, 4.0], 2, 2);
+        let (u, v, pdf) = distribution.sample_continuous(0.5, 0.5);
+        assert!(u >= 0.5 && v >= 0.5);
+        assert_eq!(pdf, 4.0);
+        assert_eq!(distribution.pdf(0.25, 0.25), 0.0);
+    }
+}
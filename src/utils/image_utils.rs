@@ -1,11 +1,36 @@
 use image::RgbImage;
 
+use crate::utils::color_utils::{convert_vec3_to_color, gamma_correct};
+use crate::vectors::vec3::Vec3;
+
 pub fn write_image(image_buffer: RgbImage, output_path: &str) {
     image_buffer
         .save(output_path)
         .expect("Could not save image due to ");
 }
 
+// Image of linear colors indexed by y * width + x with y running up from the bottom
+// row, clamped and gamma corrected for display.
+pub fn linear_to_image(width: u32, height: u32, pixels: &[Vec3]) -> RgbImage {
+    let mut image = RgbImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let color = pixels[(y * width + x) as usize];
+            let clamped = Vec3::new_with_values(
+                color.x().clamp(0.0, 1.0),
+                color.y().clamp(0.0, 1.0),
+                color.z().clamp(0.0, 1.0),
+            );
+            image.put_pixel(
+                x,
+                height - 1 - y,
+                image::Rgb(convert_vec3_to_color(gamma_correct(&clamped, 0.5))),
+            );
+        }
+    }
+    image
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -13,11 +38,12 @@ mod tests {
     use std::path::Path;
     #[test]
     fn test_write_image() {
-        let img: RgbImage = RgbImage::new(256 as u32, 256 as u32);
+        let img: RgbImage = RgbImage::new(256_u32, 256_u32);
         let path_string = "test.png";
         let path = Path::new(path_string);
         write_image(img, path_string);
-        assert_eq!(path.exists(), true);
-        remove_file(path_string).expect(&format!("Could not delete test image at {}", path_string));
+        assert!(path.exists());
+        remove_file(path_string)
+            .unwrap_or_else(|_| panic!("Could not delete test image at {}", path_string));
     }
 }
@@ -0,0 +1,150 @@
+// Affine transforms as 4x4 matrices stored column by column, the layout used by both
+// glTF and pbrt scene files.
+
+use crate::utils::vec3_utils::{cross, dot, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+pub type Matrix = [[f64; 4]; 4];
+
+pub const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+pub fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 4]; 4];
+    for (column, product_column) in product.iter_mut().enumerate() {
+        for (row, value) in product_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    product
+}
+
+pub fn translation(offset: &Vec3) -> Matrix {
+    let mut m = IDENTITY;
+    m[3] = [offset.x(), offset.y(), offset.z(), 1.0];
+    m
+}
+
+pub fn scaling(scale: &Vec3) -> Matrix {
+    let mut m = IDENTITY;
+    for axis in 0..3 {
+        m[axis][axis] = scale[axis];
+    }
+    m
+}
+
+// Rotation by angle degrees about axis, counterclockwise when looking down the axis
+pub fn rotation(angle: f64, axis: &Vec3) -> Matrix {
+    let a = unit_vector(*axis);
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let mut m = IDENTITY;
+    for column in 0..3 {
+        let mut e = Vec3::new();
+        e[column] = 1.0;
+        // Rodrigues' formula applied to each basis vector
+        let rotated = cos * e + sin * cross(&a, &e) + (1.0 - cos) * dot(&a, &e) * a;
+        m[column] = [rotated.x(), rotated.y(), rotated.z(), 0.0];
+    }
+    m
+}
+
+pub fn axes(m: &Matrix) -> [Vec3; 3] {
+    [0, 1, 2].map(|column| Vec3::new_with_values(m[column][0], m[column][1], m[column][2]))
+}
+
+pub fn determinant(m: &Matrix) -> f64 {
+    let [a0, a1, a2] = axes(m);
+    dot(&a0, &cross(&a1, &a2))
+}
+
+// Inverse of an affine transform, None for singular ones
+pub fn inverse(m: &Matrix) -> Option<Matrix> {
+    let det = determinant(m);
+    if det == 0.0 {
+        return None;
+    }
+    let [a0, a1, a2] = axes(m);
+    // Rows of the inverse of the linear part are the cofactor columns over det
+    let rows = [
+        cross(&a1, &a2) / det,
+        cross(&a2, &a0) / det,
+        cross(&a0, &a1) / det,
+    ];
+    let offset = Vec3::new_with_values(m[3][0], m[3][1], m[3][2]);
+    let mut inverted = IDENTITY;
+    for (row, r) in rows.iter().enumerate() {
+        for column in 0..3 {
+            inverted[column][row] = r[column];
+        }
+        inverted[3][row] = -dot(r, &offset);
+    }
+    Some(inverted)
+}
+
+pub fn transform_vector(m: &Matrix, v: &Vec3) -> Vec3 {
+    let mut result = Vec3::new();
+    for row in 0..3 {
+        result[row] = m[0][row] * v.x() + m[1][row] * v.y() + m[2][row] * v.z();
+    }
+    result
+}
+
+pub fn transform_point(m: &Matrix, p: &Vec3) -> Vec3 {
+    transform_vector(m, p) + Vec3::new_with_values(m[3][0], m[3][1], m[3][2])
+}
+
+// Normals transform with the inverse transpose, which is the cofactor matrix up to the
+// determinant. Its sign is kept so that mirroring transforms don't flip normals inwards.
+pub fn transform_normal(m: &Matrix, n: &Vec3) -> Vec3 {
+    let [a0, a1, a2] = axes(m);
+    let normal = n.x() * cross(&a1, &a2) + n.y() * cross(&a2, &a0) + n.z() * cross(&a0, &a1);
+    match determinant(m) < 0.0 {
+        true => -unit_vector(normal),
+        false => unit_vector(normal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_normal() {
+        // Non uniform scale keeps normals perpendicular to the surface
+        let scale = scaling(&Vec3::new_with_values(4.0, 1.0, 1.0));
+        let tangent = transform_vector(&scale, &Vec3::new_with_values(1.0, -1.0, 0.0));
+        let normal = transform_normal(&scale, &unit_vector(Vec3::new_with_values(1.0, 1.0, 0.0)));
+        assert!(dot(&tangent, &normal).abs() < 1e-12);
+
+        let mirror = scaling(&Vec3::new_with_values(1.0, 1.0, -1.0));
+        let normal = transform_normal(&mirror, &Vec3::new_with_values(0.0, 0.0, 1.0));
+        assert_eq!(normal, Vec3::new_with_values(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_inverse_and_rotation() {
+        let m = multiply(
+            &translation(&Vec3::new_with_values(1.0, -2.0, 3.0)),
+            &multiply(
+                &rotation(30.0, &Vec3::new_with_values(1.0, 1.0, 0.0)),
+                &scaling(&Vec3::new_with_values(2.0, 0.5, -1.0)),
+            ),
+        );
+        let product = multiply(&m, &inverse(&m).unwrap());
+        for (column, values) in product.iter().enumerate() {
+            for (row, value) in values.iter().enumerate() {
+                assert!((value - IDENTITY[column][row]).abs() < 1e-12);
+            }
+        }
+
+        // A quarter turn about z takes x to y
+        let quarter = rotation(90.0, &Vec3::new_with_values(0.0, 0.0, 1.0));
+        let rotated = transform_vector(&quarter, &Vec3::new_with_values(1.0, 0.0, 0.0));
+        assert!((rotated - Vec3::new_with_values(0.0, 1.0, 0.0)).length() < 1e-12);
+        assert!(inverse(&scaling(&Vec3::new())).is_none());
+    }
+}
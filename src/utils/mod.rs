@@ -1,4 +1,7 @@
 pub mod color_utils;
+pub mod distribution_utils;
 pub mod image_utils;
+pub mod matrix_utils;
+pub mod polynomial_utils;
 pub mod random_number_utils;
 pub mod vec3_utils;
@@ -0,0 +1,159 @@
+// Real roots of low degree polynomials, returned in increasing order. Cubics and
+// quartics are solved by bracketing: the roots of the derivative split the real line
+// into monotonic pieces, each holding at most one root. This stays accurate for the
+// nearly touching roots of grazing rays, where the closed forms lose most of their digits.
+
+const MAX_ITERATIONS: usize = 100;
+
+// Coefficients from the highest degree down
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().fold(0.0, |value, c| value * x + c)
+}
+
+fn derivative(coefficients: &[f64]) -> Vec<f64> {
+    let degree = coefficients.len() - 1;
+    coefficients[..degree]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| c * (degree - i) as f64)
+        .collect()
+}
+
+// Newton iteration that falls back to bisection whenever it leaves the bracket
+fn bracketed_root(coefficients: &[f64], mut lo: f64, mut hi: f64) -> f64 {
+    let slope = derivative(coefficients);
+    let lo_sign = evaluate(coefficients, lo).signum();
+    let mut x = 0.5 * (lo + hi);
+    for _ in 0..MAX_ITERATIONS {
+        let f = evaluate(coefficients, x);
+        if f == 0.0 {
+            return x;
+        }
+        match f.signum() == lo_sign {
+            true => lo = x,
+            false => hi = x,
+        }
+        let df = evaluate(&slope, x);
+        let newton = x - f / df;
+        let next = match df != 0.0 && lo < newton && newton < hi {
+            true => newton,
+            false => 0.5 * (lo + hi),
+        };
+        if (next - x).abs() <= 1e-14 * x.abs().max(1.0) {
+            return next;
+        }
+        x = next;
+    }
+    x
+}
+
+fn solve_by_bracketing(coefficients: &[f64]) -> Vec<f64> {
+    let leading = coefficients[0];
+    let normalized: Vec<f64> = coefficients.iter().map(|c| c / leading).collect();
+    // Cauchy's bound, every root lies strictly inside it
+    let bound = 1.0 + normalized[1..].iter().fold(0.0, |m: f64, c| m.max(c.abs()));
+
+    let slope = derivative(&normalized);
+    let critical_points = match slope.len() {
+        3 => solve_quadratic(slope[0], slope[1], slope[2]),
+        _ => solve_cubic(slope[0], slope[1], slope[2], slope[3]),
+    };
+    let mut points = vec![-bound];
+    points.extend(critical_points.into_iter().filter(|x| x.abs() < bound));
+    points.push(bound);
+
+    let mut roots: Vec<f64> = vec![];
+    for pair in points.windows(2) {
+        let (lo, hi) = (pair[0], pair[1]);
+        let (f_lo, f_hi) = (evaluate(&normalized, lo), evaluate(&normalized, hi));
+        let root = if f_lo == 0.0 {
+            lo
+        } else if f_hi != 0.0 && f_lo.signum() != f_hi.signum() {
+            bracketed_root(&normalized, lo, hi)
+        } else {
+            continue;
+        };
+        if roots.last() != Some(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return match b == 0.0 {
+            true => vec![],
+            false => vec![-c / b],
+        };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    // Avoids cancellation between -b and the square root
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let mut roots = match q == 0.0 {
+        true => vec![0.0, 0.0],
+        false => vec![q / a, c / q],
+    };
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    match a == 0.0 {
+        true => solve_quadratic(b, c, d),
+        false => solve_by_bracketing(&[a, b, c, d]),
+    }
+}
+
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    match a == 0.0 {
+        true => solve_cubic(b, c, d, e),
+        false => solve_by_bracketing(&[a, b, c, d, e]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(roots: &[f64], expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "{:?}", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9, "{:?}", roots);
+        }
+    }
+
+    #[test]
+    fn test_solve_quadratic() {
+        // (x - 1)(x - 3)
+        assert_roots(&solve_quadratic(1.0, -4.0, 3.0), &[1.0, 3.0]);
+        assert_roots(&solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(&solve_quadratic(0.0, 2.0, -4.0), &[2.0]);
+    }
+
+    #[test]
+    fn test_solve_cubic() {
+        // (x + 1)(x - 2)(x - 4)
+        assert_roots(&solve_cubic(1.0, -5.0, 2.0, 8.0), &[-1.0, 2.0, 4.0]);
+        // (x - 1)(x^2 + 1)
+        assert_roots(&solve_cubic(2.0, -2.0, 2.0, -2.0), &[1.0]);
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x + 2)(x + 1)(x - 1)(x - 3)
+        assert_roots(
+            &solve_quartic(1.0, -1.0, -7.0, 1.0, 6.0),
+            &[-2.0, -1.0, 1.0, 3.0],
+        );
+        // (x^2 - 4)(x^2 + 1)
+        assert_roots(&solve_quartic(1.0, 0.0, -3.0, 0.0, -4.0), &[-2.0, 2.0]);
+        assert_roots(&solve_quartic(1.0, 0.0, 1.0, 0.0, 1.0), &[]);
+        // (x - 2.3)(x - 2.7)(x^2 - 5x + 6.26), two close roots and a complex pair
+        let roots = solve_quartic(1.0, -10.0, 37.47, -62.35, 38.8746);
+        assert_roots(&roots, &[2.3, 2.7]);
+    }
+}
@@ -17,6 +17,6 @@ mod tests {
     #[test]
     fn test_random_f64_range() {
         let number = random_f64_range(0.0, 0.1);
-        assert!(number >= 0.0 && number < 0.1);
+        assert!((0.0..0.1).contains(&number));
     }
 }
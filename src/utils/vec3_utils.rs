@@ -38,6 +38,14 @@ pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     r_out_perp + r_out_parallel
 }
 
+pub fn perpendicular(n: &Vec3) -> Vec3 {
+    let helper = match n.x().abs() > 0.9 {
+        true => Vec3::new_with_values(0.0, 1.0, 0.0),
+        false => Vec3::new_with_values(1.0, 0.0, 0.0),
+    };
+    unit_vector(cross(n, &helper))
+}
+
 pub fn random_in_unit_disk() -> Vec3 {
     loop {
         let p = Vec3 {
@@ -103,4 +111,17 @@ mod tests {
         };
         debug_assert_eq!(unit_vector(test_vector), test_vector / 3.0f64.sqrt());
     }
+
+    #[test]
+    fn test_perpendicular() {
+        for n in [
+            Vec3::new_with_values(1.0, 0.0, 0.0),
+            Vec3::new_with_values(0.0, 1.0, 0.0),
+            unit_vector(Vec3::new_with_values(1.0, 2.0, 3.0)),
+        ] {
+            let t = perpendicular(&n);
+            assert!(dot(&t, &n).abs() < 1e-12);
+            assert!((t.length() - 1.0).abs() < 1e-12);
+        }
+    }
 }
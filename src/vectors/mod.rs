@@ -1 +1,2 @@
+pub mod onb;
 pub mod vec3;
@@ -0,0 +1,44 @@
+use crate::utils::vec3_utils::{cross, dot, perpendicular, unit_vector};
+use crate::vectors::vec3::Vec3;
+
+// Orthonormal basis with w as the main axis, used to move rays into the local frame of
+// primitives that are defined around an axis
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(w: &Vec3) -> Self {
+        let w = unit_vector(*w);
+        let u = perpendicular(&w);
+        let v = cross(&w, &u);
+        Onb { u, v, w }
+    }
+
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new_with_values(dot(a, &self.u), dot(a, &self.v), dot(a, &self.w))
+    }
+
+    pub fn to_world(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_onb_round_trip() {
+        let onb = Onb::from_w(&Vec3::new_with_values(1.0, 2.0, -0.5));
+        assert!(dot(&onb.u, &onb.v).abs() < 1e-12);
+        assert!(dot(&onb.v, &onb.w).abs() < 1e-12);
+        assert!((cross(&onb.u, &onb.v) - onb.w).length() < 1e-12);
+
+        let a = Vec3::new_with_values(0.3, -2.0, 4.0);
+        assert!((onb.to_world(&onb.to_local(&a)) - a).length() < 1e-12);
+    }
+}
@@ -56,7 +56,7 @@ impl Vec3 {
 
     pub fn random_in_unit_sphere() -> Self {
         loop {
-            let random_vec = Self::random_vec3();
+            let random_vec = Self::random_vec3_min_max(-1.0, 1.0);
             if random_vec.length() <= 1.0 {
                 return random_vec;
             }
@@ -73,6 +73,12 @@ impl Vec3 {
     }
 }
 
+impl Default for Vec3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Add for Vec3 {
     type Output = Self;
 
@@ -274,19 +280,19 @@ mod tests {
             vector3: [1.0, 2.0, 3.0],
         };
         assert_eq!(
-            test_vector1.clone() * test_vector2,
+            test_vector1 * test_vector2,
             Vec3 {
                 vector3: [1.0, 4.0, 9.0]
             }
         );
         assert_eq!(
-            test_vector1.clone() * 2.0,
+            test_vector1 * 2.0,
             Vec3 {
                 vector3: [2.0, 4.0, 6.0]
             }
         );
         assert_eq!(
-            2.0 * test_vector1.clone(),
+            2.0 * test_vector1,
             Vec3 {
                 vector3: [2.0, 4.0, 6.0]
             }
@@ -315,7 +321,7 @@ mod tests {
             vector3: [1.0, 2.0, 3.0],
         };
         assert_eq!(
-            test_vector1.clone() / 2.0,
+            test_vector1 / 2.0,
             Vec3 {
                 vector3: [0.5, 1.0, 1.5]
             }
@@ -412,4 +418,28 @@ mod tests {
         assert!(test_vector1[1] >= 0.0 && test_vector1[1] < 0.1);
         assert!(test_vector1[2] >= 0.0 && test_vector1[2] < 0.1);
     }
+
+    #[test]
+    fn test_random_in_unit_sphere() {
+        // Uniform over the ball: centered, every octant equally likely and E[r^2] = 3/5
+        let samples = 20000;
+        let mut sum = Vec3::new();
+        let mut length_squared = 0.0;
+        let mut octants = [0; 8];
+        for _ in 0..samples {
+            let v = Vec3::random_in_unit_sphere();
+            assert!(v.length() <= 1.0);
+            sum += v;
+            length_squared += v.length_squared();
+            let octant =
+                (v.x() > 0.0) as usize + 2 * (v.y() > 0.0) as usize + 4 * (v.z() > 0.0) as usize;
+            octants[octant] += 1;
+        }
+        let mean = sum / samples as f64;
+        assert!(mean.length() < 0.02);
+        assert!((length_squared / samples as f64 - 0.6).abs() < 0.01);
+        for count in octants {
+            assert!((count as f64 / samples as f64 - 0.125).abs() < 0.02);
+        }
+    }
 }